@@ -0,0 +1,519 @@
+use crate::db::DB_CONNECTION;
+use crate::metadata_cache;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::thread;
+
+// Shows with an open-ended episode count get estimated broadcasts this far ahead
+const OPEN_ENDED_LOOKAHEAD_WEEKS: i64 = 8;
+pub(crate) const DEFAULT_CALENDAR_STATUSES: [&str; 2] = ["watching", "plan_to_watch"];
+
+// Broadcast times from Jikan are given in Japan time, which has no DST
+pub(crate) fn jst() -> FixedOffset {
+    FixedOffset::east_opt(9 * 3600).unwrap()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AiringStatus {
+    NotYetAired,
+    Airing,
+    Finished,
+    Unknown,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleEntry {
+    pub anime_id: i64,
+    pub title: String,
+    pub image_url: String,
+    pub status: String,
+    pub episode: i32,
+    // RFC 3339 in the user's local offset at broadcast time (DST aware)
+    pub airs_at: String,
+    // "HH:MM" local and Japan time, for display
+    pub local_time: String,
+    pub jst_time: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleDay {
+    // YYYY-MM-DD in local time
+    pub date: String,
+    pub weekday: String,
+    pub entries: Vec<ScheduleEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeeklySchedule {
+    pub week_start: String,
+    pub days: Vec<ScheduleDay>,
+    // List entries without any broadcast information
+    pub unscheduled: Vec<UnscheduledEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnscheduledEntry {
+    pub anime_id: i64,
+    pub title: String,
+    pub status: String,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    // One row per episode with its broadcast time in UTC seconds
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS airing_schedule (
+            anime_id INTEGER NOT NULL,
+            episode INTEGER NOT NULL,
+            airs_at INTEGER NOT NULL,
+            broadcast_day TEXT,
+            broadcast_time_jst TEXT,
+            PRIMARY KEY (anime_id, episode)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_airing_schedule_time ON airing_schedule(airs_at)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn airing_status(data: &Value) -> AiringStatus {
+    match data.get("status").and_then(Value::as_str) {
+        Some("Not yet aired") => AiringStatus::NotYetAired,
+        Some("Currently Airing") => AiringStatus::Airing,
+        Some("Finished Airing") => AiringStatus::Finished,
+        _ => AiringStatus::Unknown,
+    }
+}
+
+pub(crate) fn total_episodes(data: &Value) -> Option<i32> {
+    data.get("episodes")
+        .and_then(Value::as_i64)
+        .map(|episodes| episodes as i32)
+        .filter(|episodes| *episodes > 0)
+}
+
+fn broadcast_field<'a>(data: &'a Value, key: &str) -> Option<&'a str> {
+    data.get("broadcast")?.get(key)?.as_str()
+}
+
+// Jikan uses plural day names ("Mondays")
+fn broadcast_weekday(data: &Value) -> Option<Weekday> {
+    let day = broadcast_field(data, "day")?.to_lowercase();
+    day.trim_end_matches('s').parse().ok()
+}
+
+fn broadcast_time(data: &Value) -> Option<NaiveTime> {
+    broadcast_field(data, "time").and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok())
+}
+
+// First broadcast: the premiere date from `aired.from` at the weekly broadcast time
+pub(crate) fn premiere(data: &Value) -> Option<DateTime<Utc>> {
+    let from = data.get("aired")?.get("from")?.as_str()?;
+    let date = NaiveDate::parse_from_str(from.get(..10)?, "%Y-%m-%d").ok()?;
+    let time = broadcast_time(data).unwrap_or(NaiveTime::MIN);
+
+    jst()
+        .from_local_datetime(&date.and_time(time))
+        .single()
+        .map(|start| start.with_timezone(&Utc))
+}
+
+// Estimated broadcast of every episode. Episode 1 airs at the premiere; later
+// episodes follow weekly on the broadcast day, which can differ from the
+// premiere when a show starts with a special double episode or moves slot.
+pub(crate) fn episode_times(data: &Value, now: DateTime<Utc>) -> Vec<(i32, DateTime<Utc>)> {
+    let premiere = match premiere(data) {
+        Some(premiere) => premiere,
+        None => return Vec::new(),
+    };
+
+    // `anchor` is the first weekly slot on or after the premiere. When the
+    // premiere is off-slot, episode 2 already airs in that first slot.
+    let premiere_jst = premiere.with_timezone(&jst());
+    let (anchor, anchor_episode) = match broadcast_weekday(data) {
+        Some(weekday) => {
+            let shift = (7 + weekday.num_days_from_monday() as i64
+                - premiere_jst.weekday().num_days_from_monday() as i64)
+                % 7;
+            if shift > 0 {
+                (premiere + Duration::days(shift), 2)
+            } else {
+                (premiere, 1)
+            }
+        }
+        None => (premiere, 1),
+    };
+
+    let last = match total_episodes(data) {
+        Some(total) => total,
+        None => match airing_status(data) {
+            AiringStatus::Finished => return vec![(1, premiere)],
+            _ => {
+                let horizon = now + Duration::weeks(OPEN_ENDED_LOOKAHEAD_WEEKS);
+                ((horizon - anchor).num_weeks() as i32 + anchor_episode).max(1)
+            }
+        },
+    };
+
+    (1..=last)
+        .map(|episode| {
+            let time = if episode == 1 {
+                premiere
+            } else {
+                anchor + Duration::weeks((episode - anchor_episode) as i64)
+            };
+            (episode, time)
+        })
+        .collect()
+}
+
+// Estimated broadcast of `episode` straight from the metadata
+pub(crate) fn estimated_air_time(data: &Value, episode: i32) -> Option<DateTime<Utc>> {
+    if episode < 1 {
+        return None;
+    }
+    episode_times(data, Utc::now())
+        .into_iter()
+        .find(|(number, _)| *number == episode)
+        .map(|(_, time)| time)
+}
+
+// Episodes broadcast by `now`, or None when the metadata is too thin to tell
+pub(crate) fn aired_episodes(data: &Value, now: DateTime<Utc>) -> Option<i32> {
+    let total = total_episodes(data);
+    match airing_status(data) {
+        AiringStatus::NotYetAired => Some(0),
+        AiringStatus::Finished => total,
+        AiringStatus::Airing | AiringStatus::Unknown => {
+            let times = episode_times(data, now);
+            if times.is_empty() {
+                return None;
+            }
+            Some(times.iter().filter(|(_, time)| *time <= now).count() as i32)
+        }
+    }
+}
+
+// Rebuild the schedule rows for one show from its cached metadata
+pub(crate) fn store_schedule(conn: &Connection, anime_id: i64, data: &Value) -> Result<(), String> {
+    conn.execute("DELETE FROM airing_schedule WHERE anime_id = ?1", params![anime_id])
+        .map_err(|e| format!("Failed to clear airing schedule: {}", e))?;
+
+    let day = broadcast_field(data, "day");
+    let time = broadcast_field(data, "time");
+    for (episode, airs_at) in episode_times(data, Utc::now()) {
+        conn.execute(
+            "INSERT INTO airing_schedule (anime_id, episode, airs_at, broadcast_day, broadcast_time_jst)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![anime_id, episode, airs_at.timestamp(), day, time],
+        )
+        .map_err(|e| format!("Failed to store airing schedule: {}", e))?;
+    }
+    Ok(())
+}
+
+// A show whose every episode has aired keeps the same schedule forever
+fn schedule_is_final(data: &Value, now: DateTime<Utc>) -> bool {
+    match airing_status(data) {
+        AiringStatus::Finished => true,
+        AiringStatus::NotYetAired => false,
+        AiringStatus::Airing | AiringStatus::Unknown => total_episodes(data)
+            .map(|total| aired_episodes(data, now) == Some(total))
+            .unwrap_or(false),
+    }
+}
+
+// Rebuild every schedule that can still change in one transaction, skipping
+// shows that have fully aired and already have their rows. Returns the number
+// of shows rebuilt.
+pub(crate) fn rebuild_all(conn: &mut Connection) -> Result<usize, String> {
+    let now = Utc::now();
+    let entries = metadata_cache::load_all(conn)?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut rebuilt = 0;
+    for (anime_id, data) in &entries {
+        if schedule_is_final(data, now) && aired_count(&tx, *anime_id, now)?.is_some() {
+            continue;
+        }
+        store_schedule(&tx, *anime_id, data)?;
+        rebuilt += 1;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(rebuilt)
+}
+
+// Extend open-ended schedules and pick up metadata cached before the table
+// existed, off the startup path since it walks the whole metadata cache
+pub fn start_rebuild() {
+    thread::spawn(|| {
+        let mut conn = DB_CONNECTION.lock().unwrap();
+        if let Err(e) = rebuild_all(&mut conn) {
+            eprintln!("Failed to rebuild airing schedule: {}", e);
+        }
+    });
+}
+
+fn from_timestamp(seconds: i64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(seconds, 0)
+}
+
+// Highest episode broadcast by `now`, None if the show has no schedule
+pub(crate) fn aired_count(conn: &Connection, anime_id: i64, now: DateTime<Utc>) -> Result<Option<i32>, String> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0, COALESCE(MAX(CASE WHEN airs_at <= ?2 THEN episode END), 0)
+         FROM airing_schedule WHERE anime_id = ?1",
+        params![anime_id, now.timestamp()],
+        |row| Ok((row.get::<_, bool>(0)?, row.get::<_, i32>(1)?)),
+    )
+    .map(|(scheduled, aired)| if scheduled { Some(aired) } else { None })
+    .map_err(|e| format!("Failed to read airing schedule: {}", e))
+}
+
+pub(crate) fn next_airing(
+    conn: &Connection,
+    anime_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Option<(i32, DateTime<Utc>)>, String> {
+    conn.query_row(
+        "SELECT episode, airs_at FROM airing_schedule WHERE anime_id = ?1 AND airs_at > ?2
+         ORDER BY airs_at LIMIT 1",
+        params![anime_id, now.timestamp()],
+        |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i64>(1)?)),
+    )
+    .optional()
+    .map(|next| next.and_then(|(episode, airs_at)| Some((episode, from_timestamp(airs_at)?))))
+    .map_err(|e| format!("Failed to read airing schedule: {}", e))
+}
+
+// Broadcasts of list entries with the given statuses between two instants
+pub(crate) fn episodes_between(
+    conn: &Connection,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    statuses: &[String],
+) -> Result<Vec<ScheduleEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT s.anime_id, u.title, u.image_url, u.status, s.episode, s.airs_at
+             FROM airing_schedule s JOIN user_anime u ON u.anime_id = s.anime_id
+             WHERE s.airs_at >= ?1 AND s.airs_at < ?2
+             ORDER BY s.airs_at, u.title",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![from.timestamp(), to.timestamp()], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i32>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let (anime_id, title, image_url, status, episode, airs_at) =
+            row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        if !statuses.contains(&status) {
+            continue;
+        }
+        let airs_at = match from_timestamp(airs_at) {
+            Some(airs_at) => airs_at,
+            None => continue,
+        };
+
+        // Converting per instant picks the right offset on either side of a DST change
+        let local = airs_at.with_timezone(&Local);
+        entries.push(ScheduleEntry {
+            anime_id,
+            title,
+            image_url,
+            status,
+            episode,
+            airs_at: local.to_rfc3339(),
+            local_time: local.format("%H:%M").to_string(),
+            jst_time: airs_at.with_timezone(&jst()).format("%H:%M").to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+fn local_midnight(date: NaiveDate) -> Result<DateTime<Utc>, String> {
+    // earliest() covers days where midnight falls into a DST gap or overlap
+    Local
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .or_else(|| {
+            Local
+                .from_local_datetime(&date.and_time(NaiveTime::from_hms_opt(1, 0, 0).unwrap()))
+                .earliest()
+        })
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("Cannot resolve local midnight for {}", date))
+}
+
+#[tauri::command]
+pub fn refresh_airing_schedule() -> Result<usize, String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    rebuild_all(&mut conn)
+}
+
+// Weekly calendar in local time. `week_start` is a YYYY-MM-DD date, defaulting to
+// this week's Monday; `statuses` defaults to watching and planned entries.
+#[tauri::command]
+pub fn get_weekly_schedule(
+    week_start: Option<String>,
+    statuses: Option<Vec<String>>,
+) -> Result<WeeklySchedule, String> {
+    let start = match week_start {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid week start {}: {}", date, e))?,
+        None => {
+            let today = Local::now().date_naive();
+            today - Duration::days(today.weekday().num_days_from_monday() as i64)
+        }
+    };
+    let statuses = statuses.unwrap_or_else(|| {
+        DEFAULT_CALENDAR_STATUSES.iter().map(|s| s.to_string()).collect()
+    });
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut days = Vec::with_capacity(7);
+    for offset in 0..7 {
+        let date = start + Duration::days(offset);
+        let entries = episodes_between(
+            &conn,
+            local_midnight(date)?,
+            local_midnight(date + Duration::days(1))?,
+            &statuses,
+        )?;
+        days.push(ScheduleDay {
+            date: date.format("%Y-%m-%d").to_string(),
+            weekday: date.format("%A").to_string(),
+            entries,
+        });
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT anime_id, title, status FROM user_anime
+             WHERE anime_id NOT IN (SELECT anime_id FROM airing_schedule)
+             ORDER BY title",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let unscheduled = stmt
+        .query_map([], |row| {
+            Ok(UnscheduledEntry {
+                anime_id: row.get(0)?,
+                title: row.get(1)?,
+                status: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))?
+        .into_iter()
+        .filter(|entry| statuses.contains(&entry.status))
+        .collect();
+
+    Ok(WeeklySchedule {
+        week_start: start.format("%Y-%m-%d").to_string(),
+        days,
+        unscheduled,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn show(from: &str, day: &str, episodes: i64) -> Value {
+        json!({
+            "status": "Currently Airing",
+            "episodes": episodes,
+            "aired": { "from": from },
+            "broadcast": { "day": day, "time": "23:30" },
+        })
+    }
+
+    fn jst_at(date: &str) -> DateTime<Utc> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        jst()
+            .from_local_datetime(&date.and_hms_opt(23, 30, 0).unwrap())
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn on_slot_premiere_airs_weekly() {
+        // 2024-04-06 is a Saturday
+        let times = episode_times(&show("2024-04-06T00:00:00+00:00", "Saturdays", 3), Utc::now());
+        assert_eq!(
+            times,
+            vec![(1, jst_at("2024-04-06")), (2, jst_at("2024-04-13")), (3, jst_at("2024-04-20"))]
+        );
+    }
+
+    #[test]
+    fn sunday_premiere_with_saturday_slot() {
+        // Premieres on Sunday 2024-04-07, then airs in the Saturday slot from the following week
+        let times = episode_times(&show("2024-04-07T00:00:00+00:00", "Saturdays", 3), Utc::now());
+        assert_eq!(
+            times,
+            vec![(1, jst_at("2024-04-07")), (2, jst_at("2024-04-13")), (3, jst_at("2024-04-20"))]
+        );
+    }
+
+    #[test]
+    fn rebuild_skips_fully_aired_shows_with_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        metadata_cache::init_tables(&conn).unwrap();
+        init_tables(&conn).unwrap();
+
+        let mut finished = show("2020-01-04T00:00:00+00:00", "Saturdays", 2);
+        finished["status"] = json!("Finished Airing");
+        let airing = show("2020-01-04T00:00:00+00:00", "Saturdays", 2000);
+        for (anime_id, data) in [(1, &finished), (2, &finished), (3, &airing)] {
+            conn.execute(
+                "INSERT INTO anime_metadata (anime_id, title, data, fetched_at) VALUES (?1, '', ?2, 0)",
+                params![anime_id, data.to_string()],
+            )
+            .unwrap();
+        }
+        // Show 1 already has its rows; show 2 was cached before the table existed
+        conn.execute(
+            "INSERT INTO airing_schedule (anime_id, episode, airs_at) VALUES (1, 1, 0)",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(rebuild_all(&mut conn).unwrap(), 2);
+        let rows = |anime_id: i64| -> i64 {
+            conn.query_row(
+                "SELECT COUNT(*) FROM airing_schedule WHERE anime_id = ?1",
+                [anime_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(rows(1), 1);
+        assert_eq!(rows(2), 2);
+        assert_eq!(rows(3), 2000);
+    }
+}
@@ -1,5 +1,25 @@
-use chrono::Datelike;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const JIKAN_BASE_URL: &str = "https://api.jikan.moe/v4";
+// Jikan allows 3 requests per second; stay well below it like the frontend does
+pub(crate) const JIKAN_REQUEST_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+// GET a Jikan v4 path such as "/anime/1/full" and parse the JSON body
+pub(crate) async fn jikan_get(path: &str) -> Result<Value, String> {
+    let url = format!("{}{}", JIKAN_BASE_URL, path);
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch {}: HTTP {}", url, response.status()));
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response from {}: {}", url, e))?;
+    serde_json::from_str(&body).map_err(|e| format!("Failed to parse response from {}: {}", url, e))
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnimeSearchResult {
@@ -38,12 +58,6 @@ pub struct RelatedAnime {
     relation_type: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Season {
-    year: i32,
-    season: String,
-}
-
 // Mock search function, replace with actual API integration
 #[tauri::command]
 pub fn search_anime(query: String) -> Result<Vec<AnimeSearchResult>, String> {
@@ -80,43 +94,3 @@ pub fn get_anime_details(id: i64) -> Result<AnimeDetail, String> {
         studios: vec!["Studio Example".to_string()],
     })
 }
-
-#[tauri::command]
-pub fn get_seasonal_anime(
-    year: Option<i32>,
-    season: Option<String>,
-) -> Result<Vec<AnimeSearchResult>, String> {
-    // This is a placeholder - in a real app you would call an external API
-    let year_val = year.unwrap_or_else(|| {
-        let now = chrono::Local::now();
-        now.year()
-    });
-
-    let season_val = season.unwrap_or_else(|| "winter".to_string());
-
-    Ok(vec![AnimeSearchResult {
-        id: 101,
-        title: format!("Seasonal Anime for {}: {}", season_val, year_val),
-        image_url: "https://example.com/placeholder.jpg".to_string(),
-        media_type: "TV".to_string(),
-        score: Some(8.1),
-        episodes: Some(12),
-        synopsis: Some("This is a placeholder seasonal anime.".to_string()),
-        airing: true,
-    }])
-}
-
-#[tauri::command]
-pub fn get_anime_recommendations(anime_id: i64) -> Result<Vec<AnimeSearchResult>, String> {
-    // This is a placeholder - in a real app you would call an external API
-    Ok(vec![AnimeSearchResult {
-        id: 201,
-        title: format!("Recommendation based on anime ID: {}", anime_id),
-        image_url: "https://example.com/placeholder.jpg".to_string(),
-        media_type: "TV".to_string(),
-        score: Some(7.9),
-        episodes: Some(13),
-        synopsis: Some("This is a placeholder recommendation.".to_string()),
-        airing: false,
-    }])
-}
@@ -0,0 +1,640 @@
+use crate::backup_crypto;
+use crate::backup_store;
+use crate::db::{self, DB_CONNECTION};
+use crate::filesystem::{self, add_dir_to_zip, get_location_dir, StorageLocation};
+use crate::sandbox;
+use chrono::{Datelike, Local, NaiveDateTime, TimeZone};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::Manager;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+const BACKUP_SETTINGS_KEY: &str = "backup_settings";
+const LAST_BACKUP_KEY: &str = "backup_last_run";
+const BACKUP_FILE_PREFIX: &str = "anitrack_backup_";
+pub(crate) const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+// The password is only ever held in memory; the UI unlocks it once per session
+// and encrypted scheduled backups are skipped until it does
+static BACKUP_PASSWORD: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+// How often the scheduler wakes up to check whether a backup is due
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    pub keep_monthly: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub interval_hours: u64,
+    pub backup_on_exit: bool,
+    pub location: StorageLocation,
+    pub custom_path: Option<String>,
+    pub folder_name: String,
+    pub retention: RetentionPolicy,
+    // Write deduplicated snapshots into the backup store instead of full zips
+    #[serde(default)]
+    pub incremental: bool,
+    // Seal zip backups with the session backup password
+    #[serde(default)]
+    pub encrypt: bool,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings {
+            enabled: false,
+            interval_hours: 24,
+            backup_on_exit: true,
+            location: StorageLocation::Documents,
+            custom_path: None,
+            folder_name: "AniTrack Backups".to_string(),
+            retention: RetentionPolicy::default(),
+            incremental: false,
+            encrypt: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub path: String,
+    pub size_in_bytes: u64,
+    pub created_at: String,
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupRestoreResult {
+    pub destination: String,
+    pub restored_files: usize,
+    pub was_encrypted: bool,
+}
+
+pub(crate) fn load_settings() -> Result<BackupSettings, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    Ok(db::get_setting(&conn, BACKUP_SETTINGS_KEY)?.unwrap_or_default())
+}
+
+pub(crate) fn get_backup_dir(app_handle: &tauri::AppHandle, settings: &BackupSettings) -> Result<PathBuf, String> {
+    let base = get_location_dir(app_handle, &settings.location, settings.custom_path.as_deref())?;
+    Ok(base.join(&settings.folder_name))
+}
+
+// Backups are named after their creation time so the retention policy does not
+// depend on filesystem timestamps, which are lost when copying folders around
+fn parse_backup_timestamp(file_name: &str) -> Option<NaiveDateTime> {
+    let stamp = file_name.strip_prefix(BACKUP_FILE_PREFIX)?.get(..15)?;
+    NaiveDateTime::parse_from_str(stamp, BACKUP_TIMESTAMP_FORMAT).ok()
+}
+
+fn scan_backups(backup_dir: &Path) -> Result<Vec<(PathBuf, NaiveDateTime, u64)>, String> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backup_dir).map_err(|e| format!("Failed to read backup directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if let Some(created) = parse_backup_timestamp(&file_name) {
+            let size = entry
+                .metadata()
+                .map_err(|e| format!("Failed to get file metadata: {}", e))?
+                .len();
+            backups.push((path, created, size));
+        }
+    }
+
+    // Newest first
+    backups.sort_by_key(|(_, created, _)| std::cmp::Reverse(*created));
+    Ok(backups)
+}
+
+// Grandfather-father-son selection: keep the newest backup of each of the last
+// N days, N weeks and N months. `dates` must be sorted newest first.
+pub(crate) fn select_backups_to_keep(dates: &[NaiveDateTime], policy: &RetentionPolicy) -> HashSet<usize> {
+    let mut keep = HashSet::new();
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut months = HashSet::new();
+
+    for (index, date) in dates.iter().enumerate() {
+        if days.len() < policy.keep_daily && days.insert(date.date()) {
+            keep.insert(index);
+        }
+
+        let week = date.iso_week();
+        if weeks.len() < policy.keep_weekly && weeks.insert((week.year(), week.week())) {
+            keep.insert(index);
+        }
+
+        if months.len() < policy.keep_monthly && months.insert((date.year(), date.month())) {
+            keep.insert(index);
+        }
+    }
+
+    // Never prune the most recent backup, even with an all-zero policy
+    if !dates.is_empty() {
+        keep.insert(0);
+    }
+
+    keep
+}
+
+fn prune_backups(backup_dir: &Path, policy: &RetentionPolicy) -> Result<usize, String> {
+    let backups = scan_backups(backup_dir)?;
+    let dates: Vec<NaiveDateTime> = backups.iter().map(|(_, created, _)| *created).collect();
+    let keep = select_backups_to_keep(&dates, policy);
+
+    let mut removed = 0;
+    for (index, (path, _, _)) in backups.iter().enumerate() {
+        if !keep.contains(&index) {
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to delete old backup {}: {}", path.display(), e))?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+// Copy the live database to a uniquely named file next to itself, so concurrent
+// backups never share a snapshot. The copy is removed when the path is dropped.
+pub(crate) fn snapshot_database() -> Result<tempfile::TempPath, String> {
+    let db_path = db::get_db_path();
+    let db_dir = db_path
+        .parent()
+        .ok_or_else(|| format!("Invalid database path: {}", db_path.display()))?;
+    let snapshot_path = filesystem::scratch_file(db_dir)?.into_temp_path();
+
+    // VACUUM INTO gives a consistent copy even while the connection is open, and
+    // accepts the empty file created above as its target
+    let conn = DB_CONNECTION.lock().unwrap();
+    conn.execute("VACUUM INTO ?1", [snapshot_path.to_string_lossy().to_string()])
+        .map_err(|e| format!("Failed to snapshot database: {}", e))?;
+
+    Ok(snapshot_path)
+}
+
+fn write_backup_archive(app_handle: &tauri::AppHandle, file: &mut fs::File) -> Result<(), String> {
+    let snapshot_path = snapshot_database()?;
+
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o755);
+
+    zip.start_file("anime_database.db", options)
+        .map_err(|e| format!("Failed to add file to zip: {}", e))?;
+    let mut snapshot = fs::File::open(&snapshot_path)
+        .map_err(|e| format!("Failed to open database snapshot: {}", e))?;
+    std::io::copy(&mut snapshot, &mut zip)
+        .map_err(|e| format!("Failed to write database to zip: {}", e))?;
+    drop(snapshot);
+    drop(snapshot_path);
+
+    // Include the app data directory (image cache, offline JSON) when it exists
+    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
+        if app_data_dir.exists() {
+            add_dir_to_zip(&app_data_dir, &app_data_dir, &mut zip, &options)?;
+        }
+    }
+
+    zip.finish().map_err(|e| format!("Failed to finalize zip file: {}", e))?;
+
+    Ok(())
+}
+
+fn perform_backup(app_handle: &tauri::AppHandle, settings: &BackupSettings) -> Result<BackupInfo, String> {
+    if settings.incremental {
+        return perform_incremental_backup(app_handle, settings);
+    }
+
+    let backup_dir = get_backup_dir(app_handle, settings)?;
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let password = if settings.encrypt {
+        Some(BACKUP_PASSWORD.lock().unwrap().clone().ok_or_else(|| {
+            "Encrypted backups are enabled but the backup password has not been unlocked".to_string()
+        })?)
+    } else {
+        None
+    };
+
+    let now = Local::now();
+    let mut file_name = format!(
+        "{}{}.zip",
+        BACKUP_FILE_PREFIX,
+        now.format(BACKUP_TIMESTAMP_FORMAT)
+    );
+
+    // Clear the flag before snapshotting so edits made during the backup are not lost
+    db::take_data_changed();
+    let result = match &password {
+        // Encrypted backups are assembled in a private scratch file and only the sealed file is kept
+        Some(password) => {
+            file_name = format!("{}.{}", file_name, backup_crypto::ENCRYPTED_EXTENSION);
+            filesystem::scratch_file(&backup_dir).and_then(|mut scratch| {
+                write_backup_archive(app_handle, scratch.as_file_mut())?;
+                filesystem::atomic_write_with(&backup_dir.join(&file_name), false, |file| {
+                    backup_crypto::encrypt_file(scratch.path(), file, password)
+                })
+            })
+        }
+        None => filesystem::atomic_write_with(&backup_dir.join(&file_name), false, |file| {
+            write_backup_archive(app_handle, file)
+        }),
+    };
+    let output_path = backup_dir.join(&file_name);
+    if let Err(e) = result {
+        db::mark_data_changed();
+        return Err(e);
+    }
+
+    {
+        let conn = DB_CONNECTION.lock().unwrap();
+        db::set_setting(&conn, LAST_BACKUP_KEY, &now.to_rfc3339())?;
+    }
+
+    let removed = prune_backups(&backup_dir, &settings.retention)?;
+    if removed > 0 {
+        println!("Pruned {} old backups from {}", removed, backup_dir.display());
+    }
+
+    let size_in_bytes = fs::metadata(&output_path)
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?
+        .len();
+
+    Ok(BackupInfo {
+        file_name,
+        path: output_path.to_string_lossy().to_string(),
+        size_in_bytes,
+        created_at: now.to_rfc3339(),
+        encrypted: password.is_some(),
+    })
+}
+
+fn perform_incremental_backup(
+    app_handle: &tauri::AppHandle,
+    settings: &BackupSettings,
+) -> Result<BackupInfo, String> {
+    db::take_data_changed();
+    let snapshot = match backup_store::create_snapshot(app_handle, settings) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            db::mark_data_changed();
+            return Err(e);
+        }
+    };
+
+    {
+        let conn = DB_CONNECTION.lock().unwrap();
+        db::set_setting(&conn, LAST_BACKUP_KEY, &snapshot.created_at)?;
+    }
+
+    backup_store::prune_snapshots(app_handle, settings)?;
+
+    Ok(BackupInfo {
+        file_name: snapshot.id.clone(),
+        path: snapshot.index_path,
+        size_in_bytes: snapshot.new_bytes,
+        created_at: snapshot.created_at,
+        encrypted: false,
+    })
+}
+
+fn is_backup_due(settings: &BackupSettings) -> Result<bool, String> {
+    if !settings.enabled {
+        return Ok(false);
+    }
+
+    let last_run: Option<String> = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        db::get_setting(&conn, LAST_BACKUP_KEY)?
+    };
+
+    let last_run = match last_run.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok()) {
+        Some(last_run) => last_run,
+        None => return Ok(true),
+    };
+
+    let interval = chrono::Duration::hours(settings.interval_hours.max(1) as i64);
+    Ok(Local::now().signed_duration_since(last_run) >= interval)
+}
+
+// Spawned from the setup hook; runs for the lifetime of the app
+pub fn start_scheduler(app_handle: tauri::AppHandle) {
+    thread::spawn(move || loop {
+        match load_settings().and_then(|settings| {
+            if is_backup_due(&settings)? {
+                perform_backup(&app_handle, &settings).map(Some)
+            } else {
+                Ok(None)
+            }
+        }) {
+            Ok(Some(info)) => println!("Scheduled backup written to {}", info.path),
+            Ok(None) => {}
+            Err(e) => eprintln!("Scheduled backup failed: {}", e),
+        }
+
+        thread::sleep(SCHEDULER_TICK);
+    });
+}
+
+// Called from the exit event; only backs up when the list changed this session
+pub fn backup_on_exit(app_handle: &tauri::AppHandle) {
+    let settings = match load_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Failed to load backup settings on exit: {}", e);
+            return;
+        }
+    };
+
+    if !settings.enabled || !settings.backup_on_exit {
+        return;
+    }
+
+    if !db::take_data_changed() {
+        return;
+    }
+
+    match perform_backup(app_handle, &settings) {
+        Ok(info) => println!("Exit backup written to {}", info.path),
+        Err(e) => eprintln!("Exit backup failed: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_backup_settings() -> Result<BackupSettings, String> {
+    load_settings()
+}
+
+#[tauri::command]
+pub fn update_backup_settings(
+    app_handle: tauri::AppHandle,
+    settings: BackupSettings,
+) -> Result<BackupSettings, String> {
+    if settings.interval_hours == 0 {
+        return Err("Backup interval must be at least one hour".to_string());
+    }
+
+    if settings.encrypt && settings.incremental {
+        return Err("Encryption is only available for full zip backups".to_string());
+    }
+
+    sandbox::check_relative(&settings.folder_name)?;
+    if Path::new(&settings.folder_name).components().count() != 1 {
+        return Err("Backup folder name must be a single folder name".to_string());
+    }
+
+    // The backup directory becomes a sandbox root, so a custom location can
+    // only be changed through choose_backup_folder's native dialog
+    if matches!(settings.location, StorageLocation::Custom) && settings.custom_path != load_settings()?.custom_path {
+        return Err("Choose the custom backup folder with the folder picker".to_string());
+    }
+    save_settings(&app_handle, settings)
+}
+
+fn save_settings(app_handle: &tauri::AppHandle, settings: BackupSettings) -> Result<BackupSettings, String> {
+    let backup_dir = get_backup_dir(app_handle, &settings)?;
+    sandbox::validate_root(&backup_dir)?;
+    // Backups include the app data directory, so they cannot be stored inside it
+    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
+        if backup_dir.starts_with(&app_data_dir) {
+            return Err("Backups cannot be stored inside the app data directory".to_string());
+        }
+    }
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    db::set_setting(&conn, BACKUP_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+// Picks a custom backup location with the native folder dialog. Returns the
+// unchanged settings when the dialog is cancelled.
+#[tauri::command]
+pub async fn choose_backup_folder(app_handle: tauri::AppHandle) -> Result<BackupSettings, String> {
+    let mut settings = load_settings()?;
+    if let Some(path) = sandbox::pick_root(&app_handle, "Choose a backup folder")? {
+        settings.location = StorageLocation::Custom;
+        settings.custom_path = Some(path.to_string_lossy().to_string());
+        settings = save_settings(&app_handle, settings)?;
+    }
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn run_backup_now(app_handle: tauri::AppHandle) -> Result<BackupInfo, String> {
+    let settings = load_settings()?;
+    perform_backup(&app_handle, &settings)
+}
+
+#[tauri::command]
+pub fn list_backups(app_handle: tauri::AppHandle) -> Result<Vec<BackupInfo>, String> {
+    let settings = load_settings()?;
+    let backup_dir = get_backup_dir(&app_handle, &settings)?;
+
+    Ok(scan_backups(&backup_dir)?
+        .into_iter()
+        .map(|(path, created, size)| BackupInfo {
+            file_name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path.to_string_lossy().to_string(),
+            size_in_bytes: size,
+            created_at: Local
+                .from_local_datetime(&created)
+                .earliest()
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_else(|| created.to_string()),
+            encrypted: path
+                .extension()
+                .map(|ext| ext == backup_crypto::ENCRYPTED_EXTENSION)
+                .unwrap_or(false),
+        })
+        .collect())
+}
+
+// ZipArchive::extract only writes entries whose names stay inside the destination
+fn extract_backup_archive(zip_path: &Path, destination: &Path) -> Result<usize, String> {
+    let file = fs::File::open(zip_path)
+        .map_err(|e| format!("Failed to open backup: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read backup archive: {}", e))?;
+
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create restore directory: {}", e))?;
+    archive
+        .extract(destination)
+        .map_err(|e| format!("Failed to extract backup: {}", e))?;
+
+    Ok(archive.len())
+}
+
+#[tauri::command]
+pub fn get_backup_directory(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let settings = load_settings()?;
+    Ok(get_backup_dir(&app_handle, &settings)?
+        .to_string_lossy()
+        .to_string())
+}
+
+#[tauri::command]
+pub fn set_backup_password(password: Option<String>) -> Result<(), String> {
+    if let Some(password) = &password {
+        if password.is_empty() {
+            return Err("Backup password cannot be empty".to_string());
+        }
+    }
+
+    *BACKUP_PASSWORD.lock().unwrap() = password;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restore_backup(
+    app_handle: tauri::AppHandle,
+    backup_path: String,
+    destination_dir: String,
+    password: Option<String>,
+) -> Result<BackupRestoreResult, String> {
+    let source = sandbox::check_path(&app_handle, Path::new(&backup_path))?;
+    let destination = sandbox::check_path(&app_handle, Path::new(&destination_dir))?;
+    if !source.exists() {
+        return Err(format!("Backup does not exist: {}", backup_path));
+    }
+
+    let was_encrypted = backup_crypto::is_encrypted(&source)?;
+    // A plaintext zip where an encrypted backup was expected may have been swapped in
+    let expects_encrypted = password.is_some()
+        || source
+            .extension()
+            .map(|ext| ext == backup_crypto::ENCRYPTED_EXTENSION)
+            .unwrap_or(false);
+    if expects_encrypted && !was_encrypted {
+        return Err(
+            "This backup should be encrypted but is not, so it has been replaced or corrupted"
+                .to_string(),
+        );
+    }
+
+    // The decrypted zip is a private scratch file in the destination, removed when dropped
+    let decrypted = if was_encrypted {
+        let password = password
+            .or_else(|| BACKUP_PASSWORD.lock().unwrap().clone())
+            .ok_or_else(|| "This backup is encrypted and requires a password".to_string())?;
+
+        fs::create_dir_all(&destination)
+            .map_err(|e| format!("Failed to create restore directory: {}", e))?;
+        let mut scratch = filesystem::scratch_file(&destination)?;
+        backup_crypto::decrypt_file(&source, scratch.as_file_mut(), &password)?;
+        Some(scratch)
+    } else {
+        None
+    };
+    let zip_path = decrypted.as_ref().map_or(source.as_path(), |scratch| scratch.path());
+
+    let result = extract_backup_archive(zip_path, &destination);
+
+    Ok(BackupRestoreResult {
+        destination: destination_dir,
+        restored_files: result?,
+        was_encrypted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, NaiveDate};
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn policy(keep_daily: usize, keep_weekly: usize, keep_monthly: usize) -> RetentionPolicy {
+        RetentionPolicy {
+            keep_daily,
+            keep_weekly,
+            keep_monthly,
+        }
+    }
+
+    fn sorted(keep: HashSet<usize>) -> Vec<usize> {
+        let mut keep: Vec<usize> = keep.into_iter().collect();
+        keep.sort();
+        keep
+    }
+
+    #[test]
+    fn keeps_nothing_without_backups() {
+        assert!(select_backups_to_keep(&[], &RetentionPolicy::default()).is_empty());
+    }
+
+    #[test]
+    fn always_keeps_newest_backup() {
+        let dates = [at(2024, 3, 15, 12), at(2024, 3, 14, 12)];
+        assert_eq!(sorted(select_backups_to_keep(&dates, &policy(0, 0, 0))), vec![0]);
+    }
+
+    #[test]
+    fn keeps_newest_backup_of_each_day() {
+        let dates = [
+            at(2024, 3, 15, 18),
+            at(2024, 3, 15, 9),
+            at(2024, 3, 14, 12),
+            at(2024, 3, 13, 12),
+        ];
+        assert_eq!(sorted(select_backups_to_keep(&dates, &policy(2, 0, 0))), vec![0, 2]);
+    }
+
+    #[test]
+    fn keeps_newest_backup_of_each_week_and_month() {
+        // One backup a day going back from Friday 2024-03-15
+        let dates: Vec<NaiveDateTime> = (0..60)
+            .map(|days| at(2024, 3, 15, 12) - ChronoDuration::days(days))
+            .collect();
+
+        // Days 03-15, 03-14 and 03-13, Sunday 03-10 for the previous ISO week
+        // and 02-29 for February
+        assert_eq!(
+            sorted(select_backups_to_keep(&dates, &policy(3, 2, 2))),
+            vec![0, 1, 2, 5, 15]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::KeyInit;
+use aes_gcm::Aes256Gcm;
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+// Encrypted backups are wrapped in a small envelope around the finished zip:
+//
+//   magic (8) | version (1) | argon2 m_cost, t_cost, p_cost (3 x u32 BE) | salt (16) | nonce prefix (7)
+//   followed by the zip split into 64 KiB segments, each sealed with AES-256-GCM
+//
+// The segments use the STREAM construction, so reordering, truncating or
+// editing any part of the file makes decryption fail instead of yielding a
+// partially restored archive.
+pub(crate) const ENCRYPTED_EXTENSION: &str = "enc";
+
+const MAGIC: &[u8; 8] = b"ANIBKENC";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_PREFIX_LEN: usize = 7;
+const HEADER_LEN: usize = 8 + 1 + 12 + SALT_LEN + NONCE_PREFIX_LEN;
+const SEGMENT_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+fn tampered() -> String {
+    "Backup could not be decrypted: the password is wrong or the file has been tampered with"
+        .to_string()
+}
+
+fn derive_key(password: &str, salt: &[u8], params: Params) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive encryption key: {}", e))?;
+    Ok(key)
+}
+
+// Read up to `buf.len()` bytes, only returning less at end of file
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, String> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let read = reader
+            .read(&mut buf[filled..])
+            .map_err(|e| format!("Failed to read backup: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
+pub(crate) fn is_encrypted(path: &Path) -> Result<bool, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Failed to open backup {}: {}", path.display(), e))?;
+    let mut magic = [0u8; 8];
+    Ok(read_full(&mut file, &mut magic)? == MAGIC.len() && &magic == MAGIC)
+}
+
+// Encrypts `source` into `output`; callers write through atomic_write_with so a
+// failed run never leaves a truncated backup behind
+pub(crate) fn encrypt_file<W: Write>(source: &Path, output: &mut W, password: &str) -> Result<(), String> {
+    if password.is_empty() {
+        return Err("Backup password cannot be empty".to_string());
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+    getrandom::getrandom(&mut salt).map_err(|e| format!("Failed to generate salt: {}", e))?;
+    getrandom::getrandom(&mut nonce_prefix)
+        .map_err(|e| format!("Failed to generate nonce: {}", e))?;
+
+    let params = Params::default();
+    let key = derive_key(password, &salt, params.clone())?;
+    let cipher = Aes256Gcm::new_from_slice(&key)
+        .map_err(|e| format!("Failed to initialize cipher: {}", e))?;
+    let mut encryptor =
+        EncryptorBE32::from_aead(cipher, GenericArray::from_slice(&nonce_prefix));
+
+    let mut input = fs::File::open(source)
+        .map_err(|e| format!("Failed to open backup {}: {}", source.display(), e))?;
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    header.extend_from_slice(&params.m_cost().to_be_bytes());
+    header.extend_from_slice(&params.t_cost().to_be_bytes());
+    header.extend_from_slice(&params.p_cost().to_be_bytes());
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce_prefix);
+    output
+        .write_all(&header)
+        .map_err(|e| format!("Failed to write encrypted backup: {}", e))?;
+
+    // Look one segment ahead so the final segment can be sealed as the last one
+    let mut current = vec![0u8; SEGMENT_LEN];
+    let mut current_len = read_full(&mut input, &mut current)?;
+    loop {
+        let mut next = vec![0u8; SEGMENT_LEN];
+        let next_len = if current_len == SEGMENT_LEN {
+            read_full(&mut input, &mut next)?
+        } else {
+            0
+        };
+
+        if next_len == 0 {
+            let sealed = encryptor
+                .encrypt_last(&current[..current_len])
+                .map_err(|_| "Failed to encrypt backup".to_string())?;
+            output
+                .write_all(&sealed)
+                .map_err(|e| format!("Failed to write encrypted backup: {}", e))?;
+            break;
+        }
+
+        let sealed = encryptor
+            .encrypt_next(&current[..current_len])
+            .map_err(|_| "Failed to encrypt backup".to_string())?;
+        output
+            .write_all(&sealed)
+            .map_err(|e| format!("Failed to write encrypted backup: {}", e))?;
+
+        current = next;
+        current_len = next_len;
+    }
+
+    Ok(())
+}
+
+// Decrypts into `output`, which the caller must discard when this fails so a
+// failed check never leaves a half-written archive behind
+pub(crate) fn decrypt_file<W: Write>(source: &Path, output: &mut W, password: &str) -> Result<(), String> {
+    let mut input = fs::File::open(source)
+        .map_err(|e| format!("Failed to open backup {}: {}", source.display(), e))?;
+    let total_len = input
+        .metadata()
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?
+        .len() as usize;
+
+    let mut header = [0u8; HEADER_LEN];
+    if read_full(&mut input, &mut header)? != HEADER_LEN || &header[..8] != MAGIC {
+        return Err("File is not an encrypted backup".to_string());
+    }
+    if header[8] != FORMAT_VERSION {
+        return Err(format!("Unsupported encrypted backup version: {}", header[8]));
+    }
+
+    let read_u32 = |offset: usize| {
+        u32::from_be_bytes([
+            header[offset],
+            header[offset + 1],
+            header[offset + 2],
+            header[offset + 3],
+        ])
+    };
+    // The header is untrusted, so cost parameters above the ones encrypt_file
+    // writes are refused instead of letting a crafted file exhaust memory or CPU
+    let written = Params::default();
+    let (m_cost, t_cost, p_cost) = (read_u32(9), read_u32(13), read_u32(17));
+    if m_cost > written.m_cost() || t_cost > written.t_cost() || p_cost > written.p_cost() {
+        return Err(tampered());
+    }
+    let params = Params::new(m_cost, t_cost, p_cost, None).map_err(|_| tampered())?;
+    let salt = &header[21..21 + SALT_LEN];
+    let nonce_prefix = &header[21 + SALT_LEN..HEADER_LEN];
+
+    let key = derive_key(password, salt, params)?;
+    let cipher = Aes256Gcm::new_from_slice(&key)
+        .map_err(|e| format!("Failed to initialize cipher: {}", e))?;
+    let decryptor = DecryptorBE32::from_aead(cipher, GenericArray::from_slice(nonce_prefix));
+
+    let body_len = total_len.saturating_sub(HEADER_LEN);
+    if body_len < TAG_LEN {
+        return Err(tampered());
+    }
+
+    decrypt_segments(&mut input, decryptor, body_len, output)
+}
+
+fn decrypt_segments<W: Write>(
+    input: &mut fs::File,
+    mut decryptor: DecryptorBE32<Aes256Gcm>,
+    body_len: usize,
+    output: &mut W,
+) -> Result<(), String> {
+    let mut remaining = body_len;
+    let mut segment = vec![0u8; SEGMENT_LEN + TAG_LEN];
+    while remaining > SEGMENT_LEN + TAG_LEN {
+        if read_full(input, &mut segment)? != segment.len() {
+            return Err(tampered());
+        }
+        let plain = decryptor
+            .decrypt_next(segment.as_slice())
+            .map_err(|_| tampered())?;
+        output
+            .write_all(&plain)
+            .map_err(|e| format!("Failed to write decrypted backup: {}", e))?;
+        remaining -= segment.len();
+    }
+
+    if read_full(input, &mut segment[..remaining])? != remaining {
+        return Err(tampered());
+    }
+    let plain = decryptor
+        .decrypt_last(&segment[..remaining])
+        .map_err(|_| tampered())?;
+    output
+        .write_all(&plain)
+        .map_err(|e| format!("Failed to write decrypted backup: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Three segments, the last one partial
+    fn encrypted_fixture(dir: &Path) -> (Vec<u8>, std::path::PathBuf) {
+        let plain: Vec<u8> = (0..SEGMENT_LEN * 2 + 1000).map(|i| (i % 251) as u8).collect();
+        let source = dir.join("backup.zip");
+        fs::write(&source, &plain).unwrap();
+
+        let mut sealed = Vec::new();
+        encrypt_file(&source, &mut sealed, "correct horse").unwrap();
+        let encrypted = dir.join("backup.zip.enc");
+        fs::write(&encrypted, &sealed).unwrap();
+        (plain, encrypted)
+    }
+
+    #[test]
+    fn round_trip_restores_the_original_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (plain, encrypted) = encrypted_fixture(dir.path());
+        assert!(is_encrypted(&encrypted).unwrap());
+        assert!(!is_encrypted(&dir.path().join("backup.zip")).unwrap());
+
+        let mut output = Vec::new();
+        decrypt_file(&encrypted, &mut output, "correct horse").unwrap();
+        assert_eq!(output, plain);
+    }
+
+    #[test]
+    fn wrong_password_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, encrypted) = encrypted_fixture(dir.path());
+
+        let mut output = Vec::new();
+        assert_eq!(decrypt_file(&encrypted, &mut output, "wrong horse"), Err(tampered()));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn tampered_segment_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, encrypted) = encrypted_fixture(dir.path());
+
+        let mut bytes = fs::read(&encrypted).unwrap();
+        bytes[HEADER_LEN + SEGMENT_LEN + TAG_LEN + 10] ^= 1;
+        fs::write(&encrypted, &bytes).unwrap();
+
+        let mut output = Vec::new();
+        assert_eq!(decrypt_file(&encrypted, &mut output, "correct horse"), Err(tampered()));
+    }
+
+    #[test]
+    fn truncated_stream_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, encrypted) = encrypted_fixture(dir.path());
+
+        // Dropping the final segment leaves whole, individually valid segments
+        let bytes = fs::read(&encrypted).unwrap();
+        fs::write(&encrypted, &bytes[..HEADER_LEN + 2 * (SEGMENT_LEN + TAG_LEN)]).unwrap();
+
+        let mut output = Vec::new();
+        assert_eq!(decrypt_file(&encrypted, &mut output, "correct horse"), Err(tampered()));
+    }
+}
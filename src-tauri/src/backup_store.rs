@@ -0,0 +1,632 @@
+use crate::backup::{self, BackupSettings, BACKUP_TIMESTAMP_FORMAT};
+use crate::filesystem::{atomic_write, scratch_file, walk_dir};
+use crate::sandbox;
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::Manager;
+
+// Layout of the content-addressed store inside the backup folder:
+//   store/chunks/ab/abcdef...   file contents, named by their SHA-256
+//   store/snapshots/<id>.json   one index per snapshot listing every file
+const STORE_DIR: &str = "store";
+const CHUNKS_DIR: &str = "chunks";
+const SNAPSHOTS_DIR: &str = "snapshots";
+const DATABASE_ENTRY: &str = "anime_database.db";
+
+// Held while snapshots are written or chunks collected, so garbage collection
+// never deletes chunks a snapshot in progress is about to reference
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+    pub modified: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotIndex {
+    pub id: String,
+    pub created_at: String,
+    pub files: Vec<SnapshotEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub created_at: String,
+    pub index_path: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    pub new_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreResult {
+    pub snapshot_id: String,
+    pub destination: String,
+    pub restored_files: usize,
+    pub restored_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GcResult {
+    pub removed_chunks: usize,
+    pub reclaimed_bytes: u64,
+}
+
+fn get_store_dir(app_handle: &tauri::AppHandle, settings: &BackupSettings) -> Result<PathBuf, String> {
+    Ok(backup::get_backup_dir(app_handle, settings)?.join(STORE_DIR))
+}
+
+fn chunk_path(store_dir: &Path, hash: &str) -> PathBuf {
+    store_dir.join(CHUNKS_DIR).join(&hash[..2]).join(hash)
+}
+
+pub(crate) fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Failed to open file {}: {}", path.display(), e))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+fn modified_secs(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_index(path: &Path) -> Result<SnapshotIndex, String> {
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read snapshot index {}: {}", path.display(), e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse snapshot index {}: {}", path.display(), e))
+}
+
+// All snapshot indexes in the store, newest first
+fn read_all_indexes(store_dir: &Path) -> Result<Vec<(PathBuf, SnapshotIndex)>, String> {
+    let snapshots_dir = store_dir.join(SNAPSHOTS_DIR);
+    if !snapshots_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut indexes = Vec::new();
+    for entry in fs::read_dir(&snapshots_dir)
+        .map_err(|e| format!("Failed to read snapshot directory: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+            let index = read_index(&path)?;
+            indexes.push((path, index));
+        }
+    }
+
+    indexes.sort_by(|a, b| b.1.id.cmp(&a.1.id));
+    Ok(indexes)
+}
+
+fn to_info(index_path: &Path, index: &SnapshotIndex, new_bytes: u64) -> SnapshotInfo {
+    SnapshotInfo {
+        id: index.id.clone(),
+        created_at: index.created_at.clone(),
+        index_path: index_path.to_string_lossy().to_string(),
+        file_count: index.files.len(),
+        total_size: index.files.iter().map(|f| f.size).sum(),
+        new_bytes,
+    }
+}
+
+// Stream a file into the store, hashing it on the way so it is only read once.
+// The copy goes to a uniquely named temporary file that is renamed to its hash
+// when complete, unless a chunk with that hash already exists. Returns the hash
+// and the number of bytes newly written.
+fn store_chunk(store_dir: &Path, source: &Path) -> Result<(String, u64), String> {
+    let chunks_dir = store_dir.join(CHUNKS_DIR);
+    fs::create_dir_all(&chunks_dir)
+        .map_err(|e| format!("Failed to create chunk directory: {}", e))?;
+
+    let mut input = fs::File::open(source)
+        .map_err(|e| format!("Failed to open file {}: {}", source.display(), e))?;
+    let mut temp = scratch_file(&chunks_dir)?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut written = 0u64;
+    loop {
+        let read = input
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file {}: {}", source.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        temp.write_all(&buffer[..read])
+            .map_err(|e| format!("Failed to copy {} into backup store: {}", source.display(), e))?;
+        written += read as u64;
+    }
+
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    // The temporary file is removed when dropped
+    let target = chunk_path(store_dir, &hash);
+    if target.exists() {
+        return Ok((hash, 0));
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create chunk directory: {}", e))?;
+    }
+    temp.as_file()
+        .sync_all()
+        .map_err(|e| format!("Failed to copy {} into backup store: {}", source.display(), e))?;
+    temp.persist(&target)
+        .map_err(|e| format!("Failed to finalize chunk {}: {}", hash, e))?;
+
+    Ok((hash, written))
+}
+
+// Add a single file to the snapshot, reusing the previous hash when size and
+// modification time are unchanged so unchanged files are never re-read. The
+// mtime only has whole seconds, so a file modified within a second of when the
+// previous snapshot was taken may have changed again unnoticed and is re-read.
+fn add_file(
+    store_dir: &Path,
+    source: &Path,
+    name: String,
+    previous: &HashMap<String, SnapshotEntry>,
+    previous_taken: i64,
+    files: &mut Vec<SnapshotEntry>,
+) -> Result<u64, String> {
+    let metadata = fs::metadata(source)
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+    let size = metadata.len();
+    let modified = modified_secs(&metadata);
+
+    let (hash, new_bytes) = match previous.get(&name) {
+        Some(entry)
+            if entry.size == size
+                && entry.modified == modified
+                && (modified as i64) + 1 < previous_taken
+                && chunk_path(store_dir, &entry.hash).exists() =>
+        {
+            (entry.hash.clone(), 0)
+        }
+        _ => store_chunk(store_dir, source)?,
+    };
+
+    files.push(SnapshotEntry {
+        path: name,
+        hash,
+        size,
+        modified,
+    });
+
+    Ok(new_bytes)
+}
+
+pub(crate) fn create_snapshot(
+    app_handle: &tauri::AppHandle,
+    settings: &BackupSettings,
+) -> Result<SnapshotInfo, String> {
+    let _lock = STORE_LOCK.lock().unwrap();
+    // Taken before any file is read, so later edits always have a newer mtime
+    let now = Local::now();
+
+    let store_dir = get_store_dir(app_handle, settings)?;
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .ok()
+        .filter(|dir| dir.exists());
+
+    let db_snapshot = backup::snapshot_database()?;
+    write_snapshot(&store_dir, &db_snapshot, app_data_dir.as_deref(), now)
+}
+
+// Store the database copy and every file under `data_dir`, then write the index
+fn write_snapshot(
+    store_dir: &Path,
+    db_snapshot: &Path,
+    data_dir: Option<&Path>,
+    now: DateTime<Local>,
+) -> Result<SnapshotInfo, String> {
+    let snapshots_dir = store_dir.join(SNAPSHOTS_DIR);
+    fs::create_dir_all(&snapshots_dir)
+        .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+
+    let (previous, previous_taken): (HashMap<String, SnapshotEntry>, i64) =
+        read_all_indexes(store_dir)?
+            .into_iter()
+            .next()
+            .map(|(_, index)| {
+                let taken = DateTime::parse_from_rfc3339(&index.created_at)
+                    .map(|taken| taken.timestamp())
+                    .unwrap_or(0);
+                let files = index
+                    .files
+                    .into_iter()
+                    .map(|entry| (entry.path.clone(), entry))
+                    .collect();
+                (files, taken)
+            })
+            .unwrap_or_default();
+
+    let mut files = Vec::new();
+    let mut new_bytes = add_file(
+        store_dir,
+        db_snapshot,
+        DATABASE_ENTRY.to_string(),
+        &HashMap::new(),
+        0,
+        &mut files,
+    )?;
+
+    if let Some(data_dir) = data_dir {
+        walk_dir(data_dir, &mut |path, is_dir| {
+            if is_dir {
+                return Ok(());
+            }
+
+            let name = path
+                .strip_prefix(data_dir)
+                .map_err(|e| format!("Failed to strip prefix: {}", e))?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            new_bytes += add_file(store_dir, path, name, &previous, previous_taken, &mut files)?;
+            Ok(())
+        })?;
+    }
+
+    let mut id = now.format(BACKUP_TIMESTAMP_FORMAT).to_string();
+    let mut suffix = 1;
+    while snapshots_dir.join(format!("{}.json", id)).exists() {
+        id = format!("{}_{}", now.format(BACKUP_TIMESTAMP_FORMAT), suffix);
+        suffix += 1;
+    }
+
+    let index = SnapshotIndex {
+        id: id.clone(),
+        created_at: now.to_rfc3339(),
+        files,
+    };
+
+    let json = serde_json::to_string_pretty(&index)
+        .map_err(|e| format!("Failed to serialize snapshot index: {}", e))?;
+    let index_path = snapshots_dir.join(format!("{}.json", id));
+    atomic_write(&index_path, json.as_bytes(), false)?;
+
+    Ok(to_info(&index_path, &index, new_bytes))
+}
+
+// Apply the backup retention policy to snapshots, then drop unreferenced chunks
+pub(crate) fn prune_snapshots(
+    app_handle: &tauri::AppHandle,
+    settings: &BackupSettings,
+) -> Result<GcResult, String> {
+    let _lock = STORE_LOCK.lock().unwrap();
+    let store_dir = get_store_dir(app_handle, settings)?;
+    let indexes = read_all_indexes(&store_dir)?;
+
+    let dated: Vec<(PathBuf, NaiveDateTime)> = indexes
+        .into_iter()
+        .filter_map(|(path, index)| {
+            let stamp = index.id.get(..15)?.to_string();
+            NaiveDateTime::parse_from_str(&stamp, BACKUP_TIMESTAMP_FORMAT)
+                .ok()
+                .map(|date| (path, date))
+        })
+        .collect();
+
+    let dates: Vec<NaiveDateTime> = dated.iter().map(|(_, date)| *date).collect();
+    let keep = backup::select_backups_to_keep(&dates, &settings.retention);
+
+    for (index, (path, _)) in dated.iter().enumerate() {
+        if !keep.contains(&index) {
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to delete snapshot {}: {}", path.display(), e))?;
+        }
+    }
+
+    collect_garbage(&store_dir)
+}
+
+fn collect_garbage(store_dir: &Path) -> Result<GcResult, String> {
+    let referenced: HashSet<String> = read_all_indexes(store_dir)?
+        .into_iter()
+        .flat_map(|(_, index)| index.files.into_iter().map(|entry| entry.hash))
+        .collect();
+
+    let mut result = GcResult {
+        removed_chunks: 0,
+        reclaimed_bytes: 0,
+    };
+
+    let chunks_dir = store_dir.join(CHUNKS_DIR);
+    if !chunks_dir.exists() {
+        return Ok(result);
+    }
+
+    walk_dir(&chunks_dir, &mut |path, is_dir| {
+        if is_dir {
+            return Ok(());
+        }
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        // Leftover temporary files from interrupted copies are never referenced
+        if !referenced.contains(&name) {
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to delete chunk {}: {}", path.display(), e))?;
+            result.removed_chunks += 1;
+            result.reclaimed_bytes += size;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(result)
+}
+
+// Reject index paths that would escape the restore destination
+fn safe_relative_path(path: &str) -> Result<PathBuf, String> {
+    let relative = PathBuf::from(path);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("Snapshot contains an invalid path: {}", path));
+    }
+    Ok(relative)
+}
+
+#[tauri::command]
+pub fn create_incremental_snapshot(app_handle: tauri::AppHandle) -> Result<SnapshotInfo, String> {
+    let settings = backup::load_settings()?;
+    create_snapshot(&app_handle, &settings)
+}
+
+#[tauri::command]
+pub fn list_snapshots(app_handle: tauri::AppHandle) -> Result<Vec<SnapshotInfo>, String> {
+    let settings = backup::load_settings()?;
+    let store_dir = get_store_dir(&app_handle, &settings)?;
+
+    Ok(read_all_indexes(&store_dir)?
+        .iter()
+        .map(|(path, index)| to_info(path, index, 0))
+        .collect())
+}
+
+#[tauri::command]
+pub fn restore_snapshot(
+    app_handle: tauri::AppHandle,
+    snapshot_id: String,
+    destination_dir: String,
+) -> Result<RestoreResult, String> {
+    let settings = backup::load_settings()?;
+    let store_dir = get_store_dir(&app_handle, &settings)?;
+
+    let index_path = store_dir
+        .join(SNAPSHOTS_DIR)
+        .join(format!("{}.json", safe_relative_path(&snapshot_id)?.display()));
+    if !index_path.exists() {
+        return Err(format!("Snapshot not found: {}", snapshot_id));
+    }
+    let index = read_index(&index_path)?;
+
+    let destination = sandbox::check_path(&app_handle, Path::new(&destination_dir))?;
+    let mut result = RestoreResult {
+        snapshot_id: index.id.clone(),
+        destination: destination_dir,
+        restored_files: 0,
+        restored_bytes: 0,
+    };
+
+    restore_files(&store_dir, &index, &destination, &mut result)?;
+    Ok(result)
+}
+
+// Copy every file of a snapshot under `destination`, verifying each chunk first
+fn restore_files(
+    store_dir: &Path,
+    index: &SnapshotIndex,
+    destination: &Path,
+    result: &mut RestoreResult,
+) -> Result<(), String> {
+    for entry in &index.files {
+        let source = chunk_path(store_dir, &entry.hash);
+        if !source.exists() {
+            return Err(format!("Backup store is missing data for {}", entry.path));
+        }
+
+        // A chunk whose content no longer matches its name has been corrupted
+        if hash_file(&source)? != entry.hash {
+            return Err(format!("Backup data for {} is corrupted", entry.path));
+        }
+
+        let target = destination.join(safe_relative_path(&entry.path)?);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create parent directories: {}", e))?;
+        }
+
+        fs::copy(&source, &target)
+            .map_err(|e| format!("Failed to restore {}: {}", entry.path, e))?;
+        result.restored_files += 1;
+        result.restored_bytes += entry.size;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_snapshot(app_handle: tauri::AppHandle, snapshot_id: String) -> Result<bool, String> {
+    let settings = backup::load_settings()?;
+    let store_dir = get_store_dir(&app_handle, &settings)?;
+
+    let index_path = store_dir
+        .join(SNAPSHOTS_DIR)
+        .join(format!("{}.json", safe_relative_path(&snapshot_id)?.display()));
+    if !index_path.exists() {
+        return Ok(false);
+    }
+
+    fs::remove_file(&index_path)
+        .map_err(|e| format!("Failed to delete snapshot {}: {}", snapshot_id, e))?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn gc_backup_store(app_handle: tauri::AppHandle) -> Result<GcResult, String> {
+    let settings = backup::load_settings()?;
+    let store_dir = get_store_dir(&app_handle, &settings)?;
+    let _lock = STORE_LOCK.lock().unwrap();
+    collect_garbage(&store_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_files(dir: &Path) -> usize {
+        let mut count = 0;
+        walk_dir(dir, &mut |_, is_dir| {
+            if !is_dir {
+                count += 1;
+            }
+            Ok(())
+        })
+        .unwrap();
+        count
+    }
+
+    fn empty_result() -> RestoreResult {
+        RestoreResult {
+            snapshot_id: String::new(),
+            destination: String::new(),
+            restored_files: 0,
+            restored_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn snapshot_stores_each_content_once() {
+        let root = tempfile::tempdir().unwrap();
+        let store = root.path().join("store");
+        let data = root.path().join("data");
+        fs::create_dir_all(data.join("nested")).unwrap();
+        fs::write(data.join("a.txt"), b"same").unwrap();
+        fs::write(data.join("nested/b.txt"), b"same").unwrap();
+        let db = root.path().join("db.snapshot");
+        fs::write(&db, b"database").unwrap();
+
+        let first = write_snapshot(&store, &db, Some(&data), Local::now()).unwrap();
+        assert_eq!(first.file_count, 3);
+        assert_eq!(first.new_bytes, 12);
+        // Two distinct contents, and no temporary files left behind
+        assert_eq!(count_files(&store.join(CHUNKS_DIR)), 2);
+
+        let second = write_snapshot(&store, &db, Some(&data), Local::now()).unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(second.new_bytes, 0);
+        assert_eq!(read_all_indexes(&store).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn restore_copies_files_and_rejects_corrupted_chunks() {
+        let root = tempfile::tempdir().unwrap();
+        let store = root.path().join("store");
+        let data = root.path().join("data");
+        fs::create_dir_all(data.join("nested")).unwrap();
+        fs::write(data.join("nested/b.txt"), b"hello").unwrap();
+        let db = root.path().join("db.snapshot");
+        fs::write(&db, b"database").unwrap();
+
+        write_snapshot(&store, &db, Some(&data), Local::now()).unwrap();
+        let (_, index) = read_all_indexes(&store).unwrap().remove(0);
+
+        let destination = root.path().join("restored");
+        let mut result = empty_result();
+        restore_files(&store, &index, &destination, &mut result).unwrap();
+        assert_eq!(result.restored_files, 2);
+        assert_eq!(result.restored_bytes, 13);
+        assert_eq!(fs::read(destination.join(DATABASE_ENTRY)).unwrap(), b"database");
+        assert_eq!(fs::read(destination.join("nested/b.txt")).unwrap(), b"hello");
+
+        let entry = index.files.iter().find(|f| f.path == "nested/b.txt").unwrap();
+        fs::write(chunk_path(&store, &entry.hash), b"tampered").unwrap();
+        let err = restore_files(&store, &index, &destination, &mut empty_result()).unwrap_err();
+        assert!(err.contains("corrupted"), "{}", err);
+    }
+
+    #[test]
+    fn garbage_collection_keeps_only_referenced_chunks() {
+        let root = tempfile::tempdir().unwrap();
+        let store = root.path().join("store");
+        let data = root.path().join("data");
+        fs::create_dir_all(&data).unwrap();
+        fs::write(data.join("a.txt"), b"old").unwrap();
+        let db = root.path().join("db.snapshot");
+        fs::write(&db, b"database").unwrap();
+
+        let first = write_snapshot(&store, &db, Some(&data), Local::now()).unwrap();
+        fs::write(data.join("a.txt"), b"new!").unwrap();
+        write_snapshot(&store, &db, Some(&data), Local::now()).unwrap();
+        fs::write(store.join(CHUNKS_DIR).join(".leftover.tmp"), b"partial").unwrap();
+
+        // Only the leftover temporary file goes while both snapshots are kept
+        let kept = collect_garbage(&store).unwrap();
+        assert_eq!(kept.removed_chunks, 1);
+        assert_eq!(count_files(&store.join(CHUNKS_DIR)), 3);
+
+        fs::remove_file(&first.index_path).unwrap();
+        let result = collect_garbage(&store).unwrap();
+        assert_eq!(result.removed_chunks, 1);
+        assert_eq!(result.reclaimed_bytes, 3);
+        assert_eq!(count_files(&store.join(CHUNKS_DIR)), 2);
+
+        let (_, index) = read_all_indexes(&store).unwrap().remove(0);
+        let mut restored = empty_result();
+        restore_files(&store, &index, &root.path().join("restored"), &mut restored).unwrap();
+        assert_eq!(fs::read(root.path().join("restored/a.txt")).unwrap(), b"new!");
+    }
+}
@@ -0,0 +1,252 @@
+use crate::backup;
+use crate::db;
+use crate::filesystem::walk_dir;
+use crate::image_cache::{self, SizeClassStats};
+use crate::sandbox;
+use crate::thumbnails;
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::Manager;
+
+// How many of the biggest files to list so the UI can point at them
+const LARGEST_FILES_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSummary {
+    pub path: String,
+    pub category: String,
+    pub size_in_bytes: u64,
+    pub modified: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryStats {
+    // "covers", "thumbnails", "api_cache", "exports" or "backups"
+    pub category: String,
+    pub paths: Vec<String>,
+    pub file_count: usize,
+    pub size_in_bytes: u64,
+    // Set when the category could not be scanned; the counts are then zero
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CacheStats {
+    // Totals for the requested cache folder, scanned recursively
+    size_in_bytes: u64,
+    image_count: usize,
+    file_count: usize,
+    size_classes: Vec<SizeClassStats>,
+    categories: Vec<CategoryStats>,
+    oldest_file: Option<FileSummary>,
+    newest_file: Option<FileSummary>,
+    largest_files: Vec<FileSummary>,
+    // Space on the volume holding the cache folder, None if it cannot be determined
+    free_space_bytes: Option<u64>,
+    total_space_bytes: Option<u64>,
+}
+
+struct ScannedFile {
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl ScannedFile {
+    fn summary(&self, category: &str) -> FileSummary {
+        FileSummary {
+            path: self.path.to_string_lossy().to_string(),
+            category: category.to_string(),
+            size_in_bytes: self.size,
+            modified: self
+                .modified
+                .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+        }
+    }
+}
+
+fn scan<F: Fn(&Path) -> bool>(dir: &Path, include: F) -> Result<Vec<ScannedFile>, String> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    walk_dir(dir, &mut |path, is_dir| {
+        if is_dir || !include(path) {
+            return Ok(());
+        }
+        let metadata = fs::metadata(path)
+            .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+        files.push(ScannedFile {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        });
+        Ok(())
+    })?;
+
+    Ok(files)
+}
+
+// Sniff the header instead of trusting extensions; cached objects have none
+fn is_image(path: &Path) -> bool {
+    let mut header = [0u8; 32];
+    let read = match fs::File::open(path).and_then(|mut file| file.read(&mut header)) {
+        Ok(read) => read,
+        Err(_) => return false,
+    };
+    image::guess_format(&header[..read]).is_ok()
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case(extension))
+        .unwrap_or(false)
+}
+
+// Folders the offline storage service may have written its JSON cache to
+fn offline_storage_dirs(app_handle: &tauri::AppHandle) -> Vec<PathBuf> {
+    let resolver = app_handle.path();
+    let mut dirs: Vec<PathBuf> = [
+        resolver.document_dir(),
+        resolver.home_dir(),
+        resolver.app_data_dir(),
+    ]
+    .into_iter()
+    .filter_map(|dir| dir.ok())
+    .map(|dir| dir.join(sandbox::OFFLINE_STORAGE_FOLDER))
+    .collect();
+    dirs.dedup();
+    dirs
+}
+
+fn volume_space(path: &Path) -> (Option<u64>, Option<u64>) {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        // The most specific mount point wins, e.g. /home over /
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| (Some(disk.available_space()), Some(disk.total_space())))
+        .unwrap_or((None, None))
+}
+
+fn collect_categories(
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<(CategoryStats, Vec<ScannedFile>)>, String> {
+    let cache_dir = image_cache::get_cache_dir(app_handle)?;
+    let variants_dir = cache_dir.join(thumbnails::VARIANTS_FOLDER);
+
+    let covers = scan(&cache_dir, |path| !path.starts_with(&variants_dir))?;
+    let thumbnails = scan(&variants_dir, |_| true)?;
+
+    let storage_dirs = offline_storage_dirs(app_handle);
+    let mut api_cache = Vec::new();
+    for dir in &storage_dirs {
+        api_cache.extend(scan(dir, |path| has_extension(path, "json"))?);
+    }
+
+    let export_dir = db::default_export_dir();
+    let exports = scan(&export_dir, |path| {
+        path.parent() == Some(export_dir.as_path())
+            && path
+                .file_name()
+                .map(|name| name.to_string_lossy().starts_with(db::EXPORT_FILE_PREFIX))
+                .unwrap_or(false)
+    })?;
+
+    let display = |path: &Path| path.to_string_lossy().to_string();
+
+    // A misconfigured backup folder should not hide the other categories
+    let (backup_paths, backups) = match backup::load_settings()
+        .and_then(|settings| backup::get_backup_dir(app_handle, &settings))
+    {
+        Ok(backup_dir) => (vec![display(&backup_dir)], scan(&backup_dir, |_| true)),
+        Err(e) => (Vec::new(), Err(e)),
+    };
+
+    let categories = vec![
+        ("covers", vec![display(&cache_dir)], Ok(covers)),
+        ("thumbnails", vec![display(&variants_dir)], Ok(thumbnails)),
+        ("api_cache", storage_dirs.iter().map(|dir| display(dir)).collect(), Ok(api_cache)),
+        ("exports", vec![display(&export_dir)], Ok(exports)),
+        ("backups", backup_paths, backups),
+    ];
+
+    Ok(categories
+        .into_iter()
+        .map(|(category, paths, scanned)| {
+            let (files, error) = match scanned {
+                Ok(files) => (files, None),
+                Err(e) => (Vec::new(), Some(e)),
+            };
+            let stats = CategoryStats {
+                category: category.to_string(),
+                paths,
+                file_count: files.len(),
+                size_in_bytes: files.iter().map(|file| file.size).sum(),
+                error,
+            };
+            (stats, files)
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_cache_stats(app_handle: tauri::AppHandle, cache_path: String) -> Result<CacheStats, String> {
+    let path = sandbox::check_path(&app_handle, Path::new(&cache_path))?;
+
+    let files = scan(&path, |_| true)?;
+    let image_count = files.iter().filter(|file| is_image(&file.path)).count();
+
+    // Thumbnail classes only exist inside the app's own image cache
+    let size_classes = if path == image_cache::get_cache_dir(&app_handle)? {
+        image_cache::size_class_breakdown(&path)?
+    } else {
+        Vec::new()
+    };
+
+    let categories = collect_categories(&app_handle)?;
+    let scanned: Vec<(&str, &ScannedFile)> = categories
+        .iter()
+        .flat_map(|(stats, files)| files.iter().map(|file| (stats.category.as_str(), file)))
+        .collect();
+
+    let dated = || {
+        scanned
+            .iter()
+            .filter_map(|(category, file)| file.modified.map(|modified| (modified, category, file)))
+    };
+    let oldest_file = dated()
+        .min_by_key(|(modified, _, _)| *modified)
+        .map(|(_, category, file)| file.summary(category));
+    let newest_file = dated()
+        .max_by_key(|(modified, _, _)| *modified)
+        .map(|(_, category, file)| file.summary(category));
+
+    let mut all: Vec<FileSummary> = scanned
+        .iter()
+        .map(|(category, file)| file.summary(category))
+        .collect();
+    all.sort_by_key(|file| std::cmp::Reverse(file.size_in_bytes));
+    all.truncate(LARGEST_FILES_LIMIT);
+
+    let (free_space_bytes, total_space_bytes) = volume_space(&path);
+
+    Ok(CacheStats {
+        size_in_bytes: files.iter().map(|file| file.size).sum(),
+        image_count,
+        file_count: files.len(),
+        size_classes,
+        categories: categories.into_iter().map(|(stats, _)| stats).collect(),
+        oldest_file,
+        newest_file,
+        largest_files: all,
+        free_space_bytes,
+        total_space_bytes,
+    })
+}
@@ -0,0 +1,333 @@
+use crate::airing::{self, DEFAULT_CALENDAR_STATUSES};
+use crate::db::{self, DB_CONNECTION};
+use crate::filesystem;
+use crate::metadata_cache;
+use crate::sandbox;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Request, Response, Server};
+
+const FEED_SETTINGS_KEY: &str = "calendar_feed_settings";
+const FEED_PATH: &str = "/airing.ics";
+const CALENDAR_FILE_NAME: &str = "airing_schedule.ics";
+
+// Events cover broadcasts from a day ago (so today's episodes stay visible) to
+// this far ahead
+const CALENDAR_HORIZON_DAYS: i64 = 90;
+
+const BIND_ATTEMPTS: u32 = 20;
+const BIND_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+static FEED_SERVER: Lazy<Mutex<Option<FeedServer>>> = Lazy::new(|| Mutex::new(None));
+
+struct FeedServer {
+    server: Arc<Server>,
+    port: u16,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarFeedSettings {
+    pub enabled: bool,
+    // The feed only listens on localhost
+    pub port: u16,
+}
+
+impl Default for CalendarFeedSettings {
+    fn default() -> Self {
+        CalendarFeedSettings {
+            enabled: false,
+            port: 8788,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarFeedStatus {
+    pub settings: CalendarFeedSettings,
+    pub running: bool,
+    // Subscription URL for calendar apps while the feed is running
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarExport {
+    pub path: String,
+    pub event_count: usize,
+}
+
+fn episode_length(data: Option<&Value>) -> Duration {
+    Duration::minutes(
+        data.and_then(metadata_cache::episode_minutes)
+            .unwrap_or(metadata_cache::DEFAULT_EPISODE_MINUTES),
+    )
+}
+
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// Line breaks become a literal "\n", including CRLF and bare CR from Windows and
+// old Mac text, since a raw CR would end the content line early
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\n', "\\n")
+}
+
+// Content lines are folded at 75 octets without splitting UTF-8 sequences (RFC 5545 3.1)
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+// One VEVENT per broadcast of watching/planned entries. UIDs only depend on the
+// show and episode, so calendar apps update events when the schedule shifts.
+pub(crate) fn build_calendar(conn: &Connection, now: DateTime<Utc>) -> Result<(String, usize), String> {
+    let statuses: Vec<String> = DEFAULT_CALENDAR_STATUSES.iter().map(|s| s.to_string()).collect();
+    let entries = airing::episodes_between(
+        conn,
+        now - Duration::days(1),
+        now + Duration::days(CALENDAR_HORIZON_DAYS),
+        &statuses,
+    )?;
+    let metadata: HashMap<i64, Value> = metadata_cache::load_all(conn)?.into_iter().collect();
+
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//AniTrack//Airing Schedule//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    push_line(&mut out, "METHOD:PUBLISH");
+    push_line(&mut out, "X-WR-CALNAME:Anime airing schedule");
+
+    let stamp = ics_time(now);
+    let mut count = 0;
+    for entry in &entries {
+        let start = match DateTime::parse_from_rfc3339(&entry.airs_at) {
+            Ok(start) => start.with_timezone(&Utc),
+            Err(_) => continue,
+        };
+        let end = start + episode_length(metadata.get(&entry.anime_id));
+
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:anime-{}-ep-{}@anitrack", entry.anime_id, entry.episode));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut out, &format!("DTSTART:{}", ics_time(start)));
+        push_line(&mut out, &format!("DTEND:{}", ics_time(end)));
+        push_line(
+            &mut out,
+            &format!("SUMMARY:{}", escape_text(&format!("{} - Episode {}", entry.title, entry.episode))),
+        );
+        push_line(
+            &mut out,
+            &format!("DESCRIPTION:{}", escape_text(&format!("Broadcast at {} JST", entry.jst_time))),
+        );
+        push_line(&mut out, &format!("URL:https://myanimelist.net/anime/{}", entry.anime_id));
+        push_line(&mut out, "TRANSP:TRANSPARENT");
+        push_line(&mut out, "END:VEVENT");
+        count += 1;
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    Ok((out, count))
+}
+
+fn load_settings() -> Result<CalendarFeedSettings, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    Ok(db::get_setting(&conn, FEED_SETTINGS_KEY)?.unwrap_or_default())
+}
+
+// Only requests addressed to the feed itself are answered, so a web page that
+// rebinds its own domain to 127.0.0.1 cannot read the list through a browser
+fn is_local_host(request: &Request, port: u16) -> bool {
+    let allowed = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Host"))
+        .is_some_and(|header| allowed.iter().any(|host| header.value.as_str().eq_ignore_ascii_case(host)))
+}
+
+fn serve(server: Arc<Server>, port: u16) {
+    for request in server.incoming_requests() {
+        let path = request.url().split('?').next().unwrap_or_default().to_string();
+        let result = if !is_local_host(&request, port) {
+            request.respond(Response::from_string("Forbidden").with_status_code(403))
+        } else if path == FEED_PATH {
+            let calendar = {
+                let conn = DB_CONNECTION.lock().unwrap();
+                build_calendar(&conn, Utc::now())
+            };
+            match calendar {
+                Ok((body, _)) => request.respond(
+                    Response::from_string(body).with_header(
+                        Header::from_bytes("Content-Type", "text/calendar; charset=utf-8").unwrap(),
+                    ),
+                ),
+                Err(e) => {
+                    eprintln!("Failed to build calendar feed: {}", e);
+                    request.respond(Response::from_string("Internal error").with_status_code(500))
+                }
+            }
+        } else {
+            request.respond(Response::from_string("Not found").with_status_code(404))
+        };
+
+        if let Err(e) = result {
+            eprintln!("Failed to answer calendar feed request: {}", e);
+        }
+    }
+}
+
+// Stop the old server and wait for its thread, which holds the last handle
+fn stop(feed: FeedServer) {
+    feed.server.unblock();
+    let _ = feed.thread.join();
+}
+
+// tiny_http closes the listening socket on its own accept thread after the
+// server is dropped, so right after a stop binding is retried for a moment
+fn bind(port: u16, just_stopped: bool) -> Result<Server, String> {
+    let attempts = if just_stopped { BIND_ATTEMPTS } else { 1 };
+    let mut attempt = 1;
+    loop {
+        match Server::http(("127.0.0.1", port)) {
+            Ok(server) => return Ok(server),
+            Err(_) if attempt < attempts => {
+                attempt += 1;
+                thread::sleep(BIND_RETRY_DELAY);
+            }
+            Err(e) => return Err(format!("Failed to start calendar feed on port {}: {}", port, e)),
+        }
+    }
+}
+
+// Stop any running feed and start it again if enabled. The lock is held
+// throughout so concurrent restarts cannot race for the port.
+pub fn restart() -> Result<(), String> {
+    let mut running = FEED_SERVER.lock().unwrap();
+    let settings = load_settings()?;
+
+    // Nothing to rebind when the feed stays on the same port
+    if settings.enabled && running.as_ref().is_some_and(|feed| feed.port == settings.port) {
+        return Ok(());
+    }
+    let stopped = running.take().map(stop).is_some();
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let port = settings.port;
+    let server = Arc::new(bind(port, stopped)?);
+    let thread = {
+        let server = server.clone();
+        thread::spawn(move || serve(server, port))
+    };
+    *running = Some(FeedServer { server, port, thread });
+
+    Ok(())
+}
+
+fn status(settings: CalendarFeedSettings) -> CalendarFeedStatus {
+    let running = FEED_SERVER.lock().unwrap().is_some();
+    CalendarFeedStatus {
+        url: running.then(|| format!("http://127.0.0.1:{}{}", settings.port, FEED_PATH)),
+        running,
+        settings,
+    }
+}
+
+#[tauri::command]
+pub fn export_airing_calendar(
+    app_handle: tauri::AppHandle,
+    export_path: Option<String>,
+) -> Result<CalendarExport, String> {
+    let (calendar, event_count) = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        build_calendar(&conn, Utc::now())?
+    };
+
+    // A fixed default name so repeated exports replace the previous file
+    let path = match export_path {
+        Some(path) => sandbox::check_path(&app_handle, Path::new(&path))?,
+        None => db::default_export_dir().join(format!("{}{}", db::EXPORT_FILE_PREFIX, CALENDAR_FILE_NAME)),
+    };
+    filesystem::atomic_write(&path, calendar.as_bytes(), false)?;
+
+    Ok(CalendarExport {
+        path: path.to_string_lossy().to_string(),
+        event_count,
+    })
+}
+
+#[tauri::command]
+pub fn get_calendar_feed_status() -> Result<CalendarFeedStatus, String> {
+    Ok(status(load_settings()?))
+}
+
+#[tauri::command]
+pub fn update_calendar_feed_settings(settings: CalendarFeedSettings) -> Result<CalendarFeedStatus, String> {
+    if settings.port < 1024 {
+        return Err("Calendar feed port must be 1024 or higher".to_string());
+    }
+
+    {
+        let conn = DB_CONNECTION.lock().unwrap();
+        db::set_setting(&conn, FEED_SETTINGS_KEY, &settings)?;
+    }
+    restart()?;
+    Ok(status(settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_text_escapes_specials_and_line_breaks() {
+        assert_eq!(escape_text(r"a\b;c,d"), r"a\\b\;c\,d");
+        assert_eq!(escape_text("one\ntwo\r\nthree\rfour"), r"one\ntwo\nthree\nfour");
+    }
+
+    #[test]
+    fn push_line_folds_ascii_at_75_octets() {
+        let line = "X".repeat(80);
+        let mut out = String::new();
+        push_line(&mut out, &line);
+        assert_eq!(out, format!("{}\r\n {}\r\n", "X".repeat(75), "X".repeat(5)));
+    }
+
+    #[test]
+    fn push_line_never_splits_multi_byte_characters() {
+        // 8 ASCII octets plus 3-octet characters: the 23rd would end at octet 77
+        let line = format!("SUMMARY:{}", "葬送のフリーレン".repeat(5));
+        let mut out = String::new();
+        push_line(&mut out, &line);
+
+        let physical: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(physical[0].len(), 74);
+        assert!(physical.iter().all(|part| part.len() <= 75));
+        assert!(physical[1..].iter().all(|part| part.starts_with(' ')));
+        assert_eq!(out.trim_end_matches("\r\n").replace("\r\n ", ""), line);
+    }
+}
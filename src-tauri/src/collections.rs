@@ -0,0 +1,540 @@
+use crate::db::{self, DB_CONNECTION};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Serialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub anime_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CustomList {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub entry_count: i64,
+    pub created_at: String,
+}
+
+// Custom lists as they appear in exports, entries in list order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomListExport {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub anime_ids: Vec<i64>,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    // Names are unique ignoring case so "Isekai" and "isekai" are one tag
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS anime_tags (
+            anime_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (anime_id, tag_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_anime_tags_tag ON anime_tags(tag_id)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS custom_lists (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            description TEXT NOT NULL DEFAULT '',
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    // Positions are kept contiguous from 0 within each list
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS custom_list_entries (
+            list_id INTEGER NOT NULL,
+            anime_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            PRIMARY KEY (list_id, anime_id)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn clean_name(name: &str, what: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("{} name cannot be empty", what));
+    }
+    Ok(name.to_string())
+}
+
+fn on_list(conn: &Connection, anime_id: i64) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM user_anime WHERE anime_id = ?1)",
+        params![anime_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to check if anime exists: {}", e))
+}
+
+fn find_tag(conn: &Connection, name: &str) -> Result<Option<i64>, String> {
+    conn.query_row("SELECT id FROM tags WHERE name = ?1", params![name], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to look up tag: {}", e))
+}
+
+fn find_list(conn: &Connection, name: &str) -> Result<Option<i64>, String> {
+    conn.query_row("SELECT id FROM custom_lists WHERE name = ?1", params![name], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to look up custom list: {}", e))
+}
+
+fn get_or_create_tag(conn: &Connection, name: &str) -> Result<i64, String> {
+    let name = clean_name(name, "Tag")?;
+    if let Some(id) = find_tag(conn, &name)? {
+        return Ok(id);
+    }
+    conn.execute("INSERT INTO tags (name) VALUES (?1)", params![name])
+        .map_err(|e| format!("Failed to create tag: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn load_tag(conn: &Connection, tag_id: i64) -> Result<Tag, String> {
+    conn.query_row(
+        "SELECT t.id, t.name, COUNT(a.anime_id)
+         FROM tags t LEFT JOIN anime_tags a ON a.tag_id = t.id
+         WHERE t.id = ?1 GROUP BY t.id",
+        params![tag_id],
+        |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                anime_count: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load tag: {}", e))?
+    .ok_or_else(|| format!("Tag {} does not exist", tag_id))
+}
+
+fn load_list(conn: &Connection, list_id: i64) -> Result<CustomList, String> {
+    conn.query_row(
+        "SELECT l.id, l.name, l.description, l.created_at, COUNT(e.anime_id)
+         FROM custom_lists l LEFT JOIN custom_list_entries e ON e.list_id = l.id
+         WHERE l.id = ?1 GROUP BY l.id",
+        params![list_id],
+        |row| {
+            Ok(CustomList {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                created_at: row.get(3)?,
+                entry_count: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load custom list: {}", e))?
+    .ok_or_else(|| format!("Custom list {} does not exist", list_id))
+}
+
+// Tag names per anime, sorted by name, for filling in list query results
+pub(crate) fn tags_by_anime(conn: &Connection) -> Result<HashMap<i64, Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.anime_id, t.name FROM anime_tags a JOIN tags t ON t.id = a.tag_id
+             ORDER BY t.name COLLATE NOCASE",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (anime_id, name) = row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        tags.entry(anime_id).or_default().push(name);
+    }
+    Ok(tags)
+}
+
+pub(crate) fn anime_tags(conn: &Connection, anime_id: i64) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.name FROM anime_tags a JOIN tags t ON t.id = a.tag_id
+             WHERE a.anime_id = ?1 ORDER BY t.name COLLATE NOCASE",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![anime_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+// Adds tags to an anime by name, creating tags that do not exist yet
+pub(crate) fn add_anime_tags(conn: &Connection, anime_id: i64, names: &[String]) -> Result<(), String> {
+    for name in names {
+        let tag_id = get_or_create_tag(conn, name)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO anime_tags (anime_id, tag_id) VALUES (?1, ?2)",
+            params![anime_id, tag_id],
+        )
+        .map_err(|e| format!("Failed to tag anime: {}", e))?;
+    }
+    Ok(())
+}
+
+// Rewrites positions as 0..n in their current order
+fn compact_positions(conn: &Connection, list_id: i64) -> Result<(), String> {
+    let ids = list_anime_ids(conn, list_id)?;
+    for (position, anime_id) in ids.iter().enumerate() {
+        conn.execute(
+            "UPDATE custom_list_entries SET position = ?1 WHERE list_id = ?2 AND anime_id = ?3",
+            params![position as i64, list_id, anime_id],
+        )
+        .map_err(|e| format!("Failed to reorder custom list: {}", e))?;
+    }
+    Ok(())
+}
+
+fn list_anime_ids(conn: &Connection, list_id: i64) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT anime_id FROM custom_list_entries WHERE list_id = ?1 ORDER BY position, anime_id")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![list_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+fn append_to_list(conn: &Connection, list_id: i64, anime_id: i64) -> Result<bool, String> {
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO custom_list_entries (list_id, anime_id, position)
+             SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0) FROM custom_list_entries WHERE list_id = ?1",
+            params![list_id, anime_id],
+        )
+        .map_err(|e| format!("Failed to add to custom list: {}", e))?;
+    Ok(inserted > 0)
+}
+
+// Drops an anime's tags and list memberships when it leaves the user's list
+pub(crate) fn remove_anime(conn: &Connection, anime_id: i64) -> Result<(), String> {
+    conn.execute("DELETE FROM anime_tags WHERE anime_id = ?1", params![anime_id])
+        .map_err(|e| format!("Failed to remove anime tags: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT list_id FROM custom_list_entries WHERE anime_id = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let list_ids: Vec<i64> = stmt
+        .query_map(params![anime_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+    conn.execute("DELETE FROM custom_list_entries WHERE anime_id = ?1", params![anime_id])
+        .map_err(|e| format!("Failed to remove anime from custom lists: {}", e))?;
+    for list_id in list_ids {
+        compact_positions(conn, list_id)?;
+    }
+    Ok(())
+}
+
+// Tags and lists are cleared along with the list on a replacing import
+pub(crate) fn clear_all(conn: &Connection) -> Result<(), String> {
+    for table in ["anime_tags", "tags", "custom_list_entries", "custom_lists"] {
+        conn.execute(&format!("DELETE FROM {}", table), [])
+            .map_err(|e| format!("Failed to clear {}: {}", table, e))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn export_lists(conn: &Connection) -> Result<Vec<CustomListExport>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, description FROM custom_lists ORDER BY name COLLATE NOCASE")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let lists: Vec<(i64, String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+
+    lists
+        .into_iter()
+        .map(|(id, name, description)| {
+            Ok(CustomListExport {
+                name,
+                description,
+                anime_ids: list_anime_ids(conn, id)?,
+            })
+        })
+        .collect()
+}
+
+fn import_list(conn: &Connection, list: &CustomListExport) -> Result<(), String> {
+    let name = clean_name(&list.name, "List")?;
+    let list_id = match find_list(conn, &name)? {
+        Some(id) => id,
+        None => {
+            conn.execute(
+                "INSERT INTO custom_lists (name, description, created_at) VALUES (?1, ?2, ?3)",
+                params![name, list.description, chrono::Utc::now().to_rfc3339()],
+            )
+            .map_err(|e| format!("Failed to create custom list: {}", e))?;
+            conn.last_insert_rowid()
+        }
+    };
+    for &anime_id in &list.anime_ids {
+        if on_list(conn, anime_id)? {
+            append_to_list(conn, list_id, anime_id)?;
+        }
+    }
+    Ok(())
+}
+
+// Merges imported lists by name. Entries missing from the user's list are
+// skipped and new entries go after the ones already there. Lists that cannot
+// be imported are skipped and their errors returned.
+pub(crate) fn import_lists(conn: &Connection, lists: &[CustomListExport]) -> Vec<String> {
+    lists
+        .iter()
+        .filter_map(|list| {
+            import_list(conn, list)
+                .err()
+                .map(|e| format!("List \"{}\": {}", list.name, e))
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_tags() -> Result<Vec<Tag>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id, t.name, COUNT(a.anime_id)
+             FROM tags t LEFT JOIN anime_tags a ON a.tag_id = t.id
+             GROUP BY t.id ORDER BY t.name COLLATE NOCASE",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                anime_count: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+#[tauri::command]
+pub fn create_tag(name: String) -> Result<Tag, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let tag_id = get_or_create_tag(&conn, &name)?;
+    db::mark_data_changed();
+    load_tag(&conn, tag_id)
+}
+
+#[tauri::command]
+pub fn rename_tag(tag_id: i64, name: String) -> Result<Tag, String> {
+    let name = clean_name(&name, "Tag")?;
+    let conn = DB_CONNECTION.lock().unwrap();
+    // Changing only the casing of a tag's own name is allowed
+    if find_tag(&conn, &name)?.is_some_and(|id| id != tag_id) {
+        return Err(format!("A tag named {} already exists", name));
+    }
+    let updated = conn
+        .execute("UPDATE tags SET name = ?1 WHERE id = ?2", params![name, tag_id])
+        .map_err(|e| format!("Failed to rename tag: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Tag {} does not exist", tag_id));
+    }
+    db::mark_data_changed();
+    load_tag(&conn, tag_id)
+}
+
+#[tauri::command]
+pub fn delete_tag(tag_id: i64) -> Result<bool, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    conn.execute("DELETE FROM anime_tags WHERE tag_id = ?1", params![tag_id])
+        .map_err(|e| format!("Failed to untag anime: {}", e))?;
+    let deleted = conn
+        .execute("DELETE FROM tags WHERE id = ?1", params![tag_id])
+        .map_err(|e| format!("Failed to delete tag: {}", e))?;
+    if deleted > 0 {
+        db::mark_data_changed();
+    }
+    Ok(deleted > 0)
+}
+
+// Replaces an anime's tags with `tags`, creating any that are new
+#[tauri::command]
+pub fn set_anime_tags(anime_id: i64, tags: Vec<String>) -> Result<Vec<String>, String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    if !on_list(&tx, anime_id)? {
+        return Err(format!("Anime {} is not on your list", anime_id));
+    }
+    tx.execute("DELETE FROM anime_tags WHERE anime_id = ?1", params![anime_id])
+        .map_err(|e| format!("Failed to clear anime tags: {}", e))?;
+    add_anime_tags(&tx, anime_id, &tags)?;
+    let tags = anime_tags(&tx, anime_id)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    db::mark_data_changed();
+    Ok(tags)
+}
+
+#[tauri::command]
+pub fn list_custom_lists() -> Result<Vec<CustomList>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT l.id, l.name, l.description, l.created_at, COUNT(e.anime_id)
+             FROM custom_lists l LEFT JOIN custom_list_entries e ON e.list_id = l.id
+             GROUP BY l.id ORDER BY l.name COLLATE NOCASE",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(CustomList {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                created_at: row.get(3)?,
+                entry_count: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+#[tauri::command]
+pub fn create_custom_list(name: String, description: Option<String>) -> Result<CustomList, String> {
+    let name = clean_name(&name, "List")?;
+    let conn = DB_CONNECTION.lock().unwrap();
+    if find_list(&conn, &name)?.is_some() {
+        return Err(format!("A list named {} already exists", name));
+    }
+    conn.execute(
+        "INSERT INTO custom_lists (name, description, created_at) VALUES (?1, ?2, ?3)",
+        params![name, description.unwrap_or_default(), chrono::Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| format!("Failed to create custom list: {}", e))?;
+    db::mark_data_changed();
+    load_list(&conn, conn.last_insert_rowid())
+}
+
+#[tauri::command]
+pub fn update_custom_list(list_id: i64, name: String, description: String) -> Result<CustomList, String> {
+    let name = clean_name(&name, "List")?;
+    let conn = DB_CONNECTION.lock().unwrap();
+    if find_list(&conn, &name)?.is_some_and(|id| id != list_id) {
+        return Err(format!("A list named {} already exists", name));
+    }
+    let updated = conn
+        .execute(
+            "UPDATE custom_lists SET name = ?1, description = ?2 WHERE id = ?3",
+            params![name, description, list_id],
+        )
+        .map_err(|e| format!("Failed to update custom list: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Custom list {} does not exist", list_id));
+    }
+    db::mark_data_changed();
+    load_list(&conn, list_id)
+}
+
+#[tauri::command]
+pub fn delete_custom_list(list_id: i64) -> Result<bool, String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute("DELETE FROM custom_list_entries WHERE list_id = ?1", params![list_id])
+        .map_err(|e| format!("Failed to clear custom list: {}", e))?;
+    let deleted = tx
+        .execute("DELETE FROM custom_lists WHERE id = ?1", params![list_id])
+        .map_err(|e| format!("Failed to delete custom list: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    if deleted > 0 {
+        db::mark_data_changed();
+    }
+    Ok(deleted > 0)
+}
+
+// Appends to the end of the list; adding an entry twice is a no-op
+#[tauri::command]
+pub fn add_to_custom_list(list_id: i64, anime_id: i64) -> Result<CustomList, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    load_list(&conn, list_id)?;
+    if !on_list(&conn, anime_id)? {
+        return Err(format!("Anime {} is not on your list", anime_id));
+    }
+    if append_to_list(&conn, list_id, anime_id)? {
+        db::mark_data_changed();
+    }
+    load_list(&conn, list_id)
+}
+
+#[tauri::command]
+pub fn remove_from_custom_list(list_id: i64, anime_id: i64) -> Result<CustomList, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let removed = conn
+        .execute(
+            "DELETE FROM custom_list_entries WHERE list_id = ?1 AND anime_id = ?2",
+            params![list_id, anime_id],
+        )
+        .map_err(|e| format!("Failed to remove from custom list: {}", e))?;
+    if removed > 0 {
+        compact_positions(&conn, list_id)?;
+        db::mark_data_changed();
+    }
+    load_list(&conn, list_id)
+}
+
+// `anime_ids` must contain exactly the list's current entries, in the new order
+#[tauri::command]
+pub fn reorder_custom_list(list_id: i64, anime_ids: Vec<i64>) -> Result<CustomList, String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let current: HashSet<i64> = list_anime_ids(&tx, list_id)?.into_iter().collect();
+    let requested: HashSet<i64> = anime_ids.iter().copied().collect();
+    if requested.len() != anime_ids.len() || requested != current {
+        return Err("New order must list every entry of the custom list exactly once".to_string());
+    }
+
+    for (position, anime_id) in anime_ids.iter().enumerate() {
+        tx.execute(
+            "UPDATE custom_list_entries SET position = ?1 WHERE list_id = ?2 AND anime_id = ?3",
+            params![position as i64, list_id, anime_id],
+        )
+        .map_err(|e| format!("Failed to reorder custom list: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    db::mark_data_changed();
+    load_list(&conn, list_id)
+}
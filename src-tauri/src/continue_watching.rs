@@ -0,0 +1,136 @@
+use crate::airing::{self, AiringStatus};
+use crate::db::DB_CONNECTION;
+use crate::metadata_cache;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize)]
+pub struct ContinueWatchingEntry {
+    pub anime_id: i64,
+    pub title: String,
+    pub image_url: String,
+    pub progress: i32,
+    pub total_episodes: Option<i32>,
+    // None once every known episode has been watched
+    pub next_episode: Option<i32>,
+    pub local_file: Option<String>,
+    // None when there is no cached metadata to decide from
+    pub has_aired: Option<bool>,
+    // Next broadcast that has not happened yet, RFC 3339
+    pub next_airs_at: Option<String>,
+    pub next_airing_episode: Option<i32>,
+    pub last_watched_at: Option<String>,
+}
+
+// Prefer files that did not fail checksum verification
+fn local_file(conn: &Connection, anime_id: i64, episode: i32) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT path FROM library_files WHERE anime_id = ?1 AND episode = ?2
+         ORDER BY COALESCE(verify_status = 'mismatch', 0), file_name LIMIT 1",
+        params![anime_id, episode],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up library file: {}", e))
+}
+
+fn last_watched(conn: &Connection, anime_id: i64) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT MAX(watched_at) FROM watch_events WHERE anime_id = ?1",
+        params![anime_id],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .map_err(|e| format!("Failed to read watch history: {}", e))
+}
+
+pub(crate) fn build_feed(conn: &Connection) -> Result<Vec<ContinueWatchingEntry>, String> {
+    let metadata: HashMap<i64, Value> = metadata_cache::load_all(conn)?.into_iter().collect();
+    let now = chrono::Utc::now();
+
+    let watching: Vec<(i64, String, String, i32)> = {
+        let mut stmt = conn
+            .prepare("SELECT anime_id, title, image_url, progress FROM user_anime WHERE status = 'watching'")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(|e| format!("Failed to execute query: {}", e))?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| format!("Failed to retrieve row: {}", e))?
+    };
+
+    let mut feed = Vec::with_capacity(watching.len());
+    for (anime_id, title, image_url, progress) in watching {
+        let data = metadata.get(&anime_id);
+        let total_episodes = data.and_then(airing::total_episodes);
+
+        let next_episode = Some(progress + 1)
+            .filter(|next| total_episodes.map(|total| *next <= total).unwrap_or(true));
+        // The stored schedule is authoritative; fall back to estimating from metadata
+        let aired = match airing::aired_count(conn, anime_id, now)? {
+            Some(aired) => Some(aired),
+            None => data.and_then(|data| airing::aired_episodes(data, now)),
+        };
+        let has_aired = match (next_episode, aired) {
+            (Some(next), Some(aired)) => Some(next <= aired),
+            _ => None,
+        };
+
+        let (next_airing_episode, next_airs_at) = match airing::next_airing(conn, anime_id, now)? {
+            Some((episode, airs_at)) => (Some(episode), Some(airs_at.to_rfc3339())),
+            // Only shows still airing have a next broadcast
+            None => match (data, aired) {
+                (Some(data), Some(aired))
+                    if matches!(airing::airing_status(data), AiringStatus::Airing | AiringStatus::NotYetAired)
+                        && total_episodes.map(|total| aired < total).unwrap_or(true) =>
+                {
+                    let episode = aired + 1;
+                    (
+                        Some(episode),
+                        airing::estimated_air_time(data, episode).map(|time| time.to_rfc3339()),
+                    )
+                }
+                _ => (None, None),
+            },
+        };
+
+        let local_file = match next_episode {
+            Some(next) => local_file(conn, anime_id, next)?,
+            None => None,
+        };
+
+        feed.push(ContinueWatchingEntry {
+            anime_id,
+            title,
+            image_url,
+            progress,
+            total_episodes,
+            next_episode,
+            local_file,
+            has_aired,
+            next_airs_at,
+            next_airing_episode,
+            last_watched_at: last_watched(conn, anime_id)?,
+        });
+    }
+
+    // Ready-to-play episodes first, then whatever was watched most recently
+    feed.sort_by(|a, b| {
+        let ready = |entry: &ContinueWatchingEntry| {
+            entry.local_file.is_some() || entry.has_aired == Some(true)
+        };
+        ready(b)
+            .cmp(&ready(a))
+            .then_with(|| b.last_watched_at.cmp(&a.last_watched_at))
+            .then_with(|| a.title.cmp(&b.title))
+    });
+
+    Ok(feed)
+}
+
+#[tauri::command]
+pub fn get_continue_watching() -> Result<Vec<ContinueWatchingEntry>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    build_feed(&conn)
+}
@@ -1,13 +1,38 @@
+use crate::airing;
+use crate::collections::{self, CustomListExport};
+use crate::filesystem;
+use crate::franchise;
+use crate::goals;
+use crate::image_cache;
+use crate::library;
+use crate::metadata_cache;
+use crate::notifications;
+use crate::sandbox;
+use crate::seasonal;
+use crate::thumbnails;
+use crate::watch_history;
 use once_cell::sync::Lazy;
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, Connection, OptionalExtension, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
-static DB_CONNECTION: Lazy<Mutex<Connection>> =
+pub(crate) static DB_CONNECTION: Lazy<Mutex<Connection>> =
     Lazy::new(|| Mutex::new(initialize_db().expect("Failed to initialize database")));
 
+// Set whenever the user's list is modified, cleared once a backup has captured it
+static DATA_CHANGED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn mark_data_changed() {
+    DATA_CHANGED.store(true, Ordering::SeqCst);
+}
+
+pub(crate) fn take_data_changed() -> bool {
+    DATA_CHANGED.swap(false, Ordering::SeqCst)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserAnime {
     pub id: Option<i64>,
@@ -21,6 +46,10 @@ pub struct UserAnime {
     pub end_date: Option<String>,
     pub image_url: String,
     pub title: String,
+    // Read from the tags tables; saving an entry leaves its tags alone, they
+    // are changed through set_anime_tags
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,6 +58,9 @@ pub struct ExportData {
     pub timestamp: String,
     pub metadata: ExportMetadata,
     pub anime_list: Vec<UserAnime>,
+    // Missing from exports older than 1.2
+    #[serde(default)]
+    pub custom_lists: Vec<CustomListExport>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,7 +90,7 @@ pub struct ImportOptions {
 
 // For simplicity, use a fixed path for the database during development
 // In a production app, we would get this from the app_handle in a setup hook
-fn get_db_path() -> PathBuf {
+pub(crate) fn get_db_path() -> PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| {
         if cfg!(windows) {
             std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string())
@@ -75,6 +107,13 @@ fn get_db_path() -> PathBuf {
     app_data_dir.join("anime_database.db")
 }
 
+// Exports without an explicit path land next to the database
+pub(crate) const EXPORT_FILE_PREFIX: &str = "anitrack_export_";
+
+pub(crate) fn default_export_dir() -> PathBuf {
+    get_db_path().parent().unwrap().to_path_buf()
+}
+
 fn initialize_db() -> Result<Connection> {
     let db_path = get_db_path();
     let conn = Connection::open(db_path)?;
@@ -111,7 +150,7 @@ fn initialize_db() -> Result<Connection> {
         // Check if we need to add the new columns to an existing table
         let columns = conn
             .prepare("PRAGMA table_info(user_anime)")?
-            .query_map([], |row| Ok(row.get::<_, String>(1)?))?
+            .query_map([], |row| row.get::<_, String>(1))?
             .collect::<Result<Vec<String>>>()?;
 
         if !columns.contains(&"image_url".to_string()) {
@@ -129,14 +168,87 @@ fn initialize_db() -> Result<Connection> {
         }
     }
 
+    // Key/value store for settings owned by the Rust side (backup schedule, etc.)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    image_cache::init_tables(&conn)?;
+    thumbnails::init_tables(&conn)?;
+    metadata_cache::init_tables(&conn)?;
+    library::init_tables(&conn)?;
+    watch_history::init_tables(&conn)?;
+    airing::init_tables(&conn)?;
+    notifications::init_tables(&conn)?;
+    seasonal::init_tables(&conn)?;
+    franchise::init_tables(&conn)?;
+    goals::init_tables(&conn)?;
+    collections::init_tables(&conn)?;
+
     Ok(conn)
 }
 
+// Read a JSON-encoded setting, returning None if it has never been saved
+pub(crate) fn get_setting<T: serde::de::DeserializeOwned>(
+    conn: &Connection,
+    key: &str,
+) -> Result<Option<T>, String> {
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => serde_json::from_str(&value)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse setting {}: {}", key, e)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Failed to read setting {}: {}", key, e)),
+    }
+}
+
+pub(crate) fn set_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| format!("Failed to serialize setting {}: {}", key, e))?;
+
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2",
+        params![key, json],
+    )
+    .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+
+    Ok(())
+}
+
 #[tauri::command]
-pub fn add_user_anime(anime: UserAnime) -> Result<UserAnime, String> {
-    let conn = DB_CONNECTION.lock().unwrap();
+pub fn add_user_anime(app_handle: tauri::AppHandle, anime: UserAnime) -> Result<UserAnime, String> {
+    let saved = save_user_anime(anime)?;
+    // Status and end date changes can complete a goal
+    goals::check_goals(&app_handle);
+    Ok(saved)
+}
+
+fn save_user_anime(anime: UserAnime) -> Result<UserAnime, String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let previous_progress: i64 = tx
+        .query_row(
+            "SELECT progress FROM user_anime WHERE anime_id = ?1",
+            params![anime.anime_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read current progress: {}", e))?
+        .unwrap_or(0);
 
-    match conn.execute(
+    match tx.execute(
         "INSERT INTO user_anime (anime_id, status, score, progress, notes, favorite, start_date, end_date, image_url, title)
          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
          ON CONFLICT(anime_id) DO UPDATE SET
@@ -155,6 +267,14 @@ pub fn add_user_anime(anime: UserAnime) -> Result<UserAnime, String> {
         ],
     ) {
         Ok(_) => {
+            // Progress edits count as watching the episodes in between, so goals
+            // and streaks also work without the player integration
+            for episode in previous_progress + 1..=anime.progress {
+                watch_history::record_watch_event(&tx, anime.anime_id, episode as i32, "manual", None, None)?;
+            }
+            tx.commit()
+                .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+            mark_data_changed();
             match conn.query_row(
                 "SELECT id, anime_id, status, score, progress, notes, favorite, start_date, end_date, image_url, title
                  FROM user_anime WHERE anime_id = ?1",
@@ -172,10 +292,14 @@ pub fn add_user_anime(anime: UserAnime) -> Result<UserAnime, String> {
                         end_date: row.get(8)?,
                         image_url: row.get(9)?,
                         title: row.get(10)?,
+                        tags: Vec::new(),
                     })
                 },
             ) {
-                Ok(user_anime) => Ok(user_anime),
+                Ok(mut user_anime) => {
+                    user_anime.tags = collections::anime_tags(&conn, user_anime.anime_id)?;
+                    Ok(user_anime)
+                }
                 Err(err) => Err(format!("Failed to retrieve updated anime: {}", err)),
             }
         }
@@ -204,76 +328,75 @@ pub fn get_user_anime(anime_id: i64) -> Result<Option<UserAnime>, String> {
                 end_date: row.get(8)?,
                 image_url: row.get(9)?,
                 title: row.get(10)?,
+                tags: Vec::new(),
             })
         },
     ) {
-        Ok(user_anime) => Ok(Some(user_anime)),
+        Ok(mut user_anime) => {
+            user_anime.tags = collections::anime_tags(&conn, user_anime.anime_id)?;
+            Ok(Some(user_anime))
+        }
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
         Err(err) => Err(format!("Failed to get anime: {}", err)),
     }
 }
 
+// Lists the user's anime, optionally narrowed to a status, a tag name and/or
+// a custom list. Custom list results come back in the list's own order.
 #[tauri::command]
-pub fn list_user_anime(status: Option<String>) -> Result<Vec<UserAnime>, String> {
+pub fn list_user_anime(
+    status: Option<String>,
+    tag: Option<String>,
+    list_id: Option<i64>,
+) -> Result<Vec<UserAnime>, String> {
     let conn = DB_CONNECTION.lock().unwrap();
 
-    // Add debugging log for status parameter
-    println!("Filtering anime list with status: {:?}", status);
-
-    let sql = match status {
-        Some(_) => "SELECT id, anime_id, status, score, progress, notes, favorite, start_date, end_date, image_url, title 
-                  FROM user_anime WHERE status = ?1 ORDER BY id DESC",
-        None => "SELECT id, anime_id, status, score, progress, notes, favorite, start_date, end_date, image_url, title 
-               FROM user_anime ORDER BY id DESC",
-    };
-
     let mut stmt = conn
-        .prepare(sql)
+        .prepare(
+            "SELECT u.id, u.anime_id, u.status, u.score, u.progress, u.notes, u.favorite, u.start_date, u.end_date, u.image_url, u.title
+             FROM user_anime u
+             LEFT JOIN custom_list_entries e ON e.anime_id = u.anime_id AND e.list_id = ?3
+             WHERE (?1 IS NULL OR u.status = ?1)
+               AND (?2 IS NULL OR u.anime_id IN (
+                    SELECT a.anime_id FROM anime_tags a JOIN tags t ON t.id = a.tag_id WHERE t.name = ?2))
+               AND (?3 IS NULL OR e.list_id IS NOT NULL)
+             ORDER BY e.position, u.id DESC",
+        )
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let mut user_anime_list = Vec::new();
-
-    // Use a common function to create the UserAnime from a row
-    fn map_row(row: &rusqlite::Row) -> rusqlite::Result<UserAnime> {
-        Ok(UserAnime {
-            id: Some(row.get(0)?),
-            anime_id: row.get(1)?,
-            status: row.get(2)?,
-            score: row.get(3)?,
-            progress: row.get(4)?,
-            notes: row.get(5)?,
-            favorite: row.get(6)?,
-            start_date: row.get(7)?,
-            end_date: row.get(8)?,
-            image_url: row.get(9)?,
-            title: row.get(10)?,
+    let rows = stmt
+        .query_map(params![status, tag, list_id], |row| {
+            Ok(UserAnime {
+                id: Some(row.get(0)?),
+                anime_id: row.get(1)?,
+                status: row.get(2)?,
+                score: row.get(3)?,
+                progress: row.get(4)?,
+                notes: row.get(5)?,
+                favorite: row.get(6)?,
+                start_date: row.get(7)?,
+                end_date: row.get(8)?,
+                image_url: row.get(9)?,
+                title: row.get(10)?,
+                tags: Vec::new(),
+            })
         })
-    }
-
-    let rows = match status {
-        Some(s) => {
-            println!("Executing query with status: {}", s);
-            stmt.query_map(params![s], map_row)
-                .map_err(|e| format!("Failed to execute query: {}", e))?
-        }
-        None => {
-            println!("Executing query without status filter");
-            stmt.query_map([], map_row)
-                .map_err(|e| format!("Failed to execute query: {}", e))?
-        }
-    };
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
 
+    let mut tags = collections::tags_by_anime(&conn)?;
+    let mut user_anime_list = Vec::new();
     for user_anime in rows {
-        user_anime_list.push(user_anime.map_err(|e| format!("Failed to retrieve row: {}", e))?);
+        let mut user_anime = user_anime.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        user_anime.tags = tags.remove(&user_anime.anime_id).unwrap_or_default();
+        user_anime_list.push(user_anime);
     }
 
-    println!("Found {} anime in list", user_anime_list.len());
     Ok(user_anime_list)
 }
 
 #[tauri::command]
 pub fn delete_user_anime(anime_id: i64) -> Result<bool, String> {
-    let conn = DB_CONNECTION.lock().unwrap();
+    let mut conn = DB_CONNECTION.lock().unwrap();
 
     // First check if the anime exists
     let exists = match conn.query_row(
@@ -291,13 +414,20 @@ pub fn delete_user_anime(anime_id: i64) -> Result<bool, String> {
         return Ok(false);
     }
 
-    // Delete the anime
-    match conn.execute(
+    // Delete the anime along with its tags and list entries
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    match tx.execute(
         "DELETE FROM user_anime WHERE anime_id = ?1",
         params![anime_id],
     ) {
         Ok(rows) => {
             if rows > 0 {
+                collections::remove_anime(&tx, anime_id)?;
+                tx.commit()
+                    .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+                mark_data_changed();
                 Ok(true)
             } else {
                 // This should rarely happen since we checked existence
@@ -310,26 +440,35 @@ pub fn delete_user_anime(anime_id: i64) -> Result<bool, String> {
 
 #[tauri::command]
 pub fn export_user_data(
+    app_handle: tauri::AppHandle,
     export_path: Option<String>,
     export_type: Option<String>,
+    keep_backup: Option<bool>,
 ) -> Result<ExportResponse, String> {
     // Get current timestamp for the filename
     let now = chrono::Local::now();
     let timestamp = now.format("%Y%m%d_%H%M%S").to_string();
 
     // Determine which anime to export based on export_type
-    let anime_list = match export_type.as_ref().map(|s| s.as_str()) {
-        Some("watching") => list_user_anime(Some("watching".to_string()))?,
-        Some("completed") => list_user_anime(Some("completed".to_string()))?,
-        Some("planned") => list_user_anime(Some("planned".to_string()))?,
-        Some("dropped") => list_user_anime(Some("dropped".to_string()))?,
-        Some("on_hold") => list_user_anime(Some("on_hold".to_string()))?,
-        _ => list_user_anime(None)?, // Default to full export
+    let anime_list = match export_type.as_deref() {
+        Some("watching") => list_user_anime(Some("watching".to_string()), None, None)?,
+        Some("completed") => list_user_anime(Some("completed".to_string()), None, None)?,
+        // The settings page sends "planned" for the plan to watch export
+        Some("planned") | Some("plan_to_watch") => {
+            list_user_anime(Some("plan_to_watch".to_string()), None, None)?
+        }
+        Some("dropped") => list_user_anime(Some("dropped".to_string()), None, None)?,
+        Some("on_hold") => list_user_anime(Some("on_hold".to_string()), None, None)?,
+        _ => list_user_anime(None, None, None)?, // Default to full export
+    };
+    let custom_lists = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        collections::export_lists(&conn)?
     };
 
     // Create the export data object with metadata
     let export_data = ExportData {
-        version: "1.1".to_string(),
+        version: "1.2".to_string(),
         timestamp: now.to_rfc3339(),
         metadata: ExportMetadata {
             app_version: env!("CARGO_PKG_VERSION").to_string(),
@@ -341,6 +480,7 @@ pub fn export_user_data(
             entry_count: anime_list.len(),
         },
         anime_list,
+        custom_lists,
     };
 
     // Serialize to JSON
@@ -351,31 +491,21 @@ pub fn export_user_data(
 
     // Determine the export path
     let export_file_path = match export_path {
-        Some(path) => PathBuf::from(path),
+        Some(path) => sandbox::check_path(&app_handle, Path::new(&path))?,
         None => {
             // Use the app's data directory for default export
-            let mut default_path = get_db_path().parent().unwrap().to_path_buf();
+            let mut default_path = default_export_dir();
             let export_type_str = export_type.as_ref().unwrap_or(&"full".to_string()).clone();
             default_path.push(format!(
-                "anitrack_export_{}_{}.json",
-                export_type_str, timestamp
+                "{}{}_{}.json",
+                EXPORT_FILE_PREFIX, export_type_str, timestamp
             ));
             default_path
         }
     };
 
-    // Create export directory if it doesn't exist
-    if let Some(parent) = export_file_path.parent() {
-        if !parent.exists() {
-            match fs::create_dir_all(parent) {
-                Ok(_) => {}
-                Err(e) => return Err(format!("Failed to create export directory: {}", e)),
-            }
-        }
-    }
-
-    // Write to file
-    match fs::write(&export_file_path, json_data) {
+    // Write to file; atomic_write creates the export directory if needed
+    match filesystem::atomic_write(&export_file_path, json_data.as_bytes(), keep_backup.unwrap_or(false)) {
         Ok(_) => Ok(ExportResponse {
             path: export_file_path.to_string_lossy().to_string(),
             entry_count: export_data.metadata.entry_count,
@@ -386,10 +516,20 @@ pub fn export_user_data(
     }
 }
 
+fn import_tags(conn: &Connection, anime: &UserAnime, problems: &mut Vec<String>) {
+    if let Err(e) = collections::add_anime_tags(conn, anime.anime_id, &anime.tags) {
+        problems.push(format!("Tags of \"{}\": {}", anime.title, e));
+    }
+}
+
 #[tauri::command]
-pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String> {
+pub fn import_user_data(
+    app_handle: tauri::AppHandle,
+    options: ImportOptions,
+) -> Result<ImportResult, String> {
     // Read the import file
-    let json_data = match fs::read_to_string(&options.import_path) {
+    let import_path = sandbox::check_path(&app_handle, Path::new(&options.import_path))?;
+    let json_data = match fs::read_to_string(&import_path) {
         Ok(data) => data,
         Err(e) => return Err(format!("Failed to read import file: {}", e)),
     };
@@ -425,11 +565,14 @@ pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String>
 
     // Get database connection
     let conn = DB_CONNECTION.lock().unwrap();
+    // Tags and lists that fail to import are reported without stopping the import
+    let mut problems: Vec<String> = Vec::new();
 
     // If replacing all data, clear the existing data
     if options.merge_strategy == "replace" {
         match conn.execute("DELETE FROM user_anime", []) {
             Ok(_) => {
+                collections::clear_all(&conn)?;
                 println!("Cleared existing anime data for full import");
             }
             Err(e) => {
@@ -458,6 +601,7 @@ pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String>
                     end_date: row.get(8)?,
                     image_url: row.get(9)?,
                     title: row.get(10)?,
+                    tags: Vec::new(),
                 })
             }
         ) {
@@ -482,6 +626,7 @@ pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String>
                     ],
                 ) {
                     Ok(_) => {
+                        import_tags(&conn, anime, &mut problems);
                         import_result.imported_entries += 1;
                     },
                     Err(e) => {
@@ -510,6 +655,7 @@ pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String>
                     ],
                 ) {
                     Ok(_) => {
+                        import_tags(&conn, anime, &mut problems);
                         import_result.updated_entries += 1;
                     },
                     Err(e) => {
@@ -556,6 +702,7 @@ pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String>
                         ],
                     ) {
                         Ok(_) => {
+                            import_tags(&conn, anime, &mut problems);
                             import_result.updated_entries += 1;
                         },
                         Err(e) => {
@@ -582,6 +729,7 @@ pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String>
                     ],
                 ) {
                     Ok(_) => {
+                        import_tags(&conn, anime, &mut problems);
                         import_result.updated_entries += 1;
                     },
                     Err(e) => {
@@ -592,6 +740,19 @@ pub fn import_user_data(options: ImportOptions) -> Result<ImportResult, String>
         }
     }
 
+    // Lists are merged after the entries so they can refer to new ones
+    problems.extend(collections::import_lists(&conn, &import_data.custom_lists));
+    if !problems.is_empty() {
+        import_result.error_message = Some(problems.join("; "));
+    }
+
+    if import_result.imported_entries > 0
+        || import_result.updated_entries > 0
+        || options.merge_strategy == "replace"
+    {
+        mark_data_changed();
+    }
+
     Ok(import_result)
 }
 
@@ -614,17 +775,17 @@ pub fn get_anime_by_id(anime_id: i64) -> Result<Option<UserAnime>, String> {
 
 #[tauri::command]
 pub fn get_anime_list(status: Option<String>) -> Result<Vec<UserAnime>, String> {
-    list_user_anime(status)
+    list_user_anime(status, None, None)
 }
 
 #[tauri::command]
-pub fn add_anime_to_list(anime: UserAnime) -> Result<UserAnime, String> {
-    add_user_anime(anime)
+pub fn add_anime_to_list(app_handle: tauri::AppHandle, anime: UserAnime) -> Result<UserAnime, String> {
+    add_user_anime(app_handle, anime)
 }
 
 #[tauri::command]
-pub fn update_anime_in_list(anime: UserAnime) -> Result<UserAnime, String> {
-    add_user_anime(anime) // We're using the same function as it handles updates too
+pub fn update_anime_in_list(app_handle: tauri::AppHandle, anime: UserAnime) -> Result<UserAnime, String> {
+    add_user_anime(app_handle, anime) // We're using the same function as it handles updates too
 }
 
 #[tauri::command]
@@ -641,7 +802,7 @@ pub fn get_anime_stats() -> Result<AnimeStats, String> {
     let completed = count_anime_by_status(&conn, "completed")?;
     let on_hold = count_anime_by_status(&conn, "on_hold")?;
     let dropped = count_anime_by_status(&conn, "dropped")?;
-    let plan_to_watch = count_anime_by_status(&conn, "planned")?;
+    let plan_to_watch = count_anime_by_status(&conn, "plan_to_watch")?;
 
     // Get total episodes watched
     let total_episodes = conn
@@ -687,8 +848,12 @@ pub fn search_anime_in_list(query: String) -> Result<Vec<UserAnime>, String> {
     let mut stmt = conn
         .prepare(
             "SELECT id, anime_id, status, score, progress, notes, favorite, start_date, end_date, image_url, title
-             FROM user_anime 
+             FROM user_anime
              WHERE title LIKE ?1
+                OR anime_id IN (SELECT a.anime_id FROM anime_tags a JOIN tags t ON t.id = a.tag_id
+                                WHERE t.name LIKE ?1)
+                OR anime_id IN (SELECT e.anime_id FROM custom_list_entries e JOIN custom_lists l ON l.id = e.list_id
+                                WHERE l.name LIKE ?1)
              ORDER BY id DESC"
         )
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
@@ -711,12 +876,16 @@ pub fn search_anime_in_list(query: String) -> Result<Vec<UserAnime>, String> {
                 end_date: row.get(8)?,
                 image_url: row.get(9)?,
                 title: row.get(10)?,
+                tags: Vec::new(),
             })
         })
         .map_err(|e| format!("Failed to execute query: {}", e))?;
 
+    let mut tags = collections::tags_by_anime(&conn)?;
     for user_anime in rows {
-        user_anime_list.push(user_anime.map_err(|e| format!("Failed to retrieve row: {}", e))?);
+        let mut user_anime = user_anime.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        user_anime.tags = tags.remove(&user_anime.anime_id).unwrap_or_default();
+        user_anime_list.push(user_anime);
     }
 
     Ok(user_anime_list)
@@ -1,12 +1,15 @@
+use crate::backup_crypto;
+use crate::image_cache;
+use crate::sandbox;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tauri::Manager;
 use std::io::{Read, Write, Seek};
 use serde::Serialize;
 use zip::ZipWriter;
 use zip::write::FileOptions;
 
-#[derive(serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize, Serialize)]
 pub enum StorageLocation {
     Documents,
     Home,
@@ -14,10 +17,25 @@ pub enum StorageLocation {
     Custom,
 }
 
-#[derive(Serialize)]
-pub struct CacheStats {
-    size_in_bytes: u64,
-    image_count: usize,
+// Walk a directory tree depth-first, calling `visit` for every entry with a
+// flag telling whether it is a directory. Directories are visited before their contents.
+pub(crate) fn walk_dir<F>(path: &Path, visit: &mut F) -> Result<(), String>
+where
+    F: FnMut(&Path, bool) -> Result<(), String>,
+{
+    for entry in fs::read_dir(path).map_err(|e| format!("Failed to read directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+
+        if path.is_file() {
+            visit(&path, false)?;
+        } else if path.is_dir() {
+            visit(&path, true)?;
+            walk_dir(&path, visit)?;
+        }
+    }
+
+    Ok(())
 }
 
 // Helper function for adding directories to a zip file
@@ -27,20 +45,17 @@ pub fn add_dir_to_zip<T: Write + Seek>(
     zip: &mut ZipWriter<T>,
     options: &FileOptions,
 ) -> Result<(), String> {
-    for entry in fs::read_dir(path).map_err(|e| format!("Failed to read directory: {}", e))? {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let path = entry.path();
-        
+    walk_dir(path, &mut |path, is_dir| {
         let name = path.strip_prefix(prefix)
             .map_err(|e| format!("Failed to strip prefix: {}", e))?;
         
-        if path.is_file() {
+        if !is_dir {
             zip.start_file(
                 name.to_string_lossy().into_owned(),
                 *options,
             ).map_err(|e| format!("Failed to add file to zip: {}", e))?;
             
-            let mut file = fs::File::open(&path)
+            let mut file = fs::File::open(path)
                 .map_err(|e| format!("Failed to open file: {}", e))?;
             
             let mut buffer = Vec::new();
@@ -49,17 +64,15 @@ pub fn add_dir_to_zip<T: Write + Seek>(
             
             zip.write_all(&buffer)
                 .map_err(|e| format!("Failed to write file to zip: {}", e))?;
-        } else if path.is_dir() {
+        } else {
             zip.add_directory(
                 name.to_string_lossy().into_owned(),
                 *options,
             ).map_err(|e| format!("Failed to add directory to zip: {}", e))?;
-            
-            add_dir_to_zip(prefix, &path, zip, options)?;
         }
-    }
-    
-    Ok(())
+
+        Ok(())
+    })
 }
 
 #[tauri::command]
@@ -70,46 +83,7 @@ pub fn create_directory(
     custom_path: Option<String>,
     recursive: bool,
 ) -> Result<String, String> {
-    let path = match location {
-        StorageLocation::Documents => {
-            app_handle
-                .path()
-                .document_dir()
-                .map_err(|e| format!("Failed to get document directory: {}", e))?
-                .join(folder_name)
-                .to_str()
-                .unwrap()
-                .to_string()
-        }
-        StorageLocation::Home => {
-            app_handle
-                .path()
-                .home_dir()
-                .map_err(|e| format!("Failed to get home directory: {}", e))?
-                .join(folder_name)
-                .to_str()
-                .unwrap()
-                .to_string()
-        }
-        StorageLocation::AppData => {
-            app_handle
-                .path()
-                .app_data_dir()
-                .map_err(|e| format!("Failed to get app data directory: {}", e))?
-                .join(folder_name)
-                .to_str()
-                .unwrap()
-                .to_string()
-        }
-        StorageLocation::Custom => {
-            if let Some(custom) = custom_path {
-                let path = Path::new(&custom).join(&folder_name);
-                path.to_str().unwrap().to_string()
-            } else {
-                return Err("Custom path is required for custom location".to_string());
-            }
-        }
-    };
+    let path = get_full_path(app_handle, folder_name, location, custom_path)?;
 
     // Create the directory
     if recursive {
@@ -135,8 +109,15 @@ pub fn file_exists(
     location: StorageLocation,
     custom_path: Option<String>,
 ) -> Result<bool, String> {
-    let full_path = get_full_path(app_handle, file_path, location, custom_path)?;
-    Ok(Path::new(&full_path).exists())
+    let full_path = get_full_path(app_handle.clone(), file_path, location, custom_path)?;
+    let exists = Path::new(&full_path).exists();
+    
+    // ImageCacheService checks existence before every cache hit, which makes it the access signal for LRU
+    if exists {
+        image_cache::record_access(&app_handle, Path::new(&full_path));
+    }
+    
+    Ok(exists)
 }
 
 #[tauri::command]
@@ -165,80 +146,142 @@ pub fn write_file(
     content: String,
     location: StorageLocation,
     custom_path: Option<String>,
+    keep_backup: Option<bool>,
 ) -> Result<(), String> {
     let full_path = get_full_path(app_handle, file_path, location, custom_path)?;
     
+    atomic_write(Path::new(&full_path), content.as_bytes(), keep_backup.unwrap_or(false))
+}
+
+// Write `contents` to a temporary file next to `path`, fsync it and rename it
+// over the target, so readers only ever see the old or the new file. With
+// `keep_backup` the previous version is kept as `<name>.bak`.
+pub(crate) fn atomic_write(path: &Path, contents: &[u8], keep_backup: bool) -> Result<(), String> {
+    atomic_write_with(path, keep_backup, |file| {
+        file.write_all(contents)
+            .map_err(|e| format!("Failed to write file {}: {}", path.display(), e))
+    })
+}
+
+// Same as atomic_write for content produced by a writer, such as a zip archive:
+// `write` fills the temporary file, which only replaces `path` once complete
+pub(crate) fn atomic_write_with<F>(path: &Path, keep_backup: bool, write: F) -> Result<(), String>
+where
+    F: FnOnce(&mut fs::File) -> Result<(), String>,
+{
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?
+        .to_string_lossy()
+        .to_string();
+    
     // Ensure the parent directory exists
-    if let Some(parent) = Path::new(&full_path).parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directories: {}", e))?;
-        }
+    if !parent.exists() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create parent directories: {}", e))?;
     }
     
-    let mut file = fs::File::create(&full_path)
-        .map_err(|e| format!("Failed to create file {}: {}", full_path, e))?;
+    // Uniquely named, so concurrent writers never share a temp file; it is
+    // removed when dropped if anything below fails
+    let mut temp = tempfile::Builder::new()
+        .prefix(&format!(".{}.", file_name))
+        .suffix(".tmp")
+        .tempfile_in(parent)
+        .map_err(|e| format!("Failed to write file {}: {}", path.display(), e))?;
+    // Keep the permissions of the file being replaced instead of the temp file's
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = temp.as_file().set_permissions(metadata.permissions());
+    }
+    write(temp.as_file_mut())?;
+    temp.as_file()
+        .sync_all()
+        .map_err(|e| format!("Failed to write file {}: {}", path.display(), e))?;
+    
+    if keep_backup && path.exists() {
+        let backup_path = parent.join(format!("{}.bak", file_name));
+        fs::copy(path, &backup_path)
+            .and_then(|_| fs::OpenOptions::new().write(true).open(&backup_path))
+            .and_then(|backup| backup.sync_all())
+            .map_err(|e| format!("Failed to keep previous version of {}: {}", path.display(), e))?;
+    }
     
-    file.write_all(content.as_bytes())
-        .map_err(|e| format!("Failed to write to file {}: {}", full_path, e))?;
+    temp.persist(path)
+        .map_err(|e| format!("Failed to replace file {}: {}", path.display(), e.error))?;
     
+    sync_dir(parent)
+}
+
+// Private scratch file for intermediate plaintext such as the zip of an
+// encrypted backup. It lives in `dir` rather than the shared temp directory,
+// is only readable by the current user and is removed when dropped.
+pub(crate) fn scratch_file(dir: &Path) -> Result<tempfile::NamedTempFile, String> {
+    tempfile::Builder::new()
+        .prefix(".")
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .map_err(|e| format!("Failed to create temporary file in {}: {}", dir.display(), e))
+}
+
+// Persist the rename itself; directories cannot be opened for syncing on Windows
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<(), String> {
+    fs::File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| format!("Failed to sync directory {}: {}", dir.display(), e))
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<(), String> {
     Ok(())
 }
 
-// Helper function to get full path based on location
+// Helper function to get full path based on location. The file path must be
+// relative, and the joined result has to stay inside the sandbox roots.
 fn get_full_path(
     app_handle: tauri::AppHandle,
     file_path: String,
     location: StorageLocation,
     custom_path: Option<String>,
 ) -> Result<String, String> {
-    let path = match location {
-        StorageLocation::Documents => {
-            app_handle
-                .path()
-                .document_dir()
-                .map_err(|e| format!("Failed to get document directory: {}", e))?
-                .join(file_path)
-                .to_str()
-                .unwrap()
-                .to_string()
-        }
-        StorageLocation::Home => {
-            app_handle
-                .path()
-                .home_dir()
-                .map_err(|e| format!("Failed to get home directory: {}", e))?
-                .join(file_path)
-                .to_str()
-                .unwrap()
-                .to_string()
-        }
-        StorageLocation::AppData => {
-            app_handle
-                .path()
-                .app_data_dir()
-                .map_err(|e| format!("Failed to get app data directory: {}", e))?
-                .join(file_path)
-                .to_str()
-                .unwrap()
-                .to_string()
-        }
-        StorageLocation::Custom => {
-            if let Some(custom) = custom_path {
-                let path = Path::new(&custom).join(&file_path);
-                path.to_str().unwrap().to_string()
-            } else {
-                return Err("Custom path is required for custom location".to_string());
-            }
-        }
-    };
+    let relative = sandbox::check_relative(&file_path)?;
+    let base = get_location_dir(&app_handle, &location, custom_path.as_deref())?;
+    let path = sandbox::check_path(&app_handle, &base.join(relative))?;
     
-    Ok(path)
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Resolve the base directory for a storage location without joining a file name
+pub(crate) fn get_location_dir(
+    app_handle: &tauri::AppHandle,
+    location: &StorageLocation,
+    custom_path: Option<&str>,
+) -> Result<PathBuf, String> {
+    match location {
+        StorageLocation::Documents => app_handle
+            .path()
+            .document_dir()
+            .map_err(|e| format!("Failed to get document directory: {}", e)),
+        StorageLocation::Home => app_handle
+            .path()
+            .home_dir()
+            .map_err(|e| format!("Failed to get home directory: {}", e)),
+        StorageLocation::AppData => app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get app data directory: {}", e)),
+        StorageLocation::Custom => match custom_path {
+            Some(custom) if !custom.is_empty() => Ok(PathBuf::from(custom)),
+            _ => Err("Custom path is required for custom location".to_string()),
+        },
+    }
 }
 
 #[tauri::command]
-pub fn create_dir_if_not_exists(path: String) -> Result<(), String> {
-    let path = Path::new(&path);
+pub fn create_dir_if_not_exists(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    let path = sandbox::check_path(&app_handle, Path::new(&path))?;
     if !path.exists() {
         fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))?;
     }
@@ -246,7 +289,8 @@ pub fn create_dir_if_not_exists(path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn get_file_info(path: String) -> Result<serde_json::Value, String> {
+pub fn get_file_info(app_handle: tauri::AppHandle, path: String) -> Result<serde_json::Value, String> {
+    let path = sandbox::check_path(&app_handle, Path::new(&path))?;
     let metadata = fs::metadata(&path).map_err(|e| format!("Failed to get file metadata: {}", e))?;
     
     let size = metadata.len();
@@ -264,17 +308,16 @@ pub fn get_file_info(path: String) -> Result<serde_json::Value, String> {
 }
 
 #[tauri::command]
-pub fn write_binary_file(path: String, contents: Vec<u8>) -> Result<(), String> {
-    // Ensure the parent directory exists
-    if let Some(parent) = Path::new(&path).parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directories: {}", e))?;
-        }
-    }
+pub fn write_binary_file(
+    app_handle: tauri::AppHandle,
+    path: String,
+    contents: Vec<u8>,
+    keep_backup: Option<bool>,
+) -> Result<(), String> {
+    let path = sandbox::check_path(&app_handle, Path::new(&path))?;
     
-    fs::write(&path, contents)
-        .map_err(|e| format!("Failed to write binary file: {}", e))?;
+    atomic_write(&path, &contents, keep_backup.unwrap_or(false))?;
+    image_cache::record_write(&app_handle, &path, contents.len() as u64);
     
     Ok(())
 }
@@ -287,24 +330,15 @@ pub fn get_documents_dir() -> Result<String, String> {
     Ok(home.to_string_lossy().to_string())
 }
 
-#[tauri::command]
-pub fn create_zip_archive(source_dir: String, output_path: String) -> Result<(), String> {
-    let source_path = Path::new(&source_dir);
-    if !source_path.exists() {
-        return Err(format!("Source directory does not exist: {}", source_dir));
-    }
-    
-    // Create a file to write the zip to
-    let file = fs::File::create(&output_path)
-        .map_err(|e| format!("Failed to create zip file: {}", e))?;
-    
+// Zip everything under `source` into `file`
+fn write_dir_zip(file: &mut fs::File, source: &Path) -> Result<(), String> {
     let mut zip = ZipWriter::new(file);
     let options = FileOptions::default()
         .compression_method(zip::CompressionMethod::Deflated)
         .unix_permissions(0o755);
     
     // Add all files and directories from the source directory
-    add_dir_to_zip(source_path, source_path, &mut zip, &options)?;
+    add_dir_to_zip(source, source, &mut zip, &options)?;
     
     // Finalize the zip file
     zip.finish().map_err(|e| format!("Failed to finalize zip file: {}", e))?;
@@ -313,92 +347,88 @@ pub fn create_zip_archive(source_dir: String, output_path: String) -> Result<(),
 }
 
 #[tauri::command]
-pub fn clear_image_cache(cache_path: String) -> Result<(), String> {
-    let path = Path::new(&cache_path);
-    if !path.exists() {
-        return Ok(());  // Nothing to clear
-    }
-    
-    // Read the directory
-    let entries = fs::read_dir(path)
-        .map_err(|e| format!("Failed to read cache directory: {}", e))?;
-    
-    // Delete each file
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let file_path = entry.path();
-        
-        if file_path.is_file() {
-            fs::remove_file(&file_path)
-                .map_err(|e| format!("Failed to delete file {}: {}", file_path.display(), e))?;
-        }
+pub fn create_zip_archive(
+    app_handle: tauri::AppHandle,
+    source_dir: String,
+    output_path: String,
+) -> Result<(), String> {
+    let source_path = sandbox::check_path(&app_handle, Path::new(&source_dir))?;
+    let output_path = sandbox::check_path(&app_handle, Path::new(&output_path))?;
+    if !source_path.exists() {
+        return Err(format!("Source directory does not exist: {}", source_dir));
     }
     
-    Ok(())
+    atomic_write_with(&output_path, false, |file| write_dir_zip(file, &source_path))
 }
 
 #[tauri::command]
-pub fn get_cache_stats(cache_path: String) -> Result<CacheStats, String> {
-    let path = Path::new(&cache_path);
+pub fn clear_image_cache(app_handle: tauri::AppHandle, cache_path: String) -> Result<(), String> {
+    let path = sandbox::check_path(&app_handle, Path::new(&cache_path))?;
     if !path.exists() {
-        return Ok(CacheStats {
-            size_in_bytes: 0,
-            image_count: 0,
-        });
+        return Ok(());  // Nothing to clear
     }
     
-    let mut total_size = 0;
-    let mut image_count = 0;
-    
     // Read the directory
-    let entries = fs::read_dir(path)
+    let entries = fs::read_dir(&path)
         .map_err(|e| format!("Failed to read cache directory: {}", e))?;
     
-    // Calculate total size and count
+    // Delete everything inside, including the objects/ and variants/ trees
     for entry in entries {
         let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
         let file_path = entry.path();
         
-        if file_path.is_file() {
-            let metadata = fs::metadata(&file_path)
-                .map_err(|e| format!("Failed to get file metadata: {}", e))?;
-            
-            total_size += metadata.len();
-            image_count += 1;
+        if file_path.is_dir() {
+            fs::remove_dir_all(&file_path)
+                .map_err(|e| format!("Failed to delete folder {}: {}", file_path.display(), e))?;
+        } else {
+            fs::remove_file(&file_path)
+                .map_err(|e| format!("Failed to delete file {}: {}", file_path.display(), e))?;
         }
     }
     
-    Ok(CacheStats {
-        size_in_bytes: total_size,
-        image_count,
-    })
+    if path == image_cache::get_cache_dir(&app_handle)? {
+        image_cache::forget_all()?;
+    }
+    
+    Ok(())
 }
 
 #[tauri::command]
-pub fn create_backup_zip(source_dir: String, destination_dir: String, zip_name: String) -> Result<String, String> {
-    let source_path = Path::new(&source_dir);
+pub fn create_backup_zip(
+    app_handle: tauri::AppHandle,
+    source_dir: String,
+    destination_dir: String,
+    zip_name: String,
+    password: Option<String>,
+) -> Result<String, String> {
+    let source_path = sandbox::check_path(&app_handle, Path::new(&source_dir))?;
     if !source_path.exists() {
         return Err(format!("Source directory does not exist: {}", source_dir));
     }
     
     // Combine destination path and zip name
-    let output_path = Path::new(&destination_dir).join(zip_name);
-    let output_path_str = output_path.to_string_lossy().to_string();
-    
-    // Create a file to write the zip to
-    let file = fs::File::create(&output_path)
-        .map_err(|e| format!("Failed to create zip file: {}", e))?;
-    
-    let mut zip = ZipWriter::new(file);
-    let options = FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o755);
-    
-    // Add all files and directories from the source directory
-    add_dir_to_zip(source_path, source_path, &mut zip, &options)?;
-    
-    // Finalize the zip file
-    zip.finish().map_err(|e| format!("Failed to finalize zip file: {}", e))?;
-    
-    Ok(output_path_str)
-} 
\ No newline at end of file
+    let destination = sandbox::check_path(&app_handle, Path::new(&destination_dir))?;
+    let output_path = destination.join(sandbox::check_relative(&zip_name)?);
+    
+    match password {
+        Some(password) => {
+            // The plain zip is only a scratch file that is removed once sealed
+            let mut scratch = scratch_file(&destination)?;
+            write_dir_zip(scratch.as_file_mut(), &source_path)?;
+            
+            let encrypted_path = destination.join(format!(
+                "{}.{}",
+                zip_name,
+                backup_crypto::ENCRYPTED_EXTENSION
+            ));
+            atomic_write_with(&encrypted_path, false, |file| {
+                backup_crypto::encrypt_file(scratch.path(), file, &password)
+            })?;
+            Ok(encrypted_path.to_string_lossy().to_string())
+        }
+        None => {
+            atomic_write_with(&output_path, false, |file| write_dir_zip(file, &source_path))?;
+            Ok(output_path.to_string_lossy().to_string())
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::api;
+use crate::db::DB_CONNECTION;
+use crate::metadata_cache;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+// Relations that stay within one franchise. Spin-offs, alternative settings,
+// adaptations and character cameos are left out so that long-running
+// multi-series franchises do not collapse into one huge group.
+const FRANCHISE_RELATIONS: [&str; 6] = [
+    "sequel",
+    "prequel",
+    "side story",
+    "parent story",
+    "full story",
+    "summary",
+];
+
+// Upper bound on Jikan lookups for members whose relations are not cached yet
+const MAX_FETCHES: usize = 30;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FranchiseMember {
+    pub anime_id: i64,
+    pub title: String,
+    // TV, Movie, OVA, ... when the metadata is cached
+    pub media_type: Option<String>,
+    pub aired_from: Option<String>,
+    pub episodes: Option<i64>,
+    // "missing", "not_completed" or "completed"
+    pub list_state: String,
+    pub user_status: Option<String>,
+    pub progress: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Franchise {
+    pub root_id: i64,
+    // "chronological" or "release"
+    pub order: String,
+    pub members: Vec<FranchiseMember>,
+    pub missing: Vec<i64>,
+    pub not_completed: Vec<i64>,
+    // False when some members' relations have never been cached, so the group
+    // may be incomplete
+    pub fully_resolved: bool,
+}
+
+struct Relation {
+    target_id: i64,
+    relation: String,
+    title: String,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    // Directed edges as Jikan reports them: `target` is the `relation` of `source`
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS anime_relations (
+            source_id INTEGER NOT NULL,
+            target_id INTEGER NOT NULL,
+            relation TEXT NOT NULL,
+            target_title TEXT NOT NULL,
+            PRIMARY KEY (source_id, target_id, relation)
+        )",
+        [],
+    )?;
+    // Sources whose relations were stored, including ones with no relations
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS anime_relations_fetched (
+            anime_id INTEGER PRIMARY KEY,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+// Stores the `relations` array of a Jikan /anime/{id}/full or
+// /anime/{id}/relations response, keeping only anime entries
+pub(crate) fn store_relations(conn: &Connection, anime_id: i64, relations: &[Value]) -> Result<(), String> {
+    conn.execute("DELETE FROM anime_relations WHERE source_id = ?1", params![anime_id])
+        .map_err(|e| format!("Failed to clear anime relations: {}", e))?;
+
+    for group in relations {
+        let relation = group.get("relation").and_then(Value::as_str).unwrap_or_default();
+        for entry in group.get("entry").and_then(Value::as_array).into_iter().flatten() {
+            if entry.get("type").and_then(Value::as_str) != Some("anime") {
+                continue;
+            }
+            let target_id = match entry.get("mal_id").and_then(Value::as_i64) {
+                Some(id) => id,
+                None => continue,
+            };
+            let title = entry.get("name").and_then(Value::as_str).unwrap_or_default();
+            conn.execute(
+                "INSERT OR REPLACE INTO anime_relations (source_id, target_id, relation, target_title)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![anime_id, target_id, relation.to_lowercase(), title],
+            )
+            .map_err(|e| format!("Failed to store anime relations: {}", e))?;
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO anime_relations_fetched (anime_id, fetched_at) VALUES (?1, ?2)
+         ON CONFLICT(anime_id) DO UPDATE SET fetched_at = ?2",
+        params![anime_id, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Failed to store anime relations: {}", e))?;
+    Ok(())
+}
+
+fn relations_of(conn: &Connection, anime_id: i64) -> Result<Option<Vec<Relation>>, String> {
+    let fetched: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM anime_relations_fetched WHERE anime_id = ?1)",
+            params![anime_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read anime relations: {}", e))?;
+    if !fetched {
+        return Ok(None);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT target_id, relation, target_title FROM anime_relations WHERE source_id = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![anime_id], |row| {
+            Ok(Relation {
+                target_id: row.get(0)?,
+                relation: row.get(1)?,
+                title: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map(Some)
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+fn incoming(conn: &Connection, anime_id: i64) -> Result<Vec<(i64, String)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT source_id, relation FROM anime_relations WHERE target_id = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![anime_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+// Breadth-first walk over franchise relations from the cache. Returns every
+// member reached with the titles seen on edges, the franchise edges, and the
+// members whose own relations are not cached.
+#[allow(clippy::type_complexity)]
+fn walk(
+    conn: &Connection,
+    root_id: i64,
+) -> Result<(BTreeMap<i64, String>, Vec<(i64, i64, String)>, Vec<i64>), String> {
+    let mut members = BTreeMap::new();
+    let mut edges = HashSet::new();
+    let mut unresolved = Vec::new();
+    let mut queue = VecDeque::from([root_id]);
+    let mut seen = HashSet::from([root_id]);
+    members.insert(root_id, String::new());
+
+    while let Some(anime_id) = queue.pop_front() {
+        let relations = relations_of(conn, anime_id)?.unwrap_or_else(|| {
+            unresolved.push(anime_id);
+            Vec::new()
+        });
+        for relation in relations {
+            if !FRANCHISE_RELATIONS.contains(&relation.relation.as_str()) {
+                continue;
+            }
+            edges.insert((anime_id, relation.target_id, relation.relation));
+            members.entry(relation.target_id).or_insert(relation.title);
+            if seen.insert(relation.target_id) {
+                queue.push_back(relation.target_id);
+            }
+        }
+
+        // Jikan does not always list the reverse edge, so also follow cached
+        // entries that point at this one
+        for (source_id, relation) in incoming(conn, anime_id)? {
+            if !FRANCHISE_RELATIONS.contains(&relation.as_str()) {
+                continue;
+            }
+            edges.insert((source_id, anime_id, relation));
+            members.entry(source_id).or_default();
+            if seen.insert(source_id) {
+                queue.push_back(source_id);
+            }
+        }
+    }
+    Ok((members, edges.into_iter().collect(), unresolved))
+}
+
+async fn fetch_unresolved(root_id: i64) -> Result<(), String> {
+    let mut fetched = 0;
+    loop {
+        let unresolved = {
+            let conn = DB_CONNECTION.lock().unwrap();
+            walk(&conn, root_id)?.2
+        };
+        let next = match unresolved.first() {
+            Some(next) if fetched < MAX_FETCHES => *next,
+            _ => return Ok(()),
+        };
+
+        if fetched > 0 {
+            tokio::time::sleep(api::JIKAN_REQUEST_DELAY).await;
+        }
+        let body = api::jikan_get(&format!("/anime/{}/full", next)).await?;
+        let data = body.get("data").cloned().unwrap_or(Value::Null);
+        let has_relations = data.get("relations").is_some_and(Value::is_array);
+        if data.is_object() {
+            // Caches the relations as well
+            metadata_cache::cache_anime_metadata(vec![data])?;
+        }
+        // Mark it resolved regardless so it is not fetched again
+        if !has_relations {
+            let conn = DB_CONNECTION.lock().unwrap();
+            store_relations(&conn, next, &[])?;
+        }
+        fetched += 1;
+    }
+}
+
+fn date_key(member: &FranchiseMember) -> (bool, String) {
+    // Unknown dates sort last
+    (member.aired_from.is_none(), member.aired_from.clone().unwrap_or_default())
+}
+
+// Story order: prequels before sequels, side stories after their parent and
+// summaries/full stories after what they retell. Ties and cycles fall back to
+// release order.
+fn chronological(members: &[FranchiseMember], edges: &[(i64, i64, String)]) -> Vec<FranchiseMember> {
+    let index: HashMap<i64, usize> = members.iter().enumerate().map(|(i, m)| (m.anime_id, i)).collect();
+    let mut after: Vec<HashSet<usize>> = vec![HashSet::new(); members.len()];
+    let mut indegree = vec![0usize; members.len()];
+
+    for (source, target, relation) in edges {
+        let (source, target) = match (index.get(source), index.get(target)) {
+            (Some(source), Some(target)) if source != target => (*source, *target),
+            _ => continue,
+        };
+        let (first, then) = match relation.as_str() {
+            "sequel" | "side story" | "summary" => (source, target),
+            "prequel" | "parent story" | "full story" => (target, source),
+            _ => continue,
+        };
+        if after[first].insert(then) {
+            indegree[then] += 1;
+        }
+    }
+
+    let mut order = Vec::with_capacity(members.len());
+    let mut placed = vec![false; members.len()];
+    while order.len() < members.len() {
+        // Earliest released member without unplaced predecessors; on a cycle
+        // take the earliest remaining member regardless
+        let ready = (0..members.len())
+            .filter(|i| !placed[*i] && indegree[*i] == 0)
+            .min_by_key(|i| date_key(&members[*i]));
+        let next = match ready {
+            Some(next) => next,
+            None => (0..members.len())
+                .filter(|i| !placed[*i])
+                .min_by_key(|i| date_key(&members[*i]))
+                .unwrap(),
+        };
+        placed[next] = true;
+        for then in &after[next] {
+            indegree[*then] = indegree[*then].saturating_sub(1);
+        }
+        order.push(members[next].clone());
+    }
+    order
+}
+
+fn build(conn: &Connection, root_id: i64, order: &str) -> Result<Franchise, String> {
+    let (titles, edges, unresolved) = walk(conn, root_id)?;
+    let metadata: HashMap<i64, Value> = metadata_cache::load_all(conn)?
+        .into_iter()
+        .filter(|(id, _)| titles.contains_key(id))
+        .collect();
+
+    let mut stmt = conn
+        .prepare("SELECT status, progress FROM user_anime WHERE anime_id = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let mut members = Vec::new();
+    for (anime_id, edge_title) in titles {
+        let data = metadata.get(&anime_id);
+        let field = |key: &str| data.and_then(|data| data.get(key));
+        let entry: Option<(String, i64)> = stmt
+            .query_row(params![anime_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .ok();
+
+        let list_state = match &entry {
+            None => "missing",
+            Some((status, _)) if status == "completed" => "completed",
+            Some(_) => "not_completed",
+        };
+        members.push(FranchiseMember {
+            anime_id,
+            title: field("title")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or(edge_title),
+            media_type: field("type").and_then(Value::as_str).map(str::to_string),
+            aired_from: field("aired")
+                .and_then(|aired| aired.get("from"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            episodes: field("episodes").and_then(Value::as_i64),
+            list_state: list_state.to_string(),
+            user_status: entry.as_ref().map(|(status, _)| status.clone()),
+            progress: entry.map(|(_, progress)| progress),
+        });
+    }
+
+    let members = if order == "release" {
+        let mut members = members;
+        members.sort_by_key(date_key);
+        members
+    } else {
+        chronological(&members, &edges)
+    };
+
+    Ok(Franchise {
+        root_id,
+        order: order.to_string(),
+        missing: members
+            .iter()
+            .filter(|m| m.list_state == "missing")
+            .map(|m| m.anime_id)
+            .collect(),
+        not_completed: members
+            .iter()
+            .filter(|m| m.list_state == "not_completed")
+            .map(|m| m.anime_id)
+            .collect(),
+        members,
+        fully_resolved: unresolved.is_empty(),
+    })
+}
+
+// Franchise of `anime_id` in "chronological" (default) or "release" order.
+// With `fetch_missing`, members whose relations are not cached yet are looked
+// up on Jikan first; otherwise this only uses the cache.
+#[tauri::command]
+pub async fn get_franchise(
+    anime_id: i64,
+    order: Option<String>,
+    fetch_missing: Option<bool>,
+) -> Result<Franchise, String> {
+    let order = order.unwrap_or_else(|| "chronological".to_string());
+    if order != "chronological" && order != "release" {
+        return Err(format!("Invalid watch order {} (expected chronological or release)", order));
+    }
+
+    if fetch_missing.unwrap_or(false) {
+        if let Err(e) = fetch_unresolved(anime_id).await {
+            eprintln!("{}; using cached relations", e);
+        }
+    }
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    build(&conn, anime_id, &order)
+}
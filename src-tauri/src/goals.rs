@@ -0,0 +1,280 @@
+use crate::db::DB_CONNECTION;
+use crate::watch_history::{self, Streak};
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+pub(crate) const GOAL_REACHED_EVENT: &str = "goals://goal-reached";
+
+const KINDS: [&str; 2] = ["completed", "episodes"];
+const PERIODS: [&str; 3] = ["week", "month", "year"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Goal {
+    pub id: i64,
+    // "completed" counts shows finished, "episodes" counts watched episodes
+    pub kind: String,
+    pub target: i64,
+    // "week" and "month" goals repeat every period; "year" goals cover `year`
+    pub period: String,
+    pub year: Option<i32>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    // First and last day of the period being measured
+    pub period_start: String,
+    pub period_end: String,
+    pub current: i64,
+    // 0-100
+    pub percent: f64,
+    // When the target was first met in this period
+    pub reached_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WatchStreaks {
+    pub current: Option<Streak>,
+    pub longest: Option<Streak>,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS goals (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL,
+            target INTEGER NOT NULL,
+            period TEXT NOT NULL,
+            year INTEGER,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    // One row per goal and period once the target is met, so the reached
+    // event fires once per week, month or year
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS goal_achievements (
+            goal_id INTEGER NOT NULL,
+            period_start TEXT NOT NULL,
+            reached_at TEXT NOT NULL,
+            PRIMARY KEY (goal_id, period_start)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+// Local start (inclusive) and end (exclusive) dates of the goal's current period
+fn period_bounds(goal: &Goal, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    match goal.period.as_str() {
+        "week" => {
+            let start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+            Some((start, start + Duration::weeks(1)))
+        }
+        "month" => {
+            let start = today.with_day(1)?;
+            let next = if start.month() == 12 {
+                NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)?
+            } else {
+                NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)?
+            };
+            Some((start, next))
+        }
+        "year" => {
+            let year = goal.year.unwrap_or(today.year());
+            Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?))
+        }
+        _ => None,
+    }
+}
+
+// watch_events stores UTC timestamps, so local midnight is converted to match
+fn utc_bound(date: NaiveDate) -> String {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    let time = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight));
+    time.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+fn count_in_period(conn: &Connection, kind: &str, start: NaiveDate, end: NaiveDate) -> Result<i64, String> {
+    let result = match kind {
+        // end_date is stored as YYYY-MM-DD or a full ISO timestamp
+        "completed" => conn.query_row(
+            "SELECT COUNT(*) FROM user_anime
+             WHERE status = 'completed' AND substr(end_date, 1, 10) >= ?1 AND substr(end_date, 1, 10) < ?2",
+            params![start.format("%Y-%m-%d").to_string(), end.format("%Y-%m-%d").to_string()],
+            |row| row.get(0),
+        ),
+        "episodes" => conn.query_row(
+            "SELECT COUNT(*) FROM watch_events WHERE watched_at >= ?1 AND watched_at < ?2",
+            params![utc_bound(start), utc_bound(end)],
+            |row| row.get(0),
+        ),
+        _ => return Err(format!("Invalid goal kind {}", kind)),
+    };
+    result.map_err(|e| format!("Failed to compute goal progress: {}", e))
+}
+
+fn load_goals(conn: &Connection) -> Result<Vec<Goal>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, kind, target, period, year, created_at FROM goals ORDER BY id")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Goal {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                target: row.get(2)?,
+                period: row.get(3)?,
+                year: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+// Progress of every goal in its current period. Targets met for the first
+// time are recorded and returned separately so the caller can announce them.
+fn evaluate(conn: &Connection) -> Result<(Vec<GoalProgress>, Vec<GoalProgress>), String> {
+    let today = Local::now().date_naive();
+    let mut progress = Vec::new();
+    let mut reached = Vec::new();
+    for goal in load_goals(conn)? {
+        let Some((start, end)) = period_bounds(&goal, today) else {
+            continue;
+        };
+        let current = count_in_period(conn, &goal.kind, start, end)?;
+        let period_start = start.format("%Y-%m-%d").to_string();
+
+        let mut newly_reached = false;
+        if current >= goal.target {
+            let inserted = conn
+                .execute(
+                    "INSERT OR IGNORE INTO goal_achievements (goal_id, period_start, reached_at) VALUES (?1, ?2, ?3)",
+                    params![goal.id, period_start, Utc::now().to_rfc3339()],
+                )
+                .map_err(|e| format!("Failed to record goal achievement: {}", e))?;
+            newly_reached = inserted > 0;
+        }
+        let reached_at = conn
+            .query_row(
+                "SELECT reached_at FROM goal_achievements WHERE goal_id = ?1 AND period_start = ?2",
+                params![goal.id, period_start],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read goal achievement: {}", e))?;
+
+        let entry = GoalProgress {
+            percent: (100.0 * current as f64 / goal.target as f64).min(100.0),
+            period_start,
+            period_end: end.pred_opt().unwrap_or(end).format("%Y-%m-%d").to_string(),
+            current,
+            reached_at,
+            goal,
+        };
+        if newly_reached {
+            reached.push(entry.clone());
+        }
+        progress.push(entry);
+    }
+    Ok((progress, reached))
+}
+
+fn evaluate_and_emit(app_handle: &tauri::AppHandle, conn: &Connection) -> Result<Vec<GoalProgress>, String> {
+    let (progress, reached) = evaluate(conn)?;
+    for entry in reached {
+        app_handle
+            .emit(GOAL_REACHED_EVENT, entry)
+            .map_err(|e| format!("Failed to emit goal event: {}", e))?;
+    }
+    Ok(progress)
+}
+
+// Called after anything that can move a goal: watched episodes and list edits
+pub(crate) fn check_goals(app_handle: &tauri::AppHandle) {
+    let conn = DB_CONNECTION.lock().unwrap();
+    if let Err(e) = evaluate_and_emit(app_handle, &conn) {
+        eprintln!("Goal check failed: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn create_goal(
+    app_handle: tauri::AppHandle,
+    kind: String,
+    target: i64,
+    period: String,
+    year: Option<i32>,
+) -> Result<Goal, String> {
+    if !KINDS.contains(&kind.as_str()) {
+        return Err(format!("Invalid goal kind {} (expected completed or episodes)", kind));
+    }
+    if !PERIODS.contains(&period.as_str()) {
+        return Err(format!("Invalid goal period {} (expected week, month or year)", period));
+    }
+    if target < 1 {
+        return Err("Goal target must be at least 1".to_string());
+    }
+    // Only yearly goals are tied to a specific year
+    let year = match period.as_str() {
+        "year" => Some(year.unwrap_or_else(|| Local::now().year())),
+        _ => None,
+    };
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    let created_at = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO goals (kind, target, period, year, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![kind, target, period, year, created_at],
+    )
+    .map_err(|e| format!("Failed to create goal: {}", e))?;
+    let goal = Goal {
+        id: conn.last_insert_rowid(),
+        kind,
+        target,
+        period,
+        year,
+        created_at,
+    };
+
+    // A goal can already be met when it is created
+    evaluate_and_emit(&app_handle, &conn)?;
+    Ok(goal)
+}
+
+#[tauri::command]
+pub fn delete_goal(goal_id: i64) -> Result<bool, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    conn.execute("DELETE FROM goal_achievements WHERE goal_id = ?1", params![goal_id])
+        .map_err(|e| format!("Failed to delete goal achievements: {}", e))?;
+    let deleted = conn
+        .execute("DELETE FROM goals WHERE id = ?1", params![goal_id])
+        .map_err(|e| format!("Failed to delete goal: {}", e))?;
+    Ok(deleted > 0)
+}
+
+#[tauri::command]
+pub fn get_goal_progress(app_handle: tauri::AppHandle) -> Result<Vec<GoalProgress>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    evaluate_and_emit(&app_handle, &conn)
+}
+
+#[tauri::command]
+pub fn get_watch_streaks() -> Result<WatchStreaks, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let days = watch_history::watch_days(&conn)?;
+    Ok(WatchStreaks {
+        current: watch_history::current_streak(&days, Local::now().date_naive()),
+        longest: watch_history::longest_streak(&days),
+    })
+}
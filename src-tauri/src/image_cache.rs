@@ -0,0 +1,844 @@
+use crate::db::{self, DB_CONNECTION};
+use crate::filesystem::{atomic_write, walk_dir};
+use crate::metadata_cache;
+use crate::thumbnails::{self, SizeClass, ThumbnailFormat};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::Manager;
+
+// Folder under the app data directory that ImageCacheService writes covers to
+pub(crate) const IMAGE_CACHE_FOLDER: &str = "image_cache";
+const CACHE_SETTINGS_KEY: &str = "image_cache_settings";
+
+// Images fetched by Rust are stored by content hash under image_cache/objects/ab/<sha256>
+const OBJECTS_FOLDER: &str = "objects";
+pub(crate) const PROTOCOL_SCHEME: &str = "animecache";
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+// Held while a downloaded image is stored and indexed, and for a whole garbage
+// collection run, so collection never deletes an object whose URL is about to be indexed
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageCacheSettings {
+    pub max_size_bytes: u64,
+    // Entries not accessed for this many days are evicted; 0 disables the age limit
+    pub max_age_days: u32,
+    #[serde(default = "default_generate_thumbnails")]
+    pub generate_thumbnails: bool,
+    #[serde(default)]
+    pub thumbnail_format: ThumbnailFormat,
+}
+
+fn default_generate_thumbnails() -> bool {
+    true
+}
+
+impl Default for ImageCacheSettings {
+    fn default() -> Self {
+        ImageCacheSettings {
+            max_size_bytes: 500 * 1024 * 1024,
+            max_age_days: 30,
+            generate_thumbnails: true,
+            thumbnail_format: ThumbnailFormat::Original,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SizeClassStats {
+    pub size_class: String,
+    pub file_count: usize,
+    pub size_in_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedImage {
+    pub url: String,
+    pub hash: String,
+    pub protocol_url: String,
+    pub content_type: String,
+    pub size_in_bytes: u64,
+    pub from_cache: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvictionResult {
+    pub removed_files: usize,
+    pub reclaimed_bytes: u64,
+    pub remaining_files: usize,
+    pub remaining_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GcCandidate {
+    pub path: String,
+    // "orphaned", "empty" or "corrupt"
+    pub reason: String,
+    pub size_in_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheGcReport {
+    pub dry_run: bool,
+    pub candidates: Vec<GcCandidate>,
+    pub removed_files: usize,
+    pub reclaimed_bytes: u64,
+    // URL index rows pointing at covers nothing references any more
+    pub stale_urls: usize,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    // LRU index for the image cache, keyed by path relative to the cache folder
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_cache_entries (
+            path TEXT PRIMARY KEY,
+            size INTEGER NOT NULL,
+            last_access INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Several URLs may point at the same cover, so they share one stored object
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_url_index (
+            url TEXT PRIMARY KEY,
+            hash TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_image_url_index_hash ON image_url_index(hash)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn get_cache_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?
+        .join(IMAGE_CACHE_FOLDER);
+
+    // Sandboxed paths are canonical, so compare against the canonical cache folder
+    Ok(fs::canonicalize(&dir).unwrap_or(dir))
+}
+
+fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn load_settings(conn: &Connection) -> Result<ImageCacheSettings, String> {
+    Ok(db::get_setting(conn, CACHE_SETTINGS_KEY)?.unwrap_or_default())
+}
+
+// Index key for a file inside the cache folder, or None for any other path
+fn cache_key(cache_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(cache_dir).ok()?;
+    let key = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+// Mark a cached file as recently used. Paths outside the cache are ignored.
+pub(crate) fn record_access(app_handle: &tauri::AppHandle, path: &Path) {
+    let cache_dir = match get_cache_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(_) => return,
+    };
+
+    if let Some(key) = cache_key(&cache_dir, path) {
+        let conn = DB_CONNECTION.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "UPDATE image_cache_entries SET last_access = ?1 WHERE path = ?2",
+            params![now_secs(), key],
+        ) {
+            eprintln!("Failed to record image cache access: {}", e);
+        }
+    }
+}
+
+// Index a file that was just written to the cache and evict if the cache is now over its limit
+pub(crate) fn record_write(app_handle: &tauri::AppHandle, path: &Path, size: u64) {
+    let cache_dir = match get_cache_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(_) => return,
+    };
+
+    let key = match cache_key(&cache_dir, path) {
+        Some(key) => key,
+        None => return,
+    };
+
+    let over_limit = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        let result = conn
+            .execute(
+                "INSERT INTO image_cache_entries (path, size, last_access) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(path) DO UPDATE SET size = ?2, last_access = ?3",
+                params![key, size as i64, now_secs()],
+            )
+            .map_err(|e| format!("Failed to index cached image: {}", e))
+            .and_then(|_| {
+                let total: i64 = conn
+                    .query_row("SELECT COALESCE(SUM(size), 0) FROM image_cache_entries", [], |row| {
+                        row.get(0)
+                    })
+                    .map_err(|e| format!("Failed to compute cache size: {}", e))?;
+                Ok(total as u64 > load_settings(&conn)?.max_size_bytes)
+            });
+
+        match result {
+            Ok(over_limit) => over_limit,
+            Err(e) => {
+                eprintln!("{}", e);
+                false
+            }
+        }
+    };
+
+    if over_limit {
+        match evict(app_handle) {
+            Ok(result) => println!(
+                "Image cache eviction removed {} files ({} bytes)",
+                result.removed_files, result.reclaimed_bytes
+            ),
+            Err(e) => eprintln!("Image cache eviction failed: {}", e),
+        }
+    }
+}
+
+// Drop every index row, used after the whole cache folder has been cleared
+pub(crate) fn forget_all() -> Result<(), String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    for table in ["image_cache_entries", "image_url_index", "image_variants"] {
+        tx.execute(&format!("DELETE FROM {}", table), [])
+            .map_err(|e| format!("Failed to reset {}: {}", table, e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    Ok(())
+}
+
+// Bring the index in line with the files on disk. Files that were never indexed
+// (e.g. cached before the index existed) use their modification time as last access.
+fn sync_index(cache_dir: &Path) -> Result<Vec<(String, u64, i64)>, String> {
+    let mut on_disk: HashMap<String, (u64, i64)> = HashMap::new();
+    if cache_dir.exists() {
+        walk_dir(cache_dir, &mut |path, is_dir| {
+            if is_dir {
+                return Ok(());
+            }
+
+            if let Some(key) = cache_key(cache_dir, path) {
+                let metadata = fs::metadata(path)
+                    .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or_else(now_secs);
+                on_disk.insert(key, (metadata.len(), modified));
+            }
+            Ok(())
+        })?;
+    }
+
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let indexed: HashMap<String, i64> = {
+        let mut stmt = tx
+            .prepare("SELECT path, last_access FROM image_cache_entries")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| format!("Failed to execute query: {}", e))?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| format!("Failed to retrieve row: {}", e))?
+    };
+
+    for key in indexed.keys() {
+        if !on_disk.contains_key(key) {
+            tx.execute("DELETE FROM image_cache_entries WHERE path = ?1", params![key])
+                .map_err(|e| format!("Failed to update image cache index: {}", e))?;
+        }
+    }
+
+    let mut entries = Vec::with_capacity(on_disk.len());
+    for (key, (size, modified)) in on_disk {
+        let last_access = indexed.get(&key).copied().unwrap_or(modified);
+        tx.execute(
+            "INSERT INTO image_cache_entries (path, size, last_access) VALUES (?1, ?2, ?3)
+             ON CONFLICT(path) DO UPDATE SET size = ?2",
+            params![key, size as i64, last_access],
+        )
+        .map_err(|e| format!("Failed to update image cache index: {}", e))?;
+        entries.push((key, size, last_access));
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit image cache index: {}", e))?;
+
+    Ok(entries)
+}
+
+pub(crate) fn evict(app_handle: &tauri::AppHandle) -> Result<EvictionResult, String> {
+    let cache_dir = get_cache_dir(app_handle)?;
+    let settings = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        load_settings(&conn)?
+    };
+
+    let mut entries = sync_index(&cache_dir)?;
+    // Least recently used first
+    entries.sort_by_key(|(_, _, last_access)| *last_access);
+
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    let expiry = if settings.max_age_days > 0 {
+        Some(now_secs() - settings.max_age_days as i64 * 24 * 60 * 60)
+    } else {
+        None
+    };
+
+    let mut result = EvictionResult {
+        removed_files: 0,
+        reclaimed_bytes: 0,
+        remaining_files: entries.len(),
+        remaining_bytes: total,
+    };
+
+    let mut removed_keys = Vec::new();
+    for (key, size, last_access) in &entries {
+        let expired = expiry.map(|cutoff| *last_access < cutoff).unwrap_or(false);
+        if !expired && total <= settings.max_size_bytes {
+            // Sorted by access time, so nothing later is expired either
+            break;
+        }
+
+        let path = cache_dir.join(key);
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to delete file {}: {}", path.display(), e)),
+        }
+
+        total -= size;
+        result.removed_files += 1;
+        result.reclaimed_bytes += size;
+        removed_keys.push(key.clone());
+    }
+
+    {
+        let conn = DB_CONNECTION.lock().unwrap();
+        for key in &removed_keys {
+            conn.execute("DELETE FROM image_cache_entries WHERE path = ?1", params![key])
+                .map_err(|e| format!("Failed to update image cache index: {}", e))?;
+        }
+    }
+
+    result.remaining_files -= result.removed_files;
+    result.remaining_bytes = total;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn get_image_cache_settings() -> Result<ImageCacheSettings, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    load_settings(&conn)
+}
+
+#[tauri::command]
+pub fn update_image_cache_settings(
+    app_handle: tauri::AppHandle,
+    settings: ImageCacheSettings,
+) -> Result<EvictionResult, String> {
+    {
+        let conn = DB_CONNECTION.lock().unwrap();
+        db::set_setting(&conn, CACHE_SETTINGS_KEY, &settings)?;
+    }
+
+    // Apply the new limits right away
+    evict(&app_handle)
+}
+
+#[tauri::command]
+pub fn evict_image_cache(app_handle: tauri::AppHandle) -> Result<EvictionResult, String> {
+    evict(&app_handle)
+}
+
+fn object_path(cache_dir: &Path, hash: &str) -> PathBuf {
+    cache_dir.join(OBJECTS_FOLDER).join(&hash[..2]).join(hash)
+}
+
+fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// URL the webview can load a cached object from. Windows and Android webviews
+// only allow custom schemes through the http://<scheme>.localhost form.
+pub(crate) fn protocol_url(hash: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/{}", PROTOCOL_SCHEME, hash)
+    } else {
+        format!("{}://localhost/{}", PROTOCOL_SCHEME, hash)
+    }
+}
+
+fn lookup_url(url: &str) -> Result<Option<(String, String, u64)>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    match conn.query_row(
+        "SELECT hash, content_type, size FROM image_url_index WHERE url = ?1",
+        params![url],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as u64)),
+    ) {
+        Ok(entry) => Ok(Some(entry)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Failed to look up cached image: {}", e)),
+    }
+}
+
+async fn download_image(url: &str) -> Result<(Vec<u8>, String), String> {
+    let response = reqwest::get(url)
+        .await
+        .map_err(|e| format!("Failed to download image {}: {}", url, e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to download image {}: HTTP {}", url, response.status()));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or(value).trim().to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    if !content_type.starts_with("image/") {
+        return Err(format!("URL {} did not return an image ({})", url, content_type));
+    }
+
+    if let Some(length) = response.content_length() {
+        if length as usize > MAX_IMAGE_BYTES {
+            return Err(format!("Image {} is too large to cache", url));
+        }
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read image {}: {}", url, e))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(format!("Image {} is too large to cache", url));
+    }
+
+    Ok((bytes.to_vec(), content_type))
+}
+
+// Builds the size classes of a cached image that are missing on disk
+async fn ensure_variants(
+    app_handle: &tauri::AppHandle,
+    cache_dir: &Path,
+    hash: &str,
+    url: &str,
+) -> Result<(), String> {
+    let settings = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        load_settings(&conn)?
+    };
+    if !settings.generate_thumbnails {
+        return Ok(());
+    }
+
+    let cache_dir = cache_dir.to_path_buf();
+    let hash = hash.to_string();
+    let variants = tauri::async_runtime::spawn_blocking(move || {
+        let format = settings.thumbnail_format;
+        if thumbnails::has_all_variants(&cache_dir, &hash, format) {
+            return Ok(Vec::new());
+        }
+        let bytes = fs::read(object_path(&cache_dir, &hash))
+            .map_err(|e| format!("Failed to read cached image: {}", e))?;
+        thumbnails::generate_variants(&cache_dir, &hash, &bytes, format)
+    })
+    .await
+    .map_err(|e| format!("Thumbnail generation task failed: {}", e))?;
+
+    // A cover that cannot be resized is still usable at full size
+    match variants {
+        Ok(variants) => {
+            for (variant_path, size) in variants {
+                record_write(app_handle, &variant_path, size);
+            }
+        }
+        Err(e) => eprintln!("Failed to generate thumbnails for {}: {}", url, e),
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn fetch_cached_image(app_handle: tauri::AppHandle, url: String) -> Result<CachedImage, String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Only http(s) image URLs can be cached: {}", url));
+    }
+
+    let cache_dir = get_cache_dir(&app_handle)?;
+
+    if let Some((hash, content_type, size)) = lookup_url(&url)? {
+        let path = object_path(&cache_dir, &hash);
+        if path.exists() {
+            record_access(&app_handle, &path);
+            ensure_variants(&app_handle, &cache_dir, &hash, &url).await?;
+            return Ok(CachedImage {
+                protocol_url: protocol_url(&hash),
+                url,
+                hash,
+                content_type,
+                size_in_bytes: size,
+                from_cache: true,
+            });
+        }
+    }
+
+    let (bytes, content_type) = download_image(&url).await?;
+    let hash: String = Sha256::digest(&bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    let path = object_path(&cache_dir, &hash);
+    {
+        let _lock = CACHE_LOCK.lock().unwrap();
+        // Identical covers served from different URLs are only stored once
+        if !path.exists() {
+            atomic_write(&path, &bytes, false)?;
+        }
+
+        let conn = DB_CONNECTION.lock().unwrap();
+        conn.execute(
+            "INSERT INTO image_url_index (url, hash, content_type, size, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(url) DO UPDATE SET hash = ?2, content_type = ?3, size = ?4, fetched_at = ?5",
+            params![url, hash, content_type, bytes.len() as i64, now_secs()],
+        )
+        .map_err(|e| format!("Failed to index cached image: {}", e))?;
+    }
+
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    record_write(&app_handle, &path, bytes.len() as u64);
+
+    ensure_variants(&app_handle, &cache_dir, &hash, &url).await?;
+
+    Ok(CachedImage {
+        protocol_url: protocol_url(&hash),
+        url,
+        hash,
+        content_type,
+        size_in_bytes: bytes.len() as u64,
+        from_cache: false,
+    })
+}
+
+fn protocol_error(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.as_bytes().to_vec())
+        .unwrap()
+}
+
+fn requested_size(request: &Request<Vec<u8>>) -> Option<SizeClass> {
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "size")
+        .and_then(|(_, value)| SizeClass::parse(value))
+}
+
+// Serves animecache://localhost/<sha256>[?size=96|225|full] from the content-addressed
+// store. Missing variants fall back to the original object.
+pub(crate) fn handle_protocol(app_handle: &tauri::AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let hash = request.uri().path().trim_start_matches('/');
+    if !is_valid_hash(hash) {
+        return protocol_error(StatusCode::BAD_REQUEST, "Invalid image id");
+    }
+
+    let cache_dir = match get_cache_dir(app_handle) {
+        Ok(dir) => dir,
+        Err(e) => return protocol_error(StatusCode::INTERNAL_SERVER_ERROR, &e),
+    };
+
+    let variant = requested_size(request)
+        .and_then(|class| thumbnails::find_variant(&cache_dir, hash, class));
+    let (path, content_type) = match variant {
+        Some(variant) => (variant.path, Some(variant.content_type)),
+        None => (object_path(&cache_dir, hash), None),
+    };
+
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(_) => return protocol_error(StatusCode::NOT_FOUND, "Image not cached"),
+    };
+    record_access(app_handle, &path);
+
+    let content_type = content_type.unwrap_or_else(|| {
+        let conn = DB_CONNECTION.lock().unwrap();
+        conn.query_row(
+            "SELECT content_type FROM image_url_index WHERE hash = ?1 LIMIT 1",
+            params![hash],
+            |row| row.get::<_, String>(0),
+        )
+        .unwrap_or_else(|_| "application/octet-stream".to_string())
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        // Objects are addressed by content, so they never change
+        .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(bytes)
+        .unwrap()
+}
+
+// Bytes per size class: originals (content-addressed objects and files written by
+// ImageCacheService) and each thumbnail class under variants/
+pub(crate) fn size_class_breakdown(cache_dir: &Path) -> Result<Vec<SizeClassStats>, String> {
+    let mut classes: Vec<SizeClassStats> = std::iter::once("original")
+        .chain(SizeClass::ALL.iter().map(|class| class.as_str()))
+        .map(|name| SizeClassStats {
+            size_class: name.to_string(),
+            file_count: 0,
+            size_in_bytes: 0,
+        })
+        .collect();
+
+    if !cache_dir.exists() {
+        return Ok(classes);
+    }
+
+    walk_dir(cache_dir, &mut |path, is_dir| {
+        if is_dir {
+            return Ok(());
+        }
+
+        let key = match cache_key(cache_dir, path) {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+        let class = match key.split('/').collect::<Vec<_>>().as_slice() {
+            [folder, class, ..] if *folder == thumbnails::VARIANTS_FOLDER => class.to_string(),
+            _ => "original".to_string(),
+        };
+
+        let size = fs::metadata(path)
+            .map_err(|e| format!("Failed to get file metadata: {}", e))?
+            .len();
+        if let Some(stats) = classes.iter_mut().find(|stats| stats.size_class == class) {
+            stats.file_count += 1;
+            stats.size_in_bytes += size;
+        }
+        Ok(())
+    })?;
+
+    Ok(classes)
+}
+
+// Hash of the content-addressed object a cache file belongs to: objects/ab/<hash>
+// or variants/<class>/ab/<hash>.<ext>. Files written by ImageCacheService have none.
+fn object_hash(key: &str) -> Option<String> {
+    let parts: Vec<&str> = key.split('/').collect();
+    let name = match parts.as_slice() {
+        [folder, _, name] if *folder == OBJECTS_FOLDER => *name,
+        [folder, _, _, name] if *folder == thumbnails::VARIANTS_FOLDER => {
+            name.split('.').next().unwrap_or(name)
+        }
+        _ => return None,
+    };
+    if is_valid_hash(name) {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+// Only formats this build can decode are judged; anything else (AVIF variants,
+// formats without a compiled-in decoder) is left alone rather than deleted
+fn is_corrupt(bytes: &[u8]) -> bool {
+    match image::guess_format(bytes) {
+        Ok(format) if format.reading_enabled() => {
+            image::load_from_memory_with_format(bytes, format).is_err()
+        }
+        _ => false,
+    }
+}
+
+// Find cached images no list entry or cached metadata points at any more, plus
+// empty or undecodable files. With `dry_run` nothing is deleted.
+pub(crate) fn collect_garbage(
+    app_handle: &tauri::AppHandle,
+    dry_run: bool,
+) -> Result<CacheGcReport, String> {
+    let _lock = CACHE_LOCK.lock().unwrap();
+    let cache_dir = get_cache_dir(app_handle)?;
+
+    let (live_hashes, stale_urls) = {
+        let conn = DB_CONNECTION.lock().unwrap();
+
+        let mut referenced = metadata_cache::referenced_image_urls(&conn)?;
+        let mut stmt = conn
+            .prepare("SELECT image_url FROM user_anime WHERE image_url IS NOT NULL")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to execute query: {}", e))?;
+        for row in rows {
+            referenced.insert(row.map_err(|e| format!("Failed to retrieve row: {}", e))?);
+        }
+
+        let mut stmt = conn
+            .prepare("SELECT url, hash FROM image_url_index")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let indexed = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| format!("Failed to execute query: {}", e))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+
+        let mut live_hashes = HashSet::new();
+        let mut stale_urls = Vec::new();
+        for (url, hash) in indexed {
+            if referenced.contains(&url) {
+                live_hashes.insert(hash);
+            } else {
+                stale_urls.push(url);
+            }
+        }
+        (live_hashes, stale_urls)
+    };
+
+    let mut report = CacheGcReport {
+        dry_run,
+        candidates: Vec::new(),
+        removed_files: 0,
+        reclaimed_bytes: 0,
+        stale_urls: stale_urls.len(),
+    };
+    if !cache_dir.exists() {
+        return Ok(report);
+    }
+
+    // Hashes whose original turned out to be broken take their variants with them
+    let mut broken_hashes = HashSet::new();
+    let mut files = Vec::new();
+    walk_dir(&cache_dir, &mut |path, is_dir| {
+        if !is_dir {
+            if let Some(key) = cache_key(&cache_dir, path) {
+                files.push((path.to_path_buf(), key));
+            }
+        }
+        Ok(())
+    })?;
+    // Originals sort before variants, so broken objects are known when variants are checked
+    files.sort_by_key(|(_, key)| !key.starts_with(OBJECTS_FOLDER));
+
+    for (path, key) in &files {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))?;
+        let hash = object_hash(key);
+
+        let reason = if bytes.is_empty() {
+            "empty"
+        } else if is_corrupt(&bytes) {
+            "corrupt"
+        } else if hash
+            .as_ref()
+            .map(|hash| !live_hashes.contains(hash) || broken_hashes.contains(hash))
+            .unwrap_or(false)
+        {
+            "orphaned"
+        } else {
+            continue;
+        };
+
+        if reason != "orphaned" && key.starts_with(OBJECTS_FOLDER) {
+            if let Some(hash) = &hash {
+                broken_hashes.insert(hash.clone());
+            }
+        }
+        report.candidates.push(GcCandidate {
+            path: path.to_string_lossy().to_string(),
+            reason: reason.to_string(),
+            size_in_bytes: bytes.len() as u64,
+        });
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    for candidate in &report.candidates {
+        let path = Path::new(&candidate.path);
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to delete file {}: {}", path.display(), e)),
+        }
+        report.removed_files += 1;
+        report.reclaimed_bytes += candidate.size_in_bytes;
+
+        if let Some(key) = cache_key(&cache_dir, path) {
+            conn.execute("DELETE FROM image_cache_entries WHERE path = ?1", params![key])
+                .map_err(|e| format!("Failed to update image cache index: {}", e))?;
+        }
+    }
+
+    for url in &stale_urls {
+        conn.execute("DELETE FROM image_url_index WHERE url = ?1", params![url])
+            .map_err(|e| format!("Failed to update image URL index: {}", e))?;
+    }
+    for hash in &broken_hashes {
+        conn.execute("DELETE FROM image_url_index WHERE hash = ?1", params![hash])
+            .map_err(|e| format!("Failed to update image URL index: {}", e))?;
+    }
+    conn.execute(
+        "DELETE FROM image_variants WHERE hash NOT IN (SELECT hash FROM image_url_index)",
+        [],
+    )
+    .map_err(|e| format!("Failed to update image variant index: {}", e))?;
+
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn gc_image_cache(app_handle: tauri::AppHandle, dry_run: bool) -> Result<CacheGcReport, String> {
+    collect_garbage(&app_handle, dry_run)
+}
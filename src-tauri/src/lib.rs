@@ -1,7 +1,31 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod airing;
 mod api;
+mod backup;
+mod backup_crypto;
+mod backup_store;
+mod cache_stats;
+mod calendar_feed;
+mod collections;
+mod continue_watching;
 mod db;
 mod filesystem;
+mod franchise;
+mod goals;
+mod image_cache;
+mod library;
+mod library_verify;
+mod library_watcher;
+mod metadata_cache;
+mod notifications;
+mod player;
+mod recommendations;
+mod sandbox;
+mod seasonal;
+mod stats;
+mod thumbnails;
+mod watch_history;
+mod year_review;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -16,6 +40,26 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
+        .register_asynchronous_uri_scheme_protocol(
+            image_cache::PROTOCOL_SCHEME,
+            |ctx, request, responder| {
+                // Reading covers and the cache index would otherwise block the webview
+                let app_handle = ctx.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(image_cache::handle_protocol(&app_handle, &request));
+                });
+            },
+        )
+        .setup(|app| {
+            backup::start_scheduler(app.handle().clone());
+            library_watcher::restart(app.handle());
+            notifications::start_checker(app.handle().clone());
+            airing::start_rebuild();
+            if let Err(e) = calendar_feed::restart() {
+                eprintln!("{}", e);
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             db::get_anime_by_id,
@@ -33,8 +77,14 @@ pub fn run() {
             db::import_user_data,
             api::search_anime,
             api::get_anime_details,
-            api::get_seasonal_anime,
-            api::get_anime_recommendations,
+            seasonal::get_seasonal_anime,
+            recommendations::get_anime_recommendations,
+            recommendations::get_recommendations,
+            franchise::get_franchise,
+            stats::get_watch_time_stats,
+            stats::get_score_distribution,
+            stats::get_stats_breakdown,
+            stats::get_completion_stats,
             filesystem::create_directory,
             filesystem::file_exists,
             filesystem::read_file,
@@ -45,9 +95,76 @@ pub fn run() {
             filesystem::get_documents_dir,
             filesystem::create_zip_archive,
             filesystem::clear_image_cache,
-            filesystem::get_cache_stats,
-            filesystem::create_backup_zip
+            cache_stats::get_cache_stats,
+            filesystem::create_backup_zip,
+            backup::get_backup_settings,
+            backup::update_backup_settings,
+            backup::choose_backup_folder,
+            backup::run_backup_now,
+            backup::list_backups,
+            backup::get_backup_directory,
+            backup::set_backup_password,
+            backup::restore_backup,
+            backup_store::create_incremental_snapshot,
+            backup_store::list_snapshots,
+            backup_store::restore_snapshot,
+            backup_store::delete_snapshot,
+            backup_store::gc_backup_store,
+            sandbox::list_allowed_roots,
+            sandbox::add_storage_root,
+            sandbox::remove_storage_root,
+            image_cache::get_image_cache_settings,
+            image_cache::update_image_cache_settings,
+            image_cache::evict_image_cache,
+            image_cache::fetch_cached_image,
+            image_cache::gc_image_cache,
+            metadata_cache::cache_anime_metadata,
+            metadata_cache::cache_anime_recommendations,
+            metadata_cache::get_cached_anime_metadata,
+            library::get_library_folders,
+            library::add_library_folder,
+            library::remove_library_folder,
+            library::scan_library,
+            library::list_library_files,
+            library::get_unmatched_library_files,
+            library::link_library_file,
+            library_verify::verify_library_files,
+            library_verify::cancel_library_verification,
+            player::get_player_settings,
+            player::update_player_settings,
+            player::play_library_file,
+            watch_history::get_watch_history,
+            continue_watching::get_continue_watching,
+            airing::refresh_airing_schedule,
+            airing::get_weekly_schedule,
+            notifications::get_notification_settings,
+            notifications::update_notification_settings,
+            calendar_feed::export_airing_calendar,
+            calendar_feed::get_calendar_feed_status,
+            calendar_feed::update_calendar_feed_settings,
+            year_review::generate_year_review,
+            goals::create_goal,
+            goals::delete_goal,
+            goals::get_goal_progress,
+            goals::get_watch_streaks,
+            collections::list_tags,
+            collections::create_tag,
+            collections::rename_tag,
+            collections::delete_tag,
+            collections::set_anime_tags,
+            collections::list_custom_lists,
+            collections::create_custom_list,
+            collections::update_custom_list,
+            collections::delete_custom_list,
+            collections::add_to_custom_list,
+            collections::remove_from_custom_list,
+            collections::reorder_custom_list
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                backup::backup_on_exit(app_handle);
+            }
+        });
 }
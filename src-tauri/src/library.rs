@@ -0,0 +1,724 @@
+use crate::db::{self, DB_CONNECTION};
+use crate::filesystem::walk_dir;
+use crate::library_watcher;
+use crate::metadata_cache;
+use crate::sandbox::{self, LIBRARY_FOLDERS_KEY};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+pub(crate) const VIDEO_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "avi", "m4v", "webm", "mov", "wmv", "flv", "ts", "ogm",
+];
+
+// Titles scoring below this are left for manual linking
+const MATCH_THRESHOLD: f64 = 0.82;
+
+static GROUP_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*[\[(]([^\])]+)[\])]\s*").unwrap());
+static CHECKSUM_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[\[(]([0-9A-Fa-f]{8})[\])]").unwrap());
+static RESOLUTION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\b(?:\d{3,4}x)?(\d{3,4})p?\b").unwrap());
+static BRACKETS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[[^\]]*\]|\([^)]*\)|\{[^}]*\}").unwrap());
+static DIMENSIONS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b\d{3,4}x\d{3,4}\b").unwrap());
+static SEASON_EPISODE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bS(\d{1,2})\s?E(\d{1,4})(?:v\d)?\b").unwrap());
+static BATCH_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:\s-)?\s(\d{1,4})(?:-|\s?~\s?)(\d{1,4})(?:v\d)?(?:\s|$)").unwrap());
+static DASH_EPISODE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\s-\s(\d{1,4})(?:v\d)?(?:\s|$)").unwrap());
+static EP_EPISODE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\b(?:ep?|episode)\s?(\d{1,4})(?:v\d)?\b").unwrap());
+static TRAILING_EPISODE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s(\d{1,4})(?:v\d)?$").unwrap());
+static SEASON_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:S(\d{1,2})|Season\s?(\d{1,2})|(\d{1,2})(?:st|nd|rd|th)\s+Season)\b").unwrap()
+});
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParsedRelease {
+    pub group: Option<String>,
+    pub title: String,
+    pub season: Option<i32>,
+    pub episode: Option<i32>,
+    pub resolution: Option<String>,
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryFile {
+    pub path: String,
+    pub file_name: String,
+    pub size_in_bytes: u64,
+    pub parsed: ParsedRelease,
+    pub anime_id: Option<i64>,
+    pub episode: Option<i32>,
+    pub match_score: Option<f64>,
+    // Links made by the user are kept across rescans
+    pub manual: bool,
+    pub crc32: Option<String>,
+    pub ed2k: Option<String>,
+    // "ok", "mismatch", "computed" (no checksum in the name to compare) or "error";
+    // None until the file has been verified
+    pub verify_status: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LibraryScanResult {
+    pub scanned: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub removed: usize,
+}
+
+// A title the library can match against, normalized once up front
+pub(crate) struct MatchCandidate {
+    anime_id: i64,
+    titles: Vec<String>,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS library_files (
+            path TEXT PRIMARY KEY,
+            file_name TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            modified INTEGER NOT NULL,
+            release_group TEXT,
+            parsed_title TEXT NOT NULL,
+            season INTEGER,
+            parsed_episode INTEGER,
+            episode INTEGER,
+            resolution TEXT,
+            checksum TEXT,
+            anime_id INTEGER,
+            match_score REAL,
+            manual BOOLEAN NOT NULL DEFAULT 0,
+            scanned_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_library_files_anime ON library_files(anime_id, episode)",
+        [],
+    )?;
+
+    // Checksum verification results, filled in by library_verify
+    let columns = conn
+        .prepare("PRAGMA table_info(library_files)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    for (column, definition) in [
+        ("crc32", "TEXT"),
+        ("ed2k", "TEXT"),
+        ("verify_status", "TEXT"),
+        ("verified_at", "INTEGER"),
+    ] {
+        if !columns.contains(&column.to_string()) {
+            conn.execute(
+                &format!("ALTER TABLE library_files ADD COLUMN {} {}", column, definition),
+                [],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| {
+            let ext = ext.to_string_lossy().to_lowercase();
+            VIDEO_EXTENSIONS.contains(&ext.as_str())
+        })
+        .unwrap_or(false)
+}
+
+fn clean_title(title: &str) -> String {
+    title
+        .trim_matches(|c: char| c.is_whitespace() || c == '-' || c == '_' || c == '.')
+        .to_string()
+}
+
+// Parse fansub/scene style names such as "[Group] Title - 05 (1080p) [ABCD1234].mkv"
+// or "Title.S02E05.1080p.mkv". Returns None if no title is left after parsing.
+pub(crate) fn parse_release(file_name: &str) -> Option<ParsedRelease> {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name.to_string());
+
+    let mut parsed = ParsedRelease::default();
+    let mut rest = stem.as_str();
+
+    if let Some(caps) = GROUP_RE.captures(rest) {
+        // A leading [ABCD1234] is a checksum, not a group
+        if !CHECKSUM_RE.is_match(caps.get(0).unwrap().as_str().trim()) {
+            parsed.group = Some(caps[1].trim().to_string());
+            rest = &rest[caps.get(0).unwrap().end()..];
+        }
+    }
+
+    parsed.checksum = CHECKSUM_RE
+        .captures_iter(rest)
+        .last()
+        .map(|caps| caps[1].to_uppercase());
+
+    // Resolution is usually inside a tag but scene names put it inline
+    parsed.resolution = RESOLUTION_RE
+        .captures_iter(rest)
+        .filter(|caps| {
+            let whole = caps.get(0).unwrap().as_str();
+            whole.ends_with(['p', 'P']) || whole.contains(['x', 'X'])
+        })
+        .last()
+        .map(|caps| format!("{}p", &caps[1]));
+
+    let mut core = BRACKETS_RE.replace_all(rest, " ").to_string();
+    // Scene releases use dots or underscores instead of spaces
+    if !core.contains(' ') || core.matches(['.', '_']).count() > core.matches(' ').count() {
+        core = core.replace(['.', '_'], " ");
+    }
+    if let Some(resolution) = &parsed.resolution {
+        core = core.replace(resolution.as_str(), " ");
+    }
+    core = DIMENSIONS_RE.replace_all(&core, " ").to_string();
+    let core = core.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mut title_end = core.len();
+    if let Some(caps) = SEASON_EPISODE_RE.captures(&core) {
+        parsed.season = caps[1].parse().ok();
+        parsed.episode = caps[2].parse().ok();
+        title_end = caps.get(0).unwrap().start();
+    } else if let Some(caps) = BATCH_RE.captures(&core) {
+        // A batch covers a range of episodes, so no single episode is set
+        title_end = caps.get(0).unwrap().start();
+    } else if let Some(caps) = DASH_EPISODE_RE
+        .captures(&core)
+        .or_else(|| EP_EPISODE_RE.captures(&core))
+        .or_else(|| TRAILING_EPISODE_RE.captures(&core))
+    {
+        parsed.episode = caps[1].parse().ok();
+        title_end = caps.get(0).unwrap().start();
+    }
+
+    let mut title = core[..title_end].to_string();
+    if parsed.season.is_none() {
+        if let Some(caps) = SEASON_RE.captures(&title) {
+            parsed.season = caps
+                .iter()
+                .skip(1)
+                .flatten()
+                .next()
+                .and_then(|m| m.as_str().parse().ok());
+            title = title[..caps.get(0).unwrap().start()].to_string();
+        }
+    }
+
+    parsed.title = clean_title(&title);
+    if parsed.title.is_empty() {
+        None
+    } else {
+        Some(parsed)
+    }
+}
+
+fn normalize(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Every title Jikan knows for a show: main, English, Japanese, synonyms and alternates
+pub(crate) fn metadata_titles(data: &Value) -> Vec<String> {
+    let mut titles = Vec::new();
+    for key in ["title", "title_english", "title_japanese"] {
+        if let Some(title) = data.get(key).and_then(Value::as_str) {
+            titles.push(title.to_string());
+        }
+    }
+    if let Some(synonyms) = data.get("title_synonyms").and_then(Value::as_array) {
+        titles.extend(synonyms.iter().filter_map(Value::as_str).map(str::to_string));
+    }
+    if let Some(alternates) = data.get("titles").and_then(Value::as_array) {
+        titles.extend(
+            alternates
+                .iter()
+                .filter_map(|entry| entry.get("title").and_then(Value::as_str))
+                .map(str::to_string),
+        );
+    }
+    titles
+}
+
+pub(crate) fn load_candidates(conn: &Connection) -> Result<Vec<MatchCandidate>, String> {
+    let mut candidates: Vec<MatchCandidate> = Vec::new();
+
+    let mut stmt = conn
+        .prepare("SELECT anime_id, title FROM user_anime WHERE title != ''")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    for row in rows {
+        let (anime_id, title) = row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        candidates.push(MatchCandidate {
+            anime_id,
+            titles: vec![normalize(&title)],
+        });
+    }
+
+    for (anime_id, data) in metadata_cache::load_all(conn)? {
+        let titles: Vec<String> = metadata_titles(&data).iter().map(|title| normalize(title)).collect();
+        match candidates.iter_mut().find(|c| c.anime_id == anime_id) {
+            Some(candidate) => candidate.titles.extend(titles),
+            None => candidates.push(MatchCandidate {
+                anime_id,
+                titles,
+            }),
+        }
+    }
+
+    for candidate in &mut candidates {
+        candidate.titles.retain(|title| !title.is_empty());
+        candidate.titles.sort();
+        candidate.titles.dedup();
+    }
+
+    Ok(candidates)
+}
+
+// Best candidate for a parsed release. Later seasons are usually listed as
+// "Title Season 2" or "Title 2nd Season", so those spellings are tried too.
+pub(crate) fn match_release(candidates: &[MatchCandidate], parsed: &ParsedRelease) -> Option<(i64, f64)> {
+    let base = normalize(&parsed.title);
+    let mut queries = vec![base.clone()];
+    if let Some(season) = parsed.season.filter(|season| *season > 1) {
+        let ordinal = match season {
+            2 => "2nd".to_string(),
+            3 => "3rd".to_string(),
+            n => format!("{}th", n),
+        };
+        queries.push(format!("{} season {}", base, season));
+        queries.push(format!("{} {} season", base, ordinal));
+        queries.push(format!("{} {}", base, season));
+    }
+
+    candidates
+        .iter()
+        .flat_map(|candidate| {
+            candidate.titles.iter().flat_map(|title| {
+                queries
+                    .iter()
+                    .map(|query| (candidate.anime_id, strsim::normalized_levenshtein(query, title)))
+            })
+        })
+        .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+fn row_to_file(row: &rusqlite::Row) -> rusqlite::Result<LibraryFile> {
+    Ok(LibraryFile {
+        path: row.get(0)?,
+        file_name: row.get(1)?,
+        size_in_bytes: row.get::<_, i64>(2)? as u64,
+        parsed: ParsedRelease {
+            group: row.get(3)?,
+            title: row.get(4)?,
+            season: row.get(5)?,
+            episode: row.get(6)?,
+            resolution: row.get(7)?,
+            checksum: row.get(8)?,
+        },
+        anime_id: row.get(9)?,
+        episode: row.get(10)?,
+        match_score: row.get(11)?,
+        manual: row.get(12)?,
+        crc32: row.get(13)?,
+        ed2k: row.get(14)?,
+        verify_status: row.get(15)?,
+    })
+}
+
+const FILE_COLUMNS: &str = "path, file_name, size, release_group, parsed_title, season,
+    parsed_episode, resolution, checksum, anime_id, episode, match_score, manual, crc32, ed2k,
+    verify_status";
+
+pub(crate) fn get_file(conn: &Connection, path: &str) -> Result<Option<LibraryFile>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM library_files WHERE path = ?1", FILE_COLUMNS),
+        params![path],
+        row_to_file,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read library file: {}", e))
+}
+
+// Parse, match and store a single file. Manual links survive re-indexing.
+pub(crate) fn index_file(
+    conn: &Connection,
+    candidates: &[MatchCandidate],
+    path: &Path,
+) -> Result<Option<LibraryFile>, String> {
+    if !is_video_file(path) {
+        return Ok(None);
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let parsed = match parse_release(&file_name) {
+        Some(parsed) => parsed,
+        None => return Ok(None),
+    };
+
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to get file metadata: {}", e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let key = path.to_string_lossy().to_string();
+    let (anime_id, score) = match match_release(candidates, &parsed) {
+        Some((anime_id, score)) => (Some(anime_id), Some(score)),
+        None => (None, None),
+    };
+
+    // A file whose size or modification time changed has to be verified again
+    conn.execute(
+        "INSERT INTO library_files (path, file_name, size, modified, release_group, parsed_title,
+            season, parsed_episode, episode, resolution, checksum, anime_id, match_score, manual,
+            scanned_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8, ?9, ?10, ?11, ?12, 0, ?13)
+         ON CONFLICT(path) DO UPDATE SET
+            crc32 = CASE WHEN size = ?3 AND modified = ?4 THEN crc32 END,
+            ed2k = CASE WHEN size = ?3 AND modified = ?4 THEN ed2k END,
+            verify_status = CASE WHEN size = ?3 AND modified = ?4 THEN verify_status END,
+            verified_at = CASE WHEN size = ?3 AND modified = ?4 THEN verified_at END,
+            file_name = ?2, size = ?3, modified = ?4, release_group = ?5, parsed_title = ?6,
+            season = ?7, parsed_episode = ?8, resolution = ?9, checksum = ?10, scanned_at = ?13,
+            episode = CASE WHEN manual THEN episode ELSE ?8 END,
+            anime_id = CASE WHEN manual THEN anime_id ELSE ?11 END,
+            match_score = CASE WHEN manual THEN match_score ELSE ?12 END",
+        params![
+            key,
+            file_name,
+            metadata.len() as i64,
+            modified,
+            parsed.group,
+            parsed.title,
+            parsed.season,
+            parsed.episode,
+            parsed.resolution,
+            parsed.checksum,
+            anime_id,
+            score,
+            chrono::Utc::now().timestamp(),
+        ],
+    )
+    .map_err(|e| format!("Failed to store library file: {}", e))?;
+
+    get_file(conn, &key)
+}
+
+pub(crate) fn library_folders() -> Vec<PathBuf> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    db::get_setting::<Vec<String>>(&conn, LIBRARY_FOLDERS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+pub(crate) fn scan_folders(folders: &[PathBuf]) -> Result<LibraryScanResult, String> {
+    let mut result = LibraryScanResult {
+        scanned: 0,
+        matched: 0,
+        unmatched: 0,
+        removed: 0,
+    };
+
+    // Collect paths before locking the database; walking large folders can take a while
+    let mut paths = Vec::new();
+    for folder in folders {
+        if !folder.is_dir() {
+            eprintln!("Library folder {} is not available", folder.display());
+            continue;
+        }
+        walk_dir(folder, &mut |path, is_dir| {
+            if !is_dir && is_video_file(path) {
+                paths.push(path.to_path_buf());
+            }
+            Ok(())
+        })?;
+    }
+
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let candidates = load_candidates(&conn)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for path in &paths {
+        if let Some(file) = index_file(&tx, &candidates, path)? {
+            result.scanned += 1;
+            if file.anime_id.is_some() {
+                result.matched += 1;
+            } else {
+                result.unmatched += 1;
+            }
+        }
+    }
+
+    // Forget files that disappeared from folders that were actually scanned
+    let known: Vec<String> = {
+        let mut stmt = tx
+            .prepare("SELECT path FROM library_files")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to execute query: {}", e))?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| format!("Failed to retrieve row: {}", e))?
+    };
+    for path in known {
+        let path_buf = PathBuf::from(&path);
+        let in_scanned_folder = folders
+            .iter()
+            .any(|folder| folder.is_dir() && path_buf.starts_with(folder));
+        if in_scanned_folder && !path_buf.exists() {
+            tx.execute("DELETE FROM library_files WHERE path = ?1", params![path])
+                .map_err(|e| format!("Failed to remove library file: {}", e))?;
+            result.removed += 1;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn get_library_folders() -> Result<Vec<String>, String> {
+    Ok(library_folders()
+        .iter()
+        .map(|folder| folder.to_string_lossy().to_string())
+        .collect())
+}
+
+// Library folders become sandbox roots, so they are only taken from the native
+// folder dialog. Async so the blocking dialog runs off the main thread.
+#[tauri::command]
+pub async fn add_library_folder(app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    // pick_root stores the canonical form so prefix checks against scanned paths line up
+    let picked = sandbox::pick_root(&app_handle, "Choose a library folder")?;
+
+    let folders = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        let mut folders: Vec<String> = db::get_setting(&conn, LIBRARY_FOLDERS_KEY)?.unwrap_or_default();
+        let Some(path) = picked else {
+            return Ok(folders);
+        };
+        let path = path.to_string_lossy().to_string();
+        if folders.contains(&path) {
+            return Ok(folders);
+        }
+        folders.push(path);
+        db::set_setting(&conn, LIBRARY_FOLDERS_KEY, &folders)?;
+        folders
+    };
+
+    library_watcher::restart(&app_handle);
+    Ok(folders)
+}
+
+// Escape LIKE wildcards so folder names containing % or _ match literally.
+// '!' is the escape character because '\\' is the Windows separator.
+fn like_prefix(folder: &str) -> String {
+    let escaped = folder.replace('!', "!!").replace('%', "!%").replace('_', "!_");
+    format!("{}{}%", escaped, std::path::MAIN_SEPARATOR)
+}
+
+#[tauri::command]
+pub fn remove_library_folder(app_handle: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+    // Folders are stored canonicalized; a folder that no longer exists is
+    // matched as given
+    let path = fs::canonicalize(&path)
+        .map(|resolved| resolved.to_string_lossy().to_string())
+        .unwrap_or(path);
+    let folders = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        let mut folders: Vec<String> = db::get_setting(&conn, LIBRARY_FOLDERS_KEY)?.unwrap_or_default();
+        folders.retain(|folder| folder != &path);
+        db::set_setting(&conn, LIBRARY_FOLDERS_KEY, &folders)?;
+
+        conn.execute(
+            "DELETE FROM library_files WHERE path = ?1 OR path LIKE ?2 ESCAPE '!'",
+            params![path, like_prefix(&path)],
+        )
+        .map_err(|e| format!("Failed to remove library files: {}", e))?;
+        folders
+    };
+
+    library_watcher::restart(&app_handle);
+    Ok(folders)
+}
+
+#[tauri::command]
+pub async fn scan_library() -> Result<LibraryScanResult, String> {
+    tauri::async_runtime::spawn_blocking(|| scan_folders(&library_folders()))
+        .await
+        .map_err(|e| format!("Library scan task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn list_library_files(anime_id: Option<i64>) -> Result<Vec<LibraryFile>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM library_files WHERE ?1 IS NULL OR anime_id = ?1
+             ORDER BY anime_id, episode, file_name",
+            FILE_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![anime_id], row_to_file)
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+#[tauri::command]
+pub fn get_unmatched_library_files() -> Result<Vec<LibraryFile>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM library_files WHERE anime_id IS NULL ORDER BY parsed_title, parsed_episode",
+            FILE_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], row_to_file)
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+// Link a file by hand; passing no anime_id clears the link and lets the next scan retry
+#[tauri::command]
+pub fn link_library_file(
+    path: String,
+    anime_id: Option<i64>,
+    episode: Option<i32>,
+) -> Result<LibraryFile, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let updated = conn
+        .execute(
+            "UPDATE library_files SET anime_id = ?1, episode = COALESCE(?2, episode),
+                match_score = NULL, manual = ?3 WHERE path = ?4",
+            params![anime_id, episode, anime_id.is_some(), path],
+        )
+        .map_err(|e| format!("Failed to link library file: {}", e))?;
+    if updated == 0 {
+        return Err(format!("File is not in the library: {}", path));
+    }
+
+    get_file(&conn, &path)?.ok_or_else(|| format!("File is not in the library: {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (file name, group, title, season, episode, resolution, checksum)
+    type Case<'a> = (
+        &'a str,
+        Option<&'a str>,
+        &'a str,
+        Option<i32>,
+        Option<i32>,
+        Option<&'a str>,
+        Option<&'a str>,
+    );
+
+    #[test]
+    fn parse_release_table() {
+        let cases: &[Case] = &[
+            (
+                "[SubsPlease] Sousou no Frieren - 05 (1080p) [ABCD1234].mkv",
+                Some("SubsPlease"), "Sousou no Frieren", None, Some(5), Some("1080p"), Some("ABCD1234"),
+            ),
+            (
+                "[abcd1234] Title - 03.mkv",
+                None, "Title", None, Some(3), None, Some("ABCD1234"),
+            ),
+            (
+                "[Group] Title - 07v2 [720p].mkv",
+                Some("Group"), "Title", None, Some(7), Some("720p"), None,
+            ),
+            (
+                "Title.S02E05.1080p.WEB.mkv",
+                None, "Title", Some(2), Some(5), Some("1080p"), None,
+            ),
+            (
+                "[Group] Title S2 - 05 [1080p].mkv",
+                Some("Group"), "Title", Some(2), Some(5), Some("1080p"), None,
+            ),
+            (
+                "[Group] Title 2nd Season - 10 (1920x1080 x264).mkv",
+                Some("Group"), "Title", Some(2), Some(10), Some("1080p"), None,
+            ),
+            (
+                "Title.1920x1080.E03.mkv",
+                None, "Title", None, Some(3), Some("1080p"), None,
+            ),
+            (
+                "Title Episode 4 [480p].mp4",
+                None, "Title", None, Some(4), Some("480p"), None,
+            ),
+            (
+                "[Group] Title - 01-12 [BD 1080p].mkv",
+                Some("Group"), "Title", None, None, Some("1080p"), None,
+            ),
+            (
+                "[Group] Mob Psycho 100 - 05 [1080p].mkv",
+                Some("Group"), "Mob Psycho 100", None, Some(5), Some("1080p"), None,
+            ),
+            (
+                "[Group] Title 01~26 [1080p].mkv",
+                Some("Group"), "Title", None, None, Some("1080p"), None,
+            ),
+        ];
+
+        for (file_name, group, title, season, episode, resolution, checksum) in cases {
+            let expected = ParsedRelease {
+                group: group.map(str::to_string),
+                title: title.to_string(),
+                season: *season,
+                episode: *episode,
+                resolution: resolution.map(str::to_string),
+                checksum: checksum.map(str::to_string),
+            };
+            assert_eq!(parse_release(file_name).as_ref(), Some(&expected), "{}", file_name);
+        }
+    }
+
+    #[test]
+    fn parse_release_without_title() {
+        assert_eq!(parse_release("[Group] [1080p].mkv"), None);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::db::DB_CONNECTION;
+use md4::{Digest, Md4};
+use rusqlite::params;
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+pub(crate) const VERIFY_PROGRESS_EVENT: &str = "library://verify-progress";
+pub(crate) const VERIFY_FINISHED_EVENT: &str = "library://verify-finished";
+
+// ED2K hashes files in 9500 KiB chunks
+const ED2K_CHUNK_LEN: u64 = 9_728_000;
+const READ_BUFFER_LEN: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+static VERIFY_RUNNING: AtomicBool = AtomicBool::new(false);
+static VERIFY_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyProgress {
+    pub path: String,
+    pub file_index: usize,
+    pub file_count: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifySummary {
+    pub verified: usize,
+    pub mismatched: usize,
+    pub computed: usize,
+    pub failed: usize,
+    pub cancelled: bool,
+    // Set when the job stopped early on an error; the counts cover the files done before it
+    pub error: Option<String>,
+}
+
+// Clears VERIFY_RUNNING when the job ends, including by a panic
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        VERIFY_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+struct FileHashes {
+    crc32: String,
+    ed2k: Option<String>,
+}
+
+// MD4 of the file for single-chunk files, otherwise MD4 over the chunk digests.
+// Files that are an exact multiple of the chunk size do not get the trailing
+// empty chunk, matching current eMule and AniDB.
+struct Ed2kHasher {
+    chunk: Md4,
+    chunk_len: u64,
+    chunk_digests: Vec<u8>,
+    chunk_count: usize,
+}
+
+impl Ed2kHasher {
+    fn new() -> Self {
+        Ed2kHasher {
+            chunk: Md4::new(),
+            chunk_len: 0,
+            chunk_digests: Vec::new(),
+            chunk_count: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.chunk_len == ED2K_CHUNK_LEN {
+                let digest = std::mem::replace(&mut self.chunk, Md4::new()).finalize();
+                self.chunk_digests.extend_from_slice(&digest);
+                self.chunk_count += 1;
+                self.chunk_len = 0;
+            }
+            let take = ((ED2K_CHUNK_LEN - self.chunk_len) as usize).min(data.len());
+            self.chunk.update(&data[..take]);
+            self.chunk_len += take as u64;
+            data = &data[take..];
+        }
+    }
+
+    fn finalize(self) -> String {
+        let last = self.chunk.finalize();
+        let digest = if self.chunk_count == 0 {
+            last.to_vec()
+        } else {
+            let mut digests = self.chunk_digests;
+            digests.extend_from_slice(&last);
+            Md4::digest(&digests).to_vec()
+        };
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+fn hash_file<F: FnMut(u64)>(path: &Path, include_ed2k: bool, mut progress: F) -> Result<FileHashes, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Failed to open file {}: {}", path.display(), e))?;
+
+    let mut crc = crc32fast::Hasher::new();
+    let mut ed2k = if include_ed2k { Some(Ed2kHasher::new()) } else { None };
+    let mut buffer = vec![0u8; READ_BUFFER_LEN];
+    let mut done = 0u64;
+    loop {
+        if VERIFY_CANCELLED.load(Ordering::SeqCst) {
+            return Err("Verification cancelled".to_string());
+        }
+
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        crc.update(&buffer[..read]);
+        if let Some(ed2k) = ed2k.as_mut() {
+            ed2k.update(&buffer[..read]);
+        }
+        done += read as u64;
+        progress(done);
+    }
+
+    Ok(FileHashes {
+        crc32: format!("{:08X}", crc.finalize()),
+        ed2k: ed2k.map(Ed2kHasher::finalize),
+    })
+}
+
+// Linked files, optionally restricted to `paths`. Already verified files are
+// skipped unless `force` is set.
+fn pending_files(paths: Option<&[String]>, force: bool) -> Result<Vec<(String, Option<String>, u64)>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, checksum, size FROM library_files
+             WHERE anime_id IS NOT NULL AND (?1 OR verify_status IS NULL)
+             ORDER BY path",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![force], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, i64>(2)? as u64))
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut files = Vec::new();
+    for row in rows {
+        let file = row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        if paths.map(|paths| paths.contains(&file.0)).unwrap_or(true) {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+fn store_result(path: &str, hashes: Option<&FileHashes>, status: &str) -> Result<(), String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    conn.execute(
+        "UPDATE library_files SET crc32 = ?1, ed2k = COALESCE(?2, ed2k), verify_status = ?3,
+            verified_at = ?4 WHERE path = ?5",
+        params![
+            hashes.map(|h| h.crc32.clone()),
+            hashes.and_then(|h| h.ed2k.clone()),
+            status,
+            chrono::Utc::now().timestamp(),
+            path,
+        ],
+    )
+    .map_err(|e| format!("Failed to store verification result: {}", e))?;
+    Ok(())
+}
+
+fn run_verification(
+    app_handle: &tauri::AppHandle,
+    paths: Option<Vec<String>>,
+    include_ed2k: bool,
+    force: bool,
+    summary: &mut VerifySummary,
+) -> Result<(), String> {
+    let files = pending_files(paths.as_deref(), force)?;
+
+    for (index, (path, expected, size)) in files.iter().enumerate() {
+        let mut last_emit = Instant::now() - PROGRESS_INTERVAL;
+        let mut emit = |bytes_done: u64| {
+            if last_emit.elapsed() >= PROGRESS_INTERVAL || bytes_done == *size {
+                last_emit = Instant::now();
+                let _ = app_handle.emit(
+                    VERIFY_PROGRESS_EVENT,
+                    VerifyProgress {
+                        path: path.clone(),
+                        file_index: index,
+                        file_count: files.len(),
+                        bytes_done,
+                        bytes_total: *size,
+                    },
+                );
+            }
+        };
+        emit(0);
+
+        match hash_file(Path::new(path), include_ed2k, &mut emit) {
+            Ok(hashes) => {
+                let status = match expected {
+                    Some(expected) if expected.eq_ignore_ascii_case(&hashes.crc32) => {
+                        summary.verified += 1;
+                        "ok"
+                    }
+                    Some(_) => {
+                        summary.mismatched += 1;
+                        "mismatch"
+                    }
+                    None => {
+                        summary.computed += 1;
+                        "computed"
+                    }
+                };
+                store_result(path, Some(&hashes), status)?;
+            }
+            Err(_) if VERIFY_CANCELLED.load(Ordering::SeqCst) => {
+                summary.cancelled = true;
+                break;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                summary.failed += 1;
+                store_result(path, None, "error")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Hash linked library files on a background thread. Progress and the final
+// summary are reported through events; only one job runs at a time.
+#[tauri::command]
+pub fn verify_library_files(
+    app_handle: tauri::AppHandle,
+    paths: Option<Vec<String>>,
+    include_ed2k: Option<bool>,
+    force: Option<bool>,
+) -> Result<(), String> {
+    if VERIFY_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A library verification is already running".to_string());
+    }
+    VERIFY_CANCELLED.store(false, Ordering::SeqCst);
+
+    std::thread::spawn(move || {
+        let running = RunningGuard;
+        let mut summary = VerifySummary {
+            verified: 0,
+            mismatched: 0,
+            computed: 0,
+            failed: 0,
+            cancelled: false,
+            error: None,
+        };
+        if let Err(e) = run_verification(
+            &app_handle,
+            paths,
+            include_ed2k.unwrap_or(false),
+            force.unwrap_or(false),
+            &mut summary,
+        ) {
+            eprintln!("Library verification failed: {}", e);
+            summary.error = Some(e);
+        }
+        // A new job may start as soon as the UI hears this one finished
+        drop(running);
+        let _ = app_handle.emit(VERIFY_FINISHED_EVENT, summary);
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn cancel_library_verification() -> Result<bool, String> {
+    let running = VERIFY_RUNNING.load(Ordering::SeqCst);
+    if running {
+        VERIFY_CANCELLED.store(true, Ordering::SeqCst);
+    }
+    Ok(running)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ed2k(data: &[u8], piece_len: usize) -> String {
+        let mut hasher = Ed2kHasher::new();
+        for piece in data.chunks(piece_len) {
+            hasher.update(piece);
+        }
+        hasher.finalize()
+    }
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn shorter_than_one_chunk_is_plain_md4() {
+        assert_eq!(ed2k(b"", 1), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(ed2k(b"abc", 2), "a448017aaf21d8525fc10ae87aa6729d");
+    }
+
+    #[test]
+    fn exactly_one_chunk_has_no_trailing_empty_chunk() {
+        let data = vec![0u8; ED2K_CHUNK_LEN as usize];
+        assert_eq!(ed2k(&data, READ_BUFFER_LEN), "d7def262a127cd79096a108e7a9fc138");
+        assert_eq!(ed2k(&data, READ_BUFFER_LEN), hex(&Md4::digest(&data)));
+
+        // The legacy variant appends the digest of an empty chunk instead
+        let mut legacy = Md4::digest(&data).to_vec();
+        legacy.extend_from_slice(&Md4::digest(b""));
+        assert_eq!(hex(&Md4::digest(&legacy)), "fc21d9af828f92a8df64beac3357425d");
+    }
+
+    #[test]
+    fn one_byte_past_a_chunk_hashes_the_chunk_digests() {
+        let data = vec![0u8; ED2K_CHUNK_LEN as usize + 1];
+        let mut digests = Md4::digest(&data[..ED2K_CHUNK_LEN as usize]).to_vec();
+        digests.extend_from_slice(&Md4::digest([0u8]));
+        let expected = hex(&Md4::digest(&digests));
+
+        assert_eq!(ed2k(&data, READ_BUFFER_LEN), expected);
+        assert_eq!(expected, "06329e9dba1373512c06386fe29e3c65");
+        // Reads that straddle the chunk boundary give the same result
+        assert_eq!(ed2k(&data, 1_000_003), expected);
+    }
+}
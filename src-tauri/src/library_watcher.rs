@@ -0,0 +1,211 @@
+use crate::db::DB_CONNECTION;
+use crate::library::{self, LibraryFile};
+use notify_debouncer_full::notify::event::{EventKind, ModifyKind};
+use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use once_cell::sync::Lazy;
+use rusqlite::params;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Emitter;
+
+pub(crate) const FILE_ADDED_EVENT: &str = "library://file-added";
+pub(crate) const FILE_REMOVED_EVENT: &str = "library://file-removed";
+
+// Downloads write in bursts; wait for the folder to settle before parsing
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(3);
+// New files must keep the same size over this long to count as complete
+const STABLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Control files some download clients keep beside an unfinished download,
+// e.g. Firefox's "name.mkv.part" and aria2's "name.mkv.aria2"
+const PARTIAL_EXTENSIONS: [&str; 3] = ["part", "aria2", "crdownload"];
+
+static WATCHER: Lazy<Mutex<Option<Debouncer<RecommendedWatcher, FileIdMap>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryFileEvent {
+    pub file: LibraryFile,
+    // Title from the user's list or the metadata cache, for "Episode 6 of X is available"
+    pub anime_title: Option<String>,
+}
+
+fn anime_title(anime_id: i64) -> Option<String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    conn.query_row(
+        "SELECT title FROM user_anime WHERE anime_id = ?1 AND title != ''
+         UNION ALL SELECT title FROM anime_metadata WHERE anime_id = ?1
+         LIMIT 1",
+        params![anime_id],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+}
+
+fn handle_added(app_handle: &tauri::AppHandle, path: &Path) -> Result<(), String> {
+    let file = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        let key = path.to_string_lossy().to_string();
+        let known = library::get_file(&conn, &key)?.is_some();
+        let candidates = library::load_candidates(&conn)?;
+        match library::index_file(&conn, &candidates, path)? {
+            // Modified files that were already indexed are refreshed silently
+            Some(file) if !known => file,
+            _ => return Ok(()),
+        }
+    };
+
+    let anime_title = file.anime_id.and_then(anime_title);
+    app_handle
+        .emit(FILE_ADDED_EVENT, LibraryFileEvent { file, anime_title })
+        .map_err(|e| format!("Failed to emit library event: {}", e))
+}
+
+fn handle_removed(app_handle: &tauri::AppHandle, path: &Path) -> Result<(), String> {
+    let key = path.to_string_lossy().to_string();
+    let removed = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        conn.execute("DELETE FROM library_files WHERE path = ?1", params![key])
+            .map_err(|e| format!("Failed to remove library file: {}", e))?
+    };
+
+    if removed > 0 {
+        app_handle
+            .emit(FILE_REMOVED_EVENT, key)
+            .map_err(|e| format!("Failed to emit library event: {}", e))?;
+    }
+    Ok(())
+}
+
+fn is_partial_marker(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| PARTIAL_EXTENSIONS.iter().any(|partial| ext.eq_ignore_ascii_case(partial)))
+        .unwrap_or(false)
+}
+
+fn has_partial_marker(path: &Path) -> bool {
+    PARTIAL_EXTENSIONS.iter().any(|ext| {
+        let mut marker = path.as_os_str().to_os_string();
+        marker.push(".");
+        marker.push(ext);
+        Path::new(&marker).exists()
+    })
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+fn handle_events(app_handle: &tauri::AppHandle, result: DebounceEventResult) {
+    let events = match result {
+        Ok(events) => events,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("Library watcher error: {}", e);
+            }
+            return;
+        }
+    };
+
+    // A burst of writes to one file arrives as several events; handle each path once
+    let mut touched = BTreeSet::new();
+    for event in events {
+        match event.kind {
+            EventKind::Create(_)
+            | EventKind::Modify(ModifyKind::Name(_))
+            | EventKind::Modify(ModifyKind::Data(_))
+            | EventKind::Remove(_) => {
+                for path in &event.paths {
+                    // A control file going away can be the only sign a download finished
+                    if is_partial_marker(path) {
+                        touched.insert(path.with_extension(""));
+                    }
+                    touched.insert(path.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut added = Vec::new();
+    for path in touched {
+        if !library::is_video_file(&path) {
+            continue;
+        }
+
+        // Renames report both the old and the new path, so existence decides
+        if path.is_file() {
+            if !has_partial_marker(&path) {
+                added.push(path);
+            }
+        } else if let Err(e) = handle_removed(app_handle, &path) {
+            eprintln!("Failed to process library change for {}: {}", path.display(), e);
+        }
+    }
+
+    // Files still growing are skipped; the write that grew them brings them back
+    if added.is_empty() {
+        return;
+    }
+    let sizes: Vec<Option<u64>> = added.iter().map(|path| file_size(path)).collect();
+    std::thread::sleep(STABLE_INTERVAL);
+    for (path, size) in added.iter().zip(sizes) {
+        if size.is_none() || file_size(path) != size {
+            continue;
+        }
+        if let Err(e) = handle_added(app_handle, path) {
+            eprintln!("Failed to process library change for {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn watch(
+    app_handle: tauri::AppHandle,
+    folders: &[PathBuf],
+) -> Result<Debouncer<RecommendedWatcher, FileIdMap>, String> {
+    let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, None, move |result| {
+        handle_events(&app_handle, result)
+    })
+    .map_err(|e| format!("Failed to create library watcher: {}", e))?;
+
+    for folder in folders.iter().filter(|folder| folder.is_dir()) {
+        debouncer
+            .watcher()
+            .watch(folder, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+        debouncer.cache().add_root(folder, RecursiveMode::Recursive);
+    }
+
+    Ok(debouncer)
+}
+
+// (Re)start watching the configured library folders. A catch-up scan runs
+// first so files added while the app was closed are indexed too.
+pub(crate) fn restart(app_handle: &tauri::AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        // Held until the new watcher is installed so overlapping restarts
+        // cannot leave a watcher for stale folders behind
+        let mut watcher = WATCHER.lock().unwrap();
+        if let Some(debouncer) = watcher.take() {
+            debouncer.stop_nonblocking();
+        }
+
+        let folders = library::library_folders();
+        if folders.is_empty() {
+            return;
+        }
+
+        if let Err(e) = library::scan_folders(&folders) {
+            eprintln!("Library scan failed: {}", e);
+        }
+        match watch(app_handle, &folders) {
+            Ok(debouncer) => *watcher = Some(debouncer),
+            Err(e) => eprintln!("{}", e),
+        }
+    });
+}
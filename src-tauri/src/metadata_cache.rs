@@ -0,0 +1,226 @@
+use crate::airing;
+use crate::db::DB_CONNECTION;
+use crate::franchise;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::collections::HashSet;
+
+// Typical TV episode length, used when the provider gives none
+pub(crate) const DEFAULT_EPISODE_MINUTES: i64 = 24;
+
+static DURATION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:(\d+)\s*hr)?\s*(?:(\d+)\s*min)?\s*(?:(\d+)\s*sec)?").unwrap());
+
+// Raw Jikan anime objects keyed by MAL id. The frontend pushes every detail
+// response it receives so Rust-side features can work without a network round trip.
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS anime_metadata (
+            anime_id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            data TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Parsed episode length, so statistics can sum watch time in SQL
+    let columns = conn
+        .prepare("PRAGMA table_info(anime_metadata)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    if !columns.contains(&"episode_minutes".to_string()) {
+        conn.execute("ALTER TABLE anime_metadata ADD COLUMN episode_minutes INTEGER", [])?;
+
+        let rows = conn
+            .prepare("SELECT anime_id, data FROM anime_metadata")?
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (anime_id, data) in rows {
+            if let Ok(data) = serde_json::from_str::<Value>(&data) {
+                conn.execute(
+                    "UPDATE anime_metadata SET episode_minutes = ?1 WHERE anime_id = ?2",
+                    params![episode_minutes(&data), anime_id],
+                )?;
+            }
+        }
+    }
+
+    // Jikan's user recommendation edges ("people who liked X also liked Y")
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS anime_recommendations (
+            source_id INTEGER NOT NULL,
+            target_id INTEGER NOT NULL,
+            votes INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            image_url TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL,
+            PRIMARY KEY (source_id, target_id)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+// Jikan durations look like "24 min per ep", "1 hr 30 min" or "45 sec per ep"
+pub(crate) fn episode_minutes(data: &Value) -> Option<i64> {
+    let caps = DURATION_RE.captures(data.get("duration")?.as_str()?)?;
+    let part = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<i64>().ok()).unwrap_or(0);
+    let seconds = part(1) * 3600 + part(2) * 60 + part(3);
+    (seconds > 0).then(|| (seconds + 59) / 60)
+}
+
+// Every cover URL in a Jikan `images` object (jpg/webp, small/regular/large)
+pub(crate) fn image_urls(data: &Value) -> Vec<String> {
+    let mut urls = Vec::new();
+    if let Some(images) = data.get("images").and_then(Value::as_object) {
+        for variants in images.values().filter_map(Value::as_object) {
+            for url in variants.values().filter_map(Value::as_str) {
+                urls.push(url.to_string());
+            }
+        }
+    }
+    urls
+}
+
+pub(crate) fn load_all(conn: &Connection) -> Result<Vec<(i64, Value)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT anime_id, data FROM anime_metadata")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let (anime_id, data) = row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        match serde_json::from_str(&data) {
+            Ok(value) => entries.push((anime_id, value)),
+            Err(e) => eprintln!("Skipping unreadable metadata for anime {}: {}", anime_id, e),
+        }
+    }
+    Ok(entries)
+}
+
+pub(crate) fn referenced_image_urls(conn: &Connection) -> Result<HashSet<String>, String> {
+    let mut urls: HashSet<String> = load_all(conn)?
+        .iter()
+        .flat_map(|(_, data)| image_urls(data))
+        .collect();
+
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT image_url FROM anime_recommendations WHERE image_url != ''")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    for row in rows {
+        urls.insert(row.map_err(|e| format!("Failed to retrieve row: {}", e))?);
+    }
+    Ok(urls)
+}
+
+#[tauri::command]
+pub fn cache_anime_metadata(entries: Vec<Value>) -> Result<usize, String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let now = chrono::Utc::now().timestamp();
+    let mut stored = 0;
+    for entry in &entries {
+        let anime_id = match entry.get("mal_id").and_then(Value::as_i64) {
+            Some(id) => id,
+            None => continue,
+        };
+        let title = entry.get("title").and_then(Value::as_str).unwrap_or_default();
+
+        tx.execute(
+            "INSERT INTO anime_metadata (anime_id, title, data, fetched_at, episode_minutes)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(anime_id) DO UPDATE SET title = ?2, data = ?3, fetched_at = ?4, episode_minutes = ?5",
+            params![anime_id, title, entry.to_string(), now, episode_minutes(entry)],
+        )
+        .map_err(|e| format!("Failed to cache anime metadata: {}", e))?;
+        airing::store_schedule(&tx, anime_id, entry)?;
+        // Only /anime/{id}/full responses carry relations
+        if let Some(relations) = entry.get("relations").and_then(Value::as_array) {
+            franchise::store_relations(&tx, anime_id, relations)?;
+        }
+        stored += 1;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(stored)
+}
+
+// Replaces the stored edges of `anime_id` with a Jikan recommendations response
+#[tauri::command]
+pub fn cache_anime_recommendations(anime_id: i64, entries: Vec<Value>) -> Result<usize, String> {
+    let mut conn = DB_CONNECTION.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    tx.execute(
+        "DELETE FROM anime_recommendations WHERE source_id = ?1",
+        params![anime_id],
+    )
+    .map_err(|e| format!("Failed to clear anime recommendations: {}", e))?;
+
+    let now = chrono::Utc::now().timestamp();
+    let mut stored = 0;
+    for entry in &entries {
+        let target = match entry.get("entry") {
+            Some(target) => target,
+            None => continue,
+        };
+        let target_id = match target.get("mal_id").and_then(Value::as_i64) {
+            Some(id) => id,
+            None => continue,
+        };
+        let title = target.get("title").and_then(Value::as_str).unwrap_or_default();
+        let image_url = image_urls(target).into_iter().next().unwrap_or_default();
+        let votes = entry.get("votes").and_then(Value::as_i64).unwrap_or(0);
+
+        tx.execute(
+            "INSERT OR REPLACE INTO anime_recommendations
+             (source_id, target_id, votes, title, image_url, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![anime_id, target_id, votes, title, image_url, now],
+        )
+        .map_err(|e| format!("Failed to cache anime recommendations: {}", e))?;
+        stored += 1;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(stored)
+}
+
+pub(crate) fn load(conn: &Connection, anime_id: i64) -> Result<Option<Value>, String> {
+    match conn.query_row(
+        "SELECT data FROM anime_metadata WHERE anime_id = ?1",
+        params![anime_id],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(data) => serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse cached metadata: {}", e)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Failed to read cached metadata: {}", e)),
+    }
+}
+
+#[tauri::command]
+pub fn get_cached_anime_metadata(anime_id: i64) -> Result<Option<Value>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    load(&conn, anime_id)
+}
@@ -0,0 +1,171 @@
+use crate::db::{self, DB_CONNECTION};
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::Duration;
+use tauri_plugin_notification::NotificationExt;
+
+const NOTIFICATION_SETTINGS_KEY: &str = "notification_settings";
+
+// How often the checker looks for newly aired episodes
+const CHECK_TICK: Duration = Duration::from_secs(60);
+// Broadcasts older than this are never announced, so enabling notifications or
+// starting the app after a while does not replay a backlog of episodes
+const LOOKBACK_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    // Wait this long after the broadcast, e.g. for subtitled releases
+    pub delay_minutes: u32,
+    pub quiet_hours_enabled: bool,
+    // "HH:MM" local time; the range may wrap past midnight
+    pub quiet_start: String,
+    pub quiet_end: String,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            enabled: true,
+            delay_minutes: 0,
+            quiet_hours_enabled: false,
+            quiet_start: "23:00".to_string(),
+            quiet_end: "08:00".to_string(),
+        }
+    }
+}
+
+struct AiredEpisode {
+    anime_id: i64,
+    title: String,
+    episode: i32,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notifications_sent (
+            anime_id INTEGER NOT NULL,
+            episode INTEGER NOT NULL,
+            sent_at INTEGER NOT NULL,
+            PRIMARY KEY (anime_id, episode)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn load_settings() -> Result<NotificationSettings, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    Ok(db::get_setting(&conn, NOTIFICATION_SETTINGS_KEY)?.unwrap_or_default())
+}
+
+fn parse_clock(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|e| format!("Invalid time {}: {}", value, e))
+}
+
+fn in_quiet_hours(settings: &NotificationSettings, now: NaiveTime) -> Result<bool, String> {
+    if !settings.quiet_hours_enabled {
+        return Ok(false);
+    }
+    let start = parse_clock(&settings.quiet_start)?;
+    let end = parse_clock(&settings.quiet_end)?;
+    Ok(if start <= end {
+        now >= start && now < end
+    } else {
+        now >= start || now < end
+    })
+}
+
+// Episodes of watching entries that aired (plus the delay) but have not been
+// announced yet. Episodes the user already watched are left out.
+fn pending_episodes(conn: &Connection, settings: &NotificationSettings, now: DateTime<Utc>) -> Result<Vec<AiredEpisode>, String> {
+    let cutoff = now.timestamp() - i64::from(settings.delay_minutes) * 60;
+    let mut stmt = conn
+        .prepare(
+            "SELECT s.anime_id, u.title, s.episode FROM airing_schedule s
+             JOIN user_anime u ON u.anime_id = s.anime_id
+             LEFT JOIN notifications_sent n ON n.anime_id = s.anime_id AND n.episode = s.episode
+             WHERE u.status = 'watching' AND s.episode > u.progress AND n.anime_id IS NULL
+               AND s.airs_at <= ?1 AND s.airs_at > ?2
+             ORDER BY s.airs_at",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![cutoff, cutoff - LOOKBACK_HOURS * 3600], |row| {
+            Ok(AiredEpisode {
+                anime_id: row.get(0)?,
+                title: row.get(1)?,
+                episode: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+fn mark_sent(conn: &Connection, episode: &AiredEpisode) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO notifications_sent (anime_id, episode, sent_at) VALUES (?1, ?2, ?3)",
+        params![episode.anime_id, episode.episode, Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Failed to record notification: {}", e))?;
+    Ok(())
+}
+
+fn check_and_notify(app_handle: &tauri::AppHandle) -> Result<usize, String> {
+    let settings = load_settings()?;
+    if !settings.enabled {
+        return Ok(0);
+    }
+    // Held back, not dropped: they go out once quiet hours end
+    if in_quiet_hours(&settings, Local::now().time())? {
+        return Ok(0);
+    }
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut sent = 0;
+    for episode in pending_episodes(&conn, &settings, Utc::now())? {
+        let shown = app_handle
+            .notification()
+            .builder()
+            .title(episode.title.clone())
+            .body(format!("Episode {} is out", episode.episode))
+            .show();
+        match shown {
+            Ok(()) => {
+                mark_sent(&conn, &episode)?;
+                sent += 1;
+            }
+            Err(e) => eprintln!("Failed to show notification for {}: {}", episode.title, e),
+        }
+    }
+    Ok(sent)
+}
+
+pub fn start_checker(app_handle: tauri::AppHandle) {
+    thread::spawn(move || loop {
+        if let Err(e) = check_and_notify(&app_handle) {
+            eprintln!("Episode notification check failed: {}", e);
+        }
+        thread::sleep(CHECK_TICK);
+    });
+}
+
+#[tauri::command]
+pub fn get_notification_settings() -> Result<NotificationSettings, String> {
+    load_settings()
+}
+
+#[tauri::command]
+pub fn update_notification_settings(settings: NotificationSettings) -> Result<NotificationSettings, String> {
+    parse_clock(&settings.quiet_start)?;
+    parse_clock(&settings.quiet_end)?;
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    db::set_setting(&conn, NOTIFICATION_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
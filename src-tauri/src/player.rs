@@ -0,0 +1,519 @@
+use crate::db::{self, DB_CONNECTION};
+use crate::goals;
+use crate::library;
+use crate::watch_history::{self, WatchEvent};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+use tauri_plugin_shell::process::CommandEvent;
+use tauri_plugin_shell::ShellExt;
+
+const PLAYER_SETTINGS_KEY: &str = "player_settings";
+pub(crate) const EPISODE_WATCHED_EVENT: &str = "player://episode-watched";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const IPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayerKind {
+    #[default]
+    Mpv,
+    Vlc,
+    Custom,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSettings {
+    pub player: PlayerKind,
+    pub mpv_path: String,
+    pub vlc_path: String,
+    // Program and arguments for a custom player; {file} is replaced with the episode
+    // path, which is appended when the placeholder is missing. Playback is not tracked.
+    pub custom_command: String,
+    // Fraction of the episode after which it counts as watched
+    pub watched_threshold: f64,
+    // Port for VLC's web interface, only bound to localhost
+    pub vlc_http_port: u16,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        PlayerSettings {
+            player: PlayerKind::Mpv,
+            mpv_path: "mpv".to_string(),
+            vlc_path: "vlc".to_string(),
+            custom_command: String::new(),
+            watched_threshold: 0.85,
+            vlc_http_port: 8089,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackSession {
+    pub session_id: u64,
+    pub path: String,
+    pub anime_id: i64,
+    pub episode: Option<i32>,
+    pub player: PlayerKind,
+    // False when progress cannot be read back (custom players, unknown episode)
+    pub tracking: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct PlaybackPosition {
+    // 0.0 - 1.0
+    pub fraction: Option<f64>,
+    pub duration_seconds: Option<f64>,
+}
+
+enum Tracker {
+    Mpv { ipc_path: String },
+    Vlc { port: u16, password: String },
+}
+
+impl Tracker {
+    fn query(&self) -> Result<PlaybackPosition, String> {
+        match self {
+            Tracker::Mpv { ipc_path } => query_mpv(ipc_path),
+            Tracker::Vlc { port, password } => query_vlc(*port, password),
+        }
+    }
+}
+
+pub(crate) fn load_settings() -> Result<PlayerSettings, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    Ok(db::get_setting(&conn, PLAYER_SETTINGS_KEY)?.unwrap_or_default())
+}
+
+// Ask mpv for percent-pos and duration over its JSON IPC protocol. Replies are
+// matched by request_id since mpv interleaves unrelated event lines.
+pub(crate) fn mpv_request<S: Read + Write>(stream: S) -> Result<PlaybackPosition, String> {
+    let mut reader = BufReader::new(stream);
+    let request = "{\"command\":[\"get_property\",\"percent-pos\"],\"request_id\":1}\n\
+                   {\"command\":[\"get_property\",\"duration\"],\"request_id\":2}\n";
+    reader
+        .get_mut()
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("Failed to write to mpv: {}", e))?;
+
+    let mut position = PlaybackPosition::default();
+    let mut pending = 2;
+    let mut line = String::new();
+    while pending > 0 {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read from mpv: {}", e))?;
+        if read == 0 {
+            return Err("mpv closed the IPC connection".to_string());
+        }
+
+        let reply: Value = match serde_json::from_str(&line) {
+            Ok(reply) => reply,
+            Err(_) => continue,
+        };
+        let data = reply.get("data").and_then(Value::as_f64);
+        match reply.get("request_id").and_then(Value::as_u64) {
+            Some(1) => {
+                position.fraction = data.map(|percent| percent / 100.0);
+                pending -= 1;
+            }
+            Some(2) => {
+                position.duration_seconds = data;
+                pending -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(position)
+}
+
+#[cfg(unix)]
+pub(crate) fn query_mpv(ipc_path: &str) -> Result<PlaybackPosition, String> {
+    let stream = std::os::unix::net::UnixStream::connect(ipc_path)
+        .map_err(|e| format!("Failed to connect to mpv: {}", e))?;
+    stream
+        .set_read_timeout(Some(IPC_TIMEOUT))
+        .map_err(|e| format!("Failed to configure mpv connection: {}", e))?;
+    mpv_request(stream)
+}
+
+#[cfg(windows)]
+pub(crate) fn query_mpv(ipc_path: &str) -> Result<PlaybackPosition, String> {
+    let pipe = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(ipc_path)
+        .map_err(|e| format!("Failed to connect to mpv: {}", e))?;
+
+    // Pipes opened through std have no read timeout, so the reply is awaited on
+    // a helper thread. A read left hanging ends once mpv exits and closes the pipe.
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(mpv_request(pipe));
+    });
+    receiver
+        .recv_timeout(IPC_TIMEOUT)
+        .map_err(|_| "Timed out waiting for mpv".to_string())?
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Parse VLC's /requests/status.json reply (raw HTTP/1.0 response)
+pub(crate) fn parse_vlc_status(response: &[u8]) -> Result<PlaybackPosition, String> {
+    let response = String::from_utf8_lossy(response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| "Malformed response from VLC".to_string())?;
+    let status_line = head.lines().next().unwrap_or_default();
+    if !status_line.split_whitespace().nth(1).map(|code| code == "200").unwrap_or(false) {
+        return Err(format!("VLC returned {}", status_line));
+    }
+
+    let status: Value = serde_json::from_str(body)
+        .map_err(|e| format!("Failed to parse VLC status: {}", e))?;
+    Ok(PlaybackPosition {
+        fraction: status.get("position").and_then(Value::as_f64),
+        duration_seconds: status
+            .get("length")
+            .and_then(Value::as_f64)
+            .filter(|length| *length > 0.0),
+    })
+}
+
+pub(crate) fn query_vlc(port: u16, password: &str) -> Result<PlaybackPosition, String> {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut stream = TcpStream::connect_timeout(&address, IPC_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to VLC: {}", e))?;
+    stream
+        .set_read_timeout(Some(IPC_TIMEOUT))
+        .map_err(|e| format!("Failed to configure VLC connection: {}", e))?;
+
+    // VLC's web interface uses basic auth with an empty user name
+    let request = format!(
+        "GET /requests/status.json HTTP/1.0\r\nHost: 127.0.0.1:{}\r\nAuthorization: Basic {}\r\n\r\n",
+        port,
+        base64(format!(":{}", password).as_bytes())
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("Failed to query VLC: {}", e))?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|e| format!("Failed to read VLC status: {}", e))?;
+    parse_vlc_status(&response)
+}
+
+// Split a command line on whitespace, keeping double-quoted segments together
+fn split_command(command: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in command.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+fn mpv_ipc_path(session_id: u64) -> String {
+    let name = format!("anitrack-mpv-{}-{}", std::process::id(), session_id);
+    if cfg!(windows) {
+        format!(r"\\.\pipe\{}", name)
+    } else {
+        std::env::temp_dir()
+            .join(format!("{}.sock", name))
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+fn random_password() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate password: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn mark_watched(
+    app_handle: &tauri::AppHandle,
+    anime_id: i64,
+    episode: i32,
+    path: &str,
+    duration_seconds: Option<f64>,
+) -> Result<WatchEvent, String> {
+    let event = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        // Rewatching an earlier episode must not move progress backwards
+        conn.execute(
+            "UPDATE user_anime SET progress = ?1 WHERE anime_id = ?2 AND progress < ?1",
+            params![episode, anime_id],
+        )
+        .map_err(|e| format!("Failed to update progress: {}", e))?;
+        watch_history::record_watch_event(&conn, anime_id, episode, "player", Some(path), duration_seconds)?
+    };
+    db::mark_data_changed();
+
+    app_handle
+        .emit(EPISODE_WATCHED_EVENT, event.clone())
+        .map_err(|e| format!("Failed to emit watch event: {}", e))?;
+    goals::check_goals(app_handle);
+    Ok(event)
+}
+
+fn monitor(
+    app_handle: tauri::AppHandle,
+    session: PlaybackSession,
+    tracker: Tracker,
+    threshold: f64,
+    exited: Arc<AtomicBool>,
+) {
+    let episode = match session.episode {
+        Some(episode) => episode,
+        None => return,
+    };
+
+    while !exited.load(Ordering::SeqCst) {
+        std::thread::sleep(POLL_INTERVAL);
+
+        // Connection errors are expected while the player is still starting up
+        let position = match tracker.query() {
+            Ok(position) => position,
+            Err(_) => continue,
+        };
+
+        if position.fraction.map(|f| f >= threshold).unwrap_or(false) {
+            if let Err(e) = mark_watched(
+                &app_handle,
+                session.anime_id,
+                episode,
+                &session.path,
+                position.duration_seconds,
+            ) {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_player_settings() -> Result<PlayerSettings, String> {
+    load_settings()
+}
+
+#[tauri::command]
+pub fn update_player_settings(settings: PlayerSettings) -> Result<PlayerSettings, String> {
+    if !(settings.watched_threshold > 0.0 && settings.watched_threshold <= 1.0) {
+        return Err("Watched threshold must be between 0 and 1".to_string());
+    }
+    if settings.player == PlayerKind::Custom && split_command(&settings.custom_command).is_empty() {
+        return Err("A custom player needs a command".to_string());
+    }
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    db::set_setting(&conn, PLAYER_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+// Open a linked library file in the configured player and track how far it gets
+#[tauri::command]
+pub fn play_library_file(app_handle: tauri::AppHandle, path: String) -> Result<PlaybackSession, String> {
+    // Only indexed files can be launched, which keeps arbitrary paths out of the player
+    let file = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        library::get_file(&conn, &path)?
+    }
+    .ok_or_else(|| format!("File is not in the library: {}", path))?;
+    let anime_id = file
+        .anime_id
+        .ok_or_else(|| format!("Link {} to an anime before playing it", file.file_name))?;
+
+    let settings = load_settings()?;
+    let session_id = NEXT_SESSION_ID.fetch_add(1, Ordering::SeqCst);
+
+    let (program, args, tracker) = match settings.player {
+        PlayerKind::Mpv => {
+            let ipc_path = mpv_ipc_path(session_id);
+            let args = vec![format!("--input-ipc-server={}", ipc_path), file.path.clone()];
+            (settings.mpv_path.clone(), args, Some(Tracker::Mpv { ipc_path }))
+        }
+        PlayerKind::Vlc => {
+            let password = random_password()?;
+            let args = vec![
+                "--extraintf=http".to_string(),
+                "--http-host=127.0.0.1".to_string(),
+                format!("--http-port={}", settings.vlc_http_port),
+                format!("--http-password={}", password),
+                file.path.clone(),
+            ];
+            let tracker = Tracker::Vlc {
+                port: settings.vlc_http_port,
+                password,
+            };
+            (settings.vlc_path.clone(), args, Some(tracker))
+        }
+        PlayerKind::Custom => {
+            let mut parts = split_command(&settings.custom_command);
+            if parts.is_empty() {
+                return Err("No custom player command configured".to_string());
+            }
+            let program = parts.remove(0);
+            if parts.iter().any(|part| part.contains("{file}")) {
+                for part in &mut parts {
+                    *part = part.replace("{file}", &file.path);
+                }
+            } else {
+                parts.push(file.path.clone());
+            }
+            (program, parts, None)
+        }
+    };
+
+    let (mut events, _child) = app_handle
+        .shell()
+        .command(&program)
+        .args(&args)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+
+    let session = PlaybackSession {
+        session_id,
+        path: file.path.clone(),
+        anime_id,
+        episode: file.episode,
+        player: settings.player,
+        tracking: tracker.is_some() && file.episode.is_some(),
+    };
+
+    let exited = Arc::new(AtomicBool::new(false));
+    {
+        let exited = exited.clone();
+        std::thread::spawn(move || {
+            while let Some(event) = events.blocking_recv() {
+                if let CommandEvent::Terminated(_) = event {
+                    break;
+                }
+            }
+            exited.store(true, Ordering::SeqCst);
+        });
+    }
+
+    if let Some(tracker) = tracker {
+        let session = session.clone();
+        let threshold = settings.watched_threshold;
+        std::thread::spawn(move || monitor(app_handle, session, tracker, threshold, exited));
+    }
+
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn mpv_request_skips_events_and_matches_request_ids() {
+        use std::os::unix::net::UnixStream;
+
+        let (client, server) = UnixStream::pair().unwrap();
+        let player = std::thread::spawn(move || {
+            let mut reader = BufReader::new(server);
+            let mut requests = Vec::new();
+            for _ in 0..2 {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                requests.push(line);
+            }
+            // Events can arrive before the replies, and replies in any order
+            let mut server = reader.into_inner();
+            server
+                .write_all(
+                    b"{\"event\":\"property-change\",\"id\":1,\"name\":\"pause\",\"data\":false}\n\
+                      {\"data\":1420.5,\"request_id\":2,\"error\":\"success\"}\n\
+                      {\"data\":42.0,\"request_id\":1,\"error\":\"success\"}\n",
+                )
+                .unwrap();
+            requests
+        });
+
+        let position = mpv_request(client).unwrap();
+        assert_eq!(position.fraction, Some(0.42));
+        assert_eq!(position.duration_seconds, Some(1420.5));
+
+        let requests = player.join().unwrap();
+        assert!(requests[0].contains("percent-pos"));
+        assert!(requests[1].contains("duration"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mpv_request_fails_when_connection_closes() {
+        use std::os::unix::net::UnixStream;
+
+        let (client, server) = UnixStream::pair().unwrap();
+        drop(server);
+        assert!(mpv_request(client).is_err());
+    }
+
+    #[test]
+    fn parse_vlc_status_reads_position_and_length() {
+        let response = b"HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n\
+                         {\"state\":\"playing\",\"position\":0.5,\"length\":1440}";
+        let position = parse_vlc_status(response).unwrap();
+        assert_eq!(position.fraction, Some(0.5));
+        assert_eq!(position.duration_seconds, Some(1440.0));
+    }
+
+    #[test]
+    fn parse_vlc_status_without_length() {
+        let response = b"HTTP/1.0 200 OK\r\n\r\n{\"state\":\"playing\",\"position\":0.25}";
+        let position = parse_vlc_status(response).unwrap();
+        assert_eq!(position.fraction, Some(0.25));
+        assert_eq!(position.duration_seconds, None);
+    }
+
+    #[test]
+    fn parse_vlc_status_rejects_errors() {
+        assert!(parse_vlc_status(b"HTTP/1.0 401 Unauthorized\r\n\r\n").is_err());
+        assert!(parse_vlc_status(b"garbage").is_err());
+    }
+}
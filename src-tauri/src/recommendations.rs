@@ -0,0 +1,313 @@
+use crate::db::DB_CONNECTION;
+use crate::metadata_cache;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+const DEFAULT_LIMIT: usize = 20;
+
+// How much each signal contributes to the final score
+const EDGE_WEIGHT: f64 = 0.45;
+const AFFINITY_WEIGHT: f64 = 0.40;
+const POPULARITY_WEIGHT: f64 = 0.15;
+
+// Pseudo-count that pulls affinities of rarely seen tags towards zero
+const AFFINITY_PRIOR: f64 = 2.0;
+// MAL member counts are log-scaled against roughly the most popular show
+const POPULAR_MEMBERS: f64 = 3_000_000.0;
+// Explanations list at most this many entries per signal
+const MAX_REASONS: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecommendationSignal {
+    // "related", "genre", "theme", "demographic", "studio" or "popularity"
+    pub kind: String,
+    pub label: String,
+    // Share of the final score, 0-100
+    pub contribution: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Recommendation {
+    pub anime_id: i64,
+    pub title: String,
+    pub image_url: String,
+    // 0-100
+    pub score: f64,
+    pub signals: Vec<RecommendationSignal>,
+}
+
+struct Seed {
+    anime_id: i64,
+    title: String,
+    // Positive for shows the user liked, negative for ones they did not
+    weight: f64,
+}
+
+struct Candidate {
+    title: String,
+    image_url: String,
+    data: Option<Value>,
+    // (seed title, seed weight * ln(1 + votes))
+    edges: Vec<(String, f64)>,
+}
+
+// Genre/theme/demographic/studio tags of a Jikan object, as (kind, name)
+fn tags(data: &Value) -> Vec<(&'static str, String)> {
+    let mut tags = Vec::new();
+    for (key, kind) in [
+        ("genres", "genre"),
+        ("explicit_genres", "genre"),
+        ("themes", "theme"),
+        ("demographics", "demographic"),
+        ("studios", "studio"),
+    ] {
+        for item in data.get(key).and_then(Value::as_array).into_iter().flatten() {
+            if let Some(name) = item.get("name").and_then(Value::as_str) {
+                tags.push((kind, name.to_string()));
+            }
+        }
+    }
+    tags
+}
+
+// Scores are centred on the user's own mean so a harsh and a generous rater
+// end up with comparable profiles. Unscored entries only count when the
+// status or favorite flag says something.
+fn user_seeds(conn: &Connection) -> Result<(Vec<Seed>, HashSet<i64>), String> {
+    let mut stmt = conn
+        .prepare("SELECT anime_id, title, status, score, favorite FROM user_anime")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows: Vec<(i64, String, String, i64, bool)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+
+    let scored: Vec<f64> = rows.iter().filter(|row| row.3 > 0).map(|row| row.3 as f64).collect();
+    let mean = if scored.is_empty() {
+        7.0
+    } else {
+        scored.iter().sum::<f64>() / scored.len() as f64
+    };
+
+    let known = rows.iter().map(|row| row.0).collect();
+    let seeds = rows
+        .into_iter()
+        .filter_map(|(anime_id, title, status, score, favorite)| {
+            let mut weight = if score > 0 {
+                ((score as f64 - mean) / 2.0).clamp(-2.0, 2.0)
+            } else {
+                match status.as_str() {
+                    "completed" => 0.3,
+                    "dropped" => -1.0,
+                    _ => 0.0,
+                }
+            };
+            if favorite {
+                weight += 0.5;
+            }
+            (weight != 0.0).then_some(Seed {
+                anime_id,
+                title,
+                weight,
+            })
+        })
+        .collect();
+    Ok((seeds, known))
+}
+
+fn title_of(data: &Value) -> String {
+    data.get("title").and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+fn popularity(data: &Value) -> f64 {
+    let score = data
+        .get("score")
+        .and_then(Value::as_f64)
+        .map(|score| ((score - 6.0) / 4.0).clamp(0.0, 1.0))
+        .unwrap_or(0.0);
+    let members = data
+        .get("members")
+        .and_then(Value::as_f64)
+        .map(|members| ((1.0 + members).ln() / (1.0 + POPULAR_MEMBERS).ln()).clamp(0.0, 1.0))
+        .unwrap_or(0.0);
+    0.6 * score + 0.4 * members
+}
+
+// Ranks every cached show that is not excluded against the seeds. Runs purely
+// on the metadata cache and the stored recommendation edges.
+fn recommend(
+    conn: &Connection,
+    seeds: &[Seed],
+    exclude: &HashSet<i64>,
+    limit: usize,
+) -> Result<Vec<Recommendation>, String> {
+    let metadata: HashMap<i64, Value> = metadata_cache::load_all(conn)?.into_iter().collect();
+
+    // Tag affinities from the seeds that have metadata
+    let mut sums: HashMap<(&'static str, String), (f64, f64)> = HashMap::new();
+    for seed in seeds {
+        if let Some(data) = metadata.get(&seed.anime_id) {
+            for tag in tags(data) {
+                let entry = sums.entry(tag).or_insert((0.0, 0.0));
+                entry.0 += seed.weight;
+                entry.1 += 1.0;
+            }
+        }
+    }
+    let affinity: HashMap<(&'static str, String), f64> = sums
+        .into_iter()
+        .map(|(tag, (sum, count))| (tag, sum / (count + AFFINITY_PRIOR)))
+        .collect();
+    let max_affinity = affinity.values().fold(0.0f64, |max, value| max.max(value.abs()));
+
+    let mut candidates: HashMap<i64, Candidate> = HashMap::new();
+    for (anime_id, data) in &metadata {
+        if exclude.contains(anime_id) {
+            continue;
+        }
+        candidates.insert(
+            *anime_id,
+            Candidate {
+                title: title_of(data),
+                image_url: metadata_cache::image_urls(data).into_iter().next().unwrap_or_default(),
+                data: Some(data.clone()),
+                edges: Vec::new(),
+            },
+        );
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT target_id, votes, title, image_url FROM anime_recommendations WHERE source_id = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    for seed in seeds.iter().filter(|seed| seed.weight > 0.0) {
+        let rows: Vec<(i64, i64, String, String)> = stmt
+            .query_map(params![seed.anime_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .map_err(|e| format!("Failed to execute query: {}", e))?
+            .collect::<rusqlite::Result<_>>()
+            .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+
+        for (target_id, votes, title, image_url) in rows {
+            if exclude.contains(&target_id) {
+                continue;
+            }
+            let candidate = candidates.entry(target_id).or_insert_with(|| Candidate {
+                title,
+                image_url,
+                data: None,
+                edges: Vec::new(),
+            });
+            candidate
+                .edges
+                .push((seed.title.clone(), seed.weight * (1.0 + votes.max(0) as f64).ln()));
+        }
+    }
+
+    let max_edge = candidates
+        .values()
+        .map(|candidate| candidate.edges.iter().map(|(_, strength)| strength).sum::<f64>())
+        .fold(0.0f64, f64::max);
+
+    let mut ranked = Vec::new();
+    for (anime_id, candidate) in candidates {
+        let edge_total: f64 = candidate.edges.iter().map(|(_, strength)| strength).sum();
+        let edge = if max_edge > 0.0 { edge_total / max_edge } else { 0.0 };
+
+        let mut matched: Vec<((&'static str, String), f64)> = Vec::new();
+        let mut affinity_score = 0.0;
+        let mut popularity_score = 0.0;
+        if let Some(data) = &candidate.data {
+            let candidate_tags = tags(data);
+            if !candidate_tags.is_empty() && max_affinity > 0.0 {
+                for tag in candidate_tags.iter() {
+                    if let Some(value) = affinity.get(tag) {
+                        matched.push((tag.clone(), *value / max_affinity));
+                    }
+                }
+                affinity_score =
+                    matched.iter().map(|(_, value)| value).sum::<f64>() / candidate_tags.len() as f64;
+            }
+            popularity_score = popularity(data);
+        }
+
+        let score = EDGE_WEIGHT * edge + AFFINITY_WEIGHT * affinity_score + POPULARITY_WEIGHT * popularity_score;
+        // Popularity alone is not a recommendation
+        if score <= 0.0 || (edge == 0.0 && affinity_score <= 0.0) {
+            continue;
+        }
+
+        let mut signals = Vec::new();
+        let mut edges = candidate.edges;
+        edges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (seed_title, strength) in edges.into_iter().take(MAX_REASONS) {
+            if max_edge > 0.0 {
+                signals.push(RecommendationSignal {
+                    kind: "related".to_string(),
+                    label: format!("Recommended by fans of {}", seed_title),
+                    contribution: 100.0 * EDGE_WEIGHT * strength / max_edge,
+                });
+            }
+        }
+        matched.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let tag_count = candidate.data.as_ref().map(|data| tags(data).len()).unwrap_or(1).max(1) as f64;
+        for ((kind, name), value) in matched.into_iter().filter(|(_, value)| *value > 0.0).take(MAX_REASONS) {
+            signals.push(RecommendationSignal {
+                kind: kind.to_string(),
+                label: name,
+                contribution: 100.0 * AFFINITY_WEIGHT * value / tag_count,
+            });
+        }
+        if popularity_score > 0.0 {
+            signals.push(RecommendationSignal {
+                kind: "popularity".to_string(),
+                label: "Well rated and widely watched".to_string(),
+                contribution: 100.0 * POPULARITY_WEIGHT * popularity_score,
+            });
+        }
+
+        ranked.push(Recommendation {
+            anime_id,
+            title: candidate.title,
+            image_url: candidate.image_url,
+            score: 100.0 * score,
+            signals,
+        });
+    }
+
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.title.cmp(&b.title)));
+    ranked.truncate(limit);
+    Ok(ranked)
+}
+
+// Personal recommendations from the user's scores, favorites and drops
+#[tauri::command]
+pub fn get_recommendations(limit: Option<usize>) -> Result<Vec<Recommendation>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let (seeds, known) = user_seeds(&conn)?;
+    if seeds.is_empty() {
+        return Ok(Vec::new());
+    }
+    recommend(&conn, &seeds, &known, limit.unwrap_or(DEFAULT_LIMIT))
+}
+
+// Shows similar to one anime, skipping anything already on the user's list
+#[tauri::command]
+pub fn get_anime_recommendations(anime_id: i64, limit: Option<usize>) -> Result<Vec<Recommendation>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let (_, mut known) = user_seeds(&conn)?;
+    known.insert(anime_id);
+
+    let title = metadata_cache::load(&conn, anime_id)?
+        .map(|data| title_of(&data))
+        .unwrap_or_default();
+    let seed = Seed {
+        anime_id,
+        title,
+        weight: 1.0,
+    };
+    recommend(&conn, &[seed], &known, limit.unwrap_or(DEFAULT_LIMIT))
+}
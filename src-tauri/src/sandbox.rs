@@ -0,0 +1,259 @@
+use crate::backup;
+use crate::db::{self, DB_CONNECTION};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+// Folder the offline storage service creates under Documents/Home
+pub(crate) const OFFLINE_STORAGE_FOLDER: &str = "AnimeManagement";
+
+// Extra folders the user picked in the UI (custom offline storage, export targets)
+pub(crate) const STORAGE_ROOTS_KEY: &str = "storage_roots";
+// Media library folders, managed by the library scanner
+pub(crate) const LIBRARY_FOLDERS_KEY: &str = "library_folders";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SandboxError {
+    #[error("PermissionDenied: {0} is outside the allowed directories")]
+    PermissionDenied(String),
+    #[error("PermissionDenied: {0} must be a relative path without '..' components")]
+    InvalidRelativePath(String),
+    #[error("Failed to resolve path {0}: {1}")]
+    Resolve(String, String),
+    #[error("PermissionDenied: {0} is a system or home directory and cannot be used as a storage folder")]
+    UnsafeRoot(String),
+}
+
+impl From<SandboxError> for String {
+    fn from(err: SandboxError) -> Self {
+        err.to_string()
+    }
+}
+
+// Canonicalize the longest existing prefix of `path` and append the rest. This
+// resolves symlinks for everything that exists while still allowing paths that
+// are about to be created. The non-existent tail may only contain plain names.
+fn resolve(path: &Path) -> Result<PathBuf, SandboxError> {
+    let display = || path.to_string_lossy().to_string();
+
+    if !path.is_absolute() {
+        return Err(SandboxError::PermissionDenied(display()));
+    }
+
+    let mut existing = path.to_path_buf();
+    let mut tail = Vec::new();
+    while !existing.exists() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                tail.push(name.to_os_string());
+                existing = parent.to_path_buf();
+            }
+            _ => return Err(SandboxError::PermissionDenied(display())),
+        }
+    }
+
+    let mut resolved = fs::canonicalize(&existing)
+        .map_err(|e| SandboxError::Resolve(display(), e.to_string()))?;
+    for name in tail.into_iter().rev() {
+        resolved.push(name);
+    }
+
+    // `file_name` skips `..`, so a dangling tail like `missing/../../x` is caught here
+    if path
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+        && !path.exists()
+    {
+        return Err(SandboxError::PermissionDenied(display()));
+    }
+
+    Ok(resolved)
+}
+
+// Directories that would expose the whole system or the user's private files
+// when added as a root. Subfolders of home are fine, home itself is not.
+fn system_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = if cfg!(windows) {
+        ["SystemRoot", "ProgramFiles", "ProgramFiles(x86)", "ProgramData"]
+            .iter()
+            .filter_map(|var| std::env::var_os(var).map(PathBuf::from))
+            .collect()
+    } else {
+        [
+            "/bin", "/boot", "/dev", "/etc", "/lib", "/lib32", "/lib64", "/proc", "/root", "/run", "/sbin",
+            "/sys", "/usr", "/var", "/System", "/Library", "/private", "/Applications",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect()
+    };
+    dirs.retain(|dir| dir.exists());
+    dirs.iter().filter_map(|dir| fs::canonicalize(dir).ok()).collect()
+}
+
+// Resolve a folder the user wants to add as a root, rejecting filesystem roots,
+// the home directory and anything above it, hidden folders directly in home
+// (.ssh, .config, ...) and system directories
+pub(crate) fn validate_root(path: &Path) -> Result<PathBuf, SandboxError> {
+    let resolved = resolve(path)?;
+    let unsafe_root = || SandboxError::UnsafeRoot(path.to_string_lossy().to_string());
+
+    if resolved.parent().is_none() {
+        return Err(unsafe_root());
+    }
+    let home = dirs::home_dir().and_then(|home| fs::canonicalize(home).ok());
+    if let Some(home) = &home {
+        if home.starts_with(&resolved) {
+            return Err(unsafe_root());
+        }
+        let hidden = resolved
+            .strip_prefix(home)
+            .ok()
+            .and_then(|rest| rest.components().next())
+            .is_some_and(|first| first.as_os_str().to_string_lossy().starts_with('.'));
+        if hidden {
+            return Err(unsafe_root());
+        }
+    }
+    // Home can itself live under a system directory (/root, /var/home)
+    if system_dirs()
+        .iter()
+        .filter(|dir| !home.as_ref().is_some_and(|home| home.starts_with(dir)))
+        .any(|dir| resolved.starts_with(dir) || dir.starts_with(&resolved))
+    {
+        return Err(unsafe_root());
+    }
+    Ok(resolved)
+}
+
+// Let the user pick a root with the native folder dialog. Roots are never
+// taken from the webview, so a compromised page cannot widen the sandbox.
+// Returns None when the dialog is cancelled.
+pub(crate) fn pick_root(app_handle: &tauri::AppHandle, title: &str) -> Result<Option<PathBuf>, String> {
+    let picked = app_handle.dialog().file().set_title(title).blocking_pick_folder();
+    match picked {
+        Some(folder) => {
+            let path = folder
+                .into_path()
+                .map_err(|e| format!("Failed to read selected folder: {}", e))?;
+            Ok(Some(validate_root(&path)?))
+        }
+        None => Ok(None),
+    }
+}
+
+fn read_path_list(key: &str) -> Vec<PathBuf> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    db::get_setting::<Vec<String>>(&conn, key)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+pub(crate) fn allowed_roots(app_handle: &tauri::AppHandle) -> Vec<PathBuf> {
+    let resolver = app_handle.path();
+    let mut roots: Vec<PathBuf> = [
+        resolver.app_data_dir(),
+        resolver.app_local_data_dir(),
+        resolver.app_cache_dir(),
+        resolver.app_config_dir(),
+    ]
+    .into_iter()
+    .filter_map(|dir| dir.ok())
+    .collect();
+
+    if let Some(data_dir) = db::get_db_path().parent() {
+        roots.push(data_dir.to_path_buf());
+    }
+
+    if let Ok(dir) = resolver.document_dir() {
+        roots.push(dir.join(OFFLINE_STORAGE_FOLDER));
+    }
+    if let Ok(dir) = resolver.home_dir() {
+        roots.push(dir.join(OFFLINE_STORAGE_FOLDER));
+    }
+
+    // Settings saved before roots were validated are re-checked here
+    if let Ok(settings) = backup::load_settings() {
+        if let Ok(dir) = backup::get_backup_dir(app_handle, &settings) {
+            roots.extend(validate_root(&dir).ok());
+        }
+    }
+
+    roots.extend(
+        read_path_list(STORAGE_ROOTS_KEY)
+            .into_iter()
+            .chain(read_path_list(LIBRARY_FOLDERS_KEY))
+            .filter(|root| validate_root(root).is_ok()),
+    );
+
+    roots
+        .iter()
+        .filter_map(|root| resolve(root).ok())
+        .collect()
+}
+
+// Resolve `path` and make sure it lives under one of the allowed roots
+pub(crate) fn check_path(app_handle: &tauri::AppHandle, path: &Path) -> Result<PathBuf, SandboxError> {
+    let resolved = resolve(path)?;
+
+    if allowed_roots(app_handle)
+        .iter()
+        .any(|root| resolved.starts_with(root))
+    {
+        Ok(resolved)
+    } else {
+        Err(SandboxError::PermissionDenied(path.to_string_lossy().to_string()))
+    }
+}
+
+// File names coming from the webview must stay below the directory they are joined to
+pub(crate) fn check_relative(file_path: &str) -> Result<&Path, SandboxError> {
+    let path = Path::new(file_path);
+    if file_path.is_empty()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(SandboxError::InvalidRelativePath(file_path.to_string()));
+    }
+    Ok(path)
+}
+
+#[tauri::command]
+pub fn list_allowed_roots(app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(allowed_roots(&app_handle)
+        .iter()
+        .map(|root| root.to_string_lossy().to_string())
+        .collect())
+}
+
+// Async so the blocking dialog runs off the main thread
+#[tauri::command]
+pub async fn add_storage_root(app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let picked = pick_root(&app_handle, "Choose a storage folder")?;
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut roots: Vec<String> = db::get_setting(&conn, STORAGE_ROOTS_KEY)?.unwrap_or_default();
+    if let Some(path) = picked {
+        let path = path.to_string_lossy().to_string();
+        if !roots.contains(&path) {
+            roots.push(path);
+            db::set_setting(&conn, STORAGE_ROOTS_KEY, &roots)?;
+        }
+    }
+    Ok(roots)
+}
+
+#[tauri::command]
+pub fn remove_storage_root(path: String) -> Result<Vec<String>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut roots: Vec<String> = db::get_setting(&conn, STORAGE_ROOTS_KEY)?.unwrap_or_default();
+    roots.retain(|root| root != &path);
+    db::set_setting(&conn, STORAGE_ROOTS_KEY, &roots)?;
+    Ok(roots)
+}
@@ -0,0 +1,297 @@
+use crate::airing;
+use crate::api;
+use crate::db::DB_CONNECTION;
+use chrono::{Datelike, Duration, Local, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+const MAX_PAGES: u32 = 20;
+
+// Charts of the current and upcoming seasons still change, past ones rarely do
+const ACTIVE_SEASON_TTL_HOURS: i64 = 12;
+const PAST_SEASON_TTL_DAYS: i64 = 30;
+
+// A carry-over whose last episode airs within this many weeks of the season
+// start is a delayed leftover rather than a continuing show
+const LEFTOVER_WEEKS: i64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Fall,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [Season::Winter, Season::Spring, Season::Summer, Season::Fall];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Season::Winter => "winter",
+            Season::Spring => "spring",
+            Season::Summer => "summer",
+            Season::Fall => "fall",
+        }
+    }
+
+    fn start_month(&self) -> u32 {
+        match self {
+            Season::Winter => 1,
+            Season::Spring => 4,
+            Season::Summer => 7,
+            Season::Fall => 10,
+        }
+    }
+
+    // Seasons are calendar quarters: winter is January to March, and so on
+    pub fn containing(date: NaiveDate) -> Season {
+        Season::ALL[(date.month0() / 3) as usize]
+    }
+
+    // First and last day of the season in `year`
+    pub fn date_range(&self, year: i32) -> Option<(NaiveDate, NaiveDate)> {
+        let start = NaiveDate::from_ymd_opt(year, self.start_month(), 1)?;
+        let next = match self {
+            Season::Fall => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+            _ => NaiveDate::from_ymd_opt(year, self.start_month() + 3, 1)?,
+        };
+        Some((start, next.pred_opt()?))
+    }
+}
+
+impl fmt::Display for Season {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Season {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "winter" => Ok(Season::Winter),
+            "spring" => Ok(Season::Spring),
+            "summer" => Ok(Season::Summer),
+            "fall" | "autumn" => Ok(Season::Fall),
+            _ => Err(format!(
+                "Invalid season {} (expected winter, spring, summer or fall)",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeasonalClassification {
+    // Premieres this season
+    New,
+    // Started in an earlier season and keeps airing through this one
+    Continuing,
+    // Started earlier and only has a few delayed episodes left this season
+    Leftover,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SeasonalEntry {
+    pub classification: SeasonalClassification,
+    // Raw Jikan anime object, same shape the frontend already renders
+    pub anime: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SeasonalChart {
+    pub year: i32,
+    pub season: Season,
+    pub start_date: String,
+    pub end_date: String,
+    pub fetched_at: i64,
+    // True when the network fetch failed and an expired cache was used instead
+    pub stale: bool,
+    pub entries: Vec<SeasonalEntry>,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS seasonal_cache (
+            year INTEGER NOT NULL,
+            season TEXT NOT NULL,
+            data TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL,
+            PRIMARY KEY (year, season)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn date_of(value: Option<&Value>) -> Option<NaiveDate> {
+    let value = value?.as_str()?;
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+// Last broadcast: the provider's end date, else the estimated final episode
+fn expected_end(data: &Value) -> Option<NaiveDate> {
+    let aired = data.get("aired");
+    date_of(aired.and_then(|aired| aired.get("to"))).or_else(|| {
+        airing::total_episodes(data)?;
+        airing::episode_times(data, Utc::now())
+            .last()
+            .map(|(_, time)| time.with_timezone(&airing::jst()).date_naive())
+    })
+}
+
+pub(crate) fn classify(data: &Value, season_start: NaiveDate) -> SeasonalClassification {
+    let from = date_of(data.get("aired").and_then(|aired| aired.get("from")));
+    match from {
+        Some(from) if from < season_start => match expected_end(data) {
+            Some(end) if end < season_start + Duration::weeks(LEFTOVER_WEEKS) => SeasonalClassification::Leftover,
+            _ => SeasonalClassification::Continuing,
+        },
+        // Unknown premiere dates are taken at the provider's word
+        _ => SeasonalClassification::New,
+    }
+}
+
+// Every page of the season, de-duplicated by MAL id since entries can shift
+// between pages while paginating
+async fn fetch_season(year: i32, season: Season) -> Result<Vec<Value>, String> {
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    for page in 1..=MAX_PAGES {
+        if page > 1 {
+            tokio::time::sleep(api::JIKAN_REQUEST_DELAY).await;
+        }
+
+        let body = api::jikan_get(&format!(
+            "/seasons/{}/{}?page={}&continuing=true",
+            year, season, page
+        ))
+        .await?;
+        for entry in body.get("data").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(id) = entry.get("mal_id").and_then(Value::as_i64) {
+                if seen.insert(id) {
+                    entries.push(entry.clone());
+                }
+            }
+        }
+
+        let has_next = body
+            .get("pagination")
+            .and_then(|pagination| pagination.get("has_next_page"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        if !has_next {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+fn load_cached(conn: &Connection, year: i32, season: Season) -> Result<Option<(Vec<Value>, i64)>, String> {
+    let cached = conn
+        .query_row(
+            "SELECT data, fetched_at FROM seasonal_cache WHERE year = ?1 AND season = ?2",
+            params![year, season.as_str()],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read seasonal cache: {}", e))?;
+
+    match cached {
+        Some((data, fetched_at)) => serde_json::from_str(&data)
+            .map(|entries| Some((entries, fetched_at)))
+            .map_err(|e| format!("Failed to parse seasonal cache: {}", e)),
+        None => Ok(None),
+    }
+}
+
+fn store_cached(conn: &Connection, year: i32, season: Season, entries: &[Value], fetched_at: i64) -> Result<(), String> {
+    let data = serde_json::to_string(entries)
+        .map_err(|e| format!("Failed to serialize seasonal cache: {}", e))?;
+    conn.execute(
+        "INSERT INTO seasonal_cache (year, season, data, fetched_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(year, season) DO UPDATE SET data = ?3, fetched_at = ?4",
+        params![year, season.as_str(), data, fetched_at],
+    )
+    .map_err(|e| format!("Failed to store seasonal cache: {}", e))?;
+    Ok(())
+}
+
+fn is_fresh(fetched_at: i64, season_end: NaiveDate) -> bool {
+    let ttl = if season_end < Local::now().date_naive() {
+        Duration::days(PAST_SEASON_TTL_DAYS)
+    } else {
+        Duration::hours(ACTIVE_SEASON_TTL_HOURS)
+    };
+    Utc::now().timestamp() - fetched_at < ttl.num_seconds()
+}
+
+// Full seasonal chart with each entry classified as new, continuing or
+// leftover. Defaults to the current season; cached per season.
+#[tauri::command]
+pub async fn get_seasonal_anime(
+    year: Option<i32>,
+    season: Option<String>,
+    force_refresh: Option<bool>,
+) -> Result<SeasonalChart, String> {
+    let today = Local::now().date_naive();
+    let year = year.unwrap_or_else(|| today.year());
+    let season = match season {
+        Some(season) => season.parse()?,
+        None => Season::containing(today),
+    };
+    let (start, end) = season
+        .date_range(year)
+        .ok_or_else(|| format!("Invalid year {}", year))?;
+
+    let cached = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        load_cached(&conn, year, season)?
+    };
+
+    let (entries, fetched_at, stale) = match cached {
+        Some((entries, fetched_at)) if !force_refresh.unwrap_or(false) && is_fresh(fetched_at, end) => {
+            (entries, fetched_at, false)
+        }
+        cached => match fetch_season(year, season).await {
+            Ok(entries) => {
+                let fetched_at = Utc::now().timestamp();
+                let conn = DB_CONNECTION.lock().unwrap();
+                store_cached(&conn, year, season, &entries, fetched_at)?;
+                (entries, fetched_at, false)
+            }
+            Err(e) => match cached {
+                Some((entries, fetched_at)) => {
+                    eprintln!("{}; using cached chart", e);
+                    (entries, fetched_at, true)
+                }
+                None => return Err(e),
+            },
+        },
+    };
+
+    Ok(SeasonalChart {
+        year,
+        season,
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        fetched_at,
+        stale,
+        entries: entries
+            .into_iter()
+            .map(|anime| SeasonalEntry {
+                classification: classify(&anime, start),
+                anime,
+            })
+            .collect(),
+    })
+}
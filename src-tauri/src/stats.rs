@@ -0,0 +1,309 @@
+use crate::db::DB_CONNECTION;
+use crate::metadata_cache::DEFAULT_EPISODE_MINUTES;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct WatchTimeStats {
+    pub total_entries: i64,
+    pub total_minutes: i64,
+    pub total_episodes: i64,
+    // Episodes whose length came from cached metadata; the rest assume a
+    // standard TV episode
+    pub episodes_with_known_length: i64,
+    pub by_status: Vec<StatusWatchTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusWatchTime {
+    pub status: String,
+    // List entries with this status, watched or not
+    pub entries: i64,
+    pub minutes: i64,
+    pub episodes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoreBucket {
+    pub score: i64,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScoreDistribution {
+    // Always 10 buckets, scores 1 to 10
+    pub buckets: Vec<ScoreBucket>,
+    pub scored: i64,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    // Population standard deviation
+    pub std_dev: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BreakdownRow {
+    pub name: String,
+    pub count: i64,
+    pub completed: i64,
+    // Mean of the user's non-zero scores
+    pub mean_score: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Breakdown {
+    // "genre", "theme", "studio" or "year"
+    pub kind: String,
+    pub rows: Vec<BreakdownRow>,
+    // List entries without cached metadata, which cannot be attributed
+    pub entries_without_metadata: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionBucket {
+    pub label: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionStats {
+    pub buckets: Vec<CompletionBucket>,
+    // Completed entries with usable start and end dates
+    pub measured: i64,
+    pub mean_days: Option<f64>,
+    pub median_days: Option<f64>,
+}
+
+// Fractional days between start and end of completed entries. Dates come
+// from the frontend as either YYYY-MM-DD or full ISO timestamps, both of which
+// julianday() understands; anything unparseable or negative is skipped.
+const COMPLETION_DAYS_SQL: &str = "SELECT julianday(end_date) - julianday(start_date) AS days
+     FROM user_anime
+     WHERE status = 'completed' AND start_date IS NOT NULL AND end_date IS NOT NULL
+       AND julianday(end_date) IS NOT NULL AND julianday(start_date) IS NOT NULL
+       AND julianday(end_date) >= julianday(start_date)";
+
+// Median of a single-column numeric query, via ORDER BY and OFFSET
+fn median(conn: &Connection, sql: &str, count: i64) -> Result<Option<f64>, String> {
+    if count == 0 {
+        return Ok(None);
+    }
+    let query = format!(
+        "SELECT AVG(value) FROM (SELECT value FROM ({}) ORDER BY value LIMIT ?1 OFFSET ?2)",
+        sql
+    );
+    conn.query_row(&query, params![2 - count % 2, (count - 1) / 2], |row| row.get(0))
+        .map_err(|e| format!("Failed to compute median: {}", e))
+}
+
+#[tauri::command]
+pub fn get_watch_time_stats() -> Result<WatchTimeStats, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT u.status,
+                    COUNT(*),
+                    SUM(u.progress * COALESCE(m.episode_minutes, ?1)),
+                    SUM(u.progress),
+                    SUM(CASE WHEN m.episode_minutes IS NOT NULL THEN u.progress ELSE 0 END)
+             FROM user_anime u LEFT JOIN anime_metadata m ON m.anime_id = u.anime_id
+             GROUP BY u.status
+             ORDER BY u.status",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![DEFAULT_EPISODE_MINUTES], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut stats = WatchTimeStats {
+        total_entries: 0,
+        total_minutes: 0,
+        total_episodes: 0,
+        episodes_with_known_length: 0,
+        by_status: Vec::new(),
+    };
+    for row in rows {
+        let (status, entries, minutes, episodes, known) =
+            row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        stats.total_entries += entries;
+        stats.total_minutes += minutes;
+        stats.total_episodes += episodes;
+        stats.episodes_with_known_length += known;
+        stats.by_status.push(StatusWatchTime {
+            status,
+            entries,
+            minutes,
+            episodes,
+        });
+    }
+    Ok(stats)
+}
+
+#[tauri::command]
+pub fn get_score_distribution() -> Result<ScoreDistribution, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "WITH RECURSIVE scores(score) AS (SELECT 1 UNION ALL SELECT score + 1 FROM scores WHERE score < 10)
+             SELECT s.score, COUNT(u.anime_id)
+             FROM scores s LEFT JOIN user_anime u ON u.score = s.score
+             GROUP BY s.score ORDER BY s.score",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let buckets = stmt
+        .query_map([], |row| {
+            Ok(ScoreBucket {
+                score: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+
+    let (scored, mean, variance): (i64, Option<f64>, Option<f64>) = conn
+        .query_row(
+            "SELECT COUNT(*), AVG(score), AVG(score * score) - AVG(score) * AVG(score)
+             FROM user_anime WHERE score > 0",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| format!("Failed to compute score statistics: {}", e))?;
+
+    Ok(ScoreDistribution {
+        buckets,
+        scored,
+        mean,
+        median: median(&conn, "SELECT score AS value FROM user_anime WHERE score > 0", scored)?,
+        // Rounding can push a zero variance slightly negative
+        std_dev: variance.map(|variance| variance.max(0.0).sqrt()),
+    })
+}
+
+// Counts and mean scores per genre, theme, studio or premiere year, read from
+// the cached Jikan objects with SQLite's JSON functions
+#[tauri::command]
+pub fn get_stats_breakdown(kind: String) -> Result<Breakdown, String> {
+    let source = match kind.as_str() {
+        "genre" => "json_each(m.data, '$.genres') j",
+        "theme" => "json_each(m.data, '$.themes') j",
+        "studio" => "json_each(m.data, '$.studios') j",
+        "year" => "",
+        _ => {
+            return Err(format!(
+                "Invalid breakdown {} (expected genre, theme, studio or year)",
+                kind
+            ))
+        }
+    };
+    let sql = if kind == "year" {
+        "SELECT CAST(COALESCE(json_extract(m.data, '$.year'),
+                              substr(json_extract(m.data, '$.aired.from'), 1, 4)) AS TEXT) AS name,
+                COUNT(*), SUM(u.status = 'completed'), AVG(NULLIF(u.score, 0))
+         FROM user_anime u JOIN anime_metadata m ON m.anime_id = u.anime_id
+         WHERE name IS NOT NULL
+         GROUP BY name ORDER BY name DESC"
+            .to_string()
+    } else {
+        format!(
+            "SELECT json_extract(j.value, '$.name') AS name,
+                    COUNT(*), SUM(u.status = 'completed'), AVG(NULLIF(u.score, 0))
+             FROM user_anime u JOIN anime_metadata m ON m.anime_id = u.anime_id, {}
+             WHERE name IS NOT NULL
+             GROUP BY name ORDER BY COUNT(*) DESC, name",
+            source
+        )
+    };
+
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(BreakdownRow {
+                name: row.get(0)?,
+                count: row.get(1)?,
+                completed: row.get(2)?,
+                mean_score: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+
+    let entries_without_metadata = conn
+        .query_row(
+            "SELECT COUNT(*) FROM user_anime
+             WHERE anime_id NOT IN (SELECT anime_id FROM anime_metadata)",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count entries without metadata: {}", e))?;
+
+    Ok(Breakdown {
+        kind,
+        rows,
+        entries_without_metadata,
+    })
+}
+
+// How long completed shows took from start to finish
+#[tauri::command]
+pub fn get_completion_stats() -> Result<CompletionStats, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let query = format!(
+        "WITH durations AS ({}),
+         buckets(position, label, low, high) AS (VALUES
+            (1, 'Same day', 0, 1),
+            (2, '1-7 days', 1, 8),
+            (3, '1-4 weeks', 8, 31),
+            (4, '1-3 months', 31, 91),
+            (5, '3-12 months', 91, 366),
+            (6, 'Over a year', 366, NULL))
+         SELECT b.label, COUNT(d.days)
+         FROM buckets b LEFT JOIN durations d
+           ON d.days >= b.low AND (b.high IS NULL OR d.days < b.high)
+         GROUP BY b.position ORDER BY b.position",
+        COMPLETION_DAYS_SQL
+    );
+    let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let buckets = stmt
+        .query_map([], |row| {
+            Ok(CompletionBucket {
+                label: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))?;
+
+    let (measured, mean_days): (i64, Option<f64>) = conn
+        .query_row(
+            &format!("SELECT COUNT(*), AVG(days) FROM ({})", COMPLETION_DAYS_SQL),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to compute completion statistics: {}", e))?;
+
+    Ok(CompletionStats {
+        buckets,
+        measured,
+        mean_days,
+        median_days: median(
+            &conn,
+            &format!("SELECT days AS value FROM ({})", COMPLETION_DAYS_SQL),
+            measured,
+        )?,
+    })
+}
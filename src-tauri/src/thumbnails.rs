@@ -0,0 +1,201 @@
+use crate::db::DB_CONNECTION;
+use crate::filesystem::atomic_write;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+// Resized/transcoded copies of cached covers live under image_cache/variants/<size class>/
+pub(crate) const VARIANTS_FOLDER: &str = "variants";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Original,
+    Webp,
+    Avif,
+}
+
+// Size classes the grid and detail views request. `Full` keeps the source
+// dimensions and only exists as a variant when transcoding is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeClass {
+    Small,
+    Medium,
+    Full,
+}
+
+impl SizeClass {
+    pub const ALL: [SizeClass; 3] = [SizeClass::Small, SizeClass::Medium, SizeClass::Full];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SizeClass::Small => "96",
+            SizeClass::Medium => "225",
+            SizeClass::Full => "full",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<SizeClass> {
+        SizeClass::ALL.iter().copied().find(|class| class.as_str() == value)
+    }
+
+    // The original object already serves the full size unless we transcode
+    fn wanted(&self, format: ThumbnailFormat) -> bool {
+        !(*self == SizeClass::Full && format == ThumbnailFormat::Original)
+    }
+
+    fn max_width(&self) -> Option<u32> {
+        match self {
+            SizeClass::Small => Some(96),
+            SizeClass::Medium => Some(225),
+            SizeClass::Full => None,
+        }
+    }
+}
+
+pub(crate) struct Variant {
+    pub path: PathBuf,
+    pub content_type: String,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_variants (
+            hash TEXT NOT NULL,
+            size_class TEXT NOT NULL,
+            path TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            PRIMARY KEY (hash, size_class)
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn target_format(source: ImageFormat, format: ThumbnailFormat) -> ImageFormat {
+    match format {
+        ThumbnailFormat::Original => source,
+        ThumbnailFormat::Webp => ImageFormat::WebP,
+        ThumbnailFormat::Avif => ImageFormat::Avif,
+    }
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+    let mut out = Cursor::new(Vec::new());
+
+    // JPEG has no alpha channel; the other encoders all accept RGBA
+    let result = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut out, format),
+        _ => DynamicImage::ImageRgba8(image.to_rgba8()).write_to(&mut out, format),
+    };
+    result.map_err(|e| format!("Failed to encode {:?} image: {}", format, e))?;
+
+    Ok(out.into_inner())
+}
+
+fn variant_path(cache_dir: &Path, class: SizeClass, hash: &str, format: ImageFormat) -> PathBuf {
+    let extension = format.extensions_str().first().copied().unwrap_or("img");
+    cache_dir
+        .join(VARIANTS_FOLDER)
+        .join(class.as_str())
+        .join(&hash[..2])
+        .join(format!("{}.{}", hash, extension))
+}
+
+// Build every size class for a cached image. Returns the written paths
+// with their sizes so the caller can index them for LRU eviction.
+pub(crate) fn generate_variants(
+    cache_dir: &Path,
+    hash: &str,
+    bytes: &[u8],
+    format: ThumbnailFormat,
+) -> Result<Vec<(PathBuf, u64)>, String> {
+    let source_format =
+        image::guess_format(bytes).map_err(|e| format!("Unrecognized image format: {}", e))?;
+    let image = image::load_from_memory_with_format(bytes, source_format)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+
+    let mut written = Vec::new();
+    for class in SizeClass::ALL.into_iter().filter(|class| class.wanted(format)) {
+
+        let resized = match class.max_width() {
+            // Never upscale covers that are already small
+            Some(width) if image.width() > width => {
+                image.resize(width, u32::MAX, FilterType::Lanczos3)
+            }
+            _ => image.clone(),
+        };
+
+        // Fall back to the source format when the requested encoder is unavailable
+        let mut output_format = target_format(source_format, format);
+        let encoded = match encode(&resized, output_format) {
+            Ok(encoded) => encoded,
+            Err(e) if output_format != source_format => {
+                eprintln!("{}, keeping {:?}", e, source_format);
+                output_format = source_format;
+                encode(&resized, output_format)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        let path = variant_path(cache_dir, class, hash, output_format);
+        atomic_write(&path, &encoded, false)?;
+
+        let conn = DB_CONNECTION.lock().unwrap();
+        conn.execute(
+            "INSERT INTO image_variants (hash, size_class, path, content_type, width, height, size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(hash, size_class) DO UPDATE SET
+             path = ?3, content_type = ?4, width = ?5, height = ?6, size = ?7",
+            params![
+                hash,
+                class.as_str(),
+                path.strip_prefix(cache_dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string(),
+                output_format.to_mime_type(),
+                resized.width(),
+                resized.height(),
+                encoded.len() as i64,
+            ],
+        )
+        .map_err(|e| format!("Failed to index image variant: {}", e))?;
+
+        written.push((path, encoded.len() as u64));
+    }
+
+    Ok(written)
+}
+
+pub(crate) fn find_variant(cache_dir: &Path, hash: &str, class: SizeClass) -> Option<Variant> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    conn.query_row(
+        "SELECT path, content_type FROM image_variants WHERE hash = ?1 AND size_class = ?2",
+        params![hash, class.as_str()],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    )
+    .ok()
+    .map(|(path, content_type)| Variant {
+        path: cache_dir.join(path),
+        content_type,
+    })
+    .filter(|variant| variant.path.exists())
+}
+
+// False when thumbnails were enabled after the image was cached or a variant
+// was evicted, so generate_variants has to run again
+pub(crate) fn has_all_variants(cache_dir: &Path, hash: &str, format: ThumbnailFormat) -> bool {
+    SizeClass::ALL
+        .into_iter()
+        .filter(|class| class.wanted(format))
+        .all(|class| find_variant(cache_dir, hash, class).is_some())
+}
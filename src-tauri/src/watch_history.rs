@@ -0,0 +1,223 @@
+use crate::db::DB_CONNECTION;
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchEvent {
+    pub id: i64,
+    pub anime_id: i64,
+    pub episode: i32,
+    // RFC 3339, UTC
+    pub watched_at: String,
+    // "player" for tracked playback, "manual" for progress edits
+    pub source: String,
+    pub file_path: Option<String>,
+    pub duration_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Streak {
+    pub days: i64,
+    pub start: String,
+    pub end: String,
+}
+
+pub(crate) fn init_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS watch_events (
+            id INTEGER PRIMARY KEY,
+            anime_id INTEGER NOT NULL,
+            episode INTEGER NOT NULL,
+            watched_at TEXT NOT NULL,
+            source TEXT NOT NULL,
+            file_path TEXT,
+            duration_seconds REAL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_watch_events_anime ON watch_events(anime_id, episode)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_watch_events_time ON watch_events(watched_at)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn record_watch_event(
+    conn: &Connection,
+    anime_id: i64,
+    episode: i32,
+    source: &str,
+    file_path: Option<&str>,
+    duration_seconds: Option<f64>,
+) -> Result<WatchEvent, String> {
+    let watched_at = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO watch_events (anime_id, episode, watched_at, source, file_path, duration_seconds)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![anime_id, episode, watched_at, source, file_path, duration_seconds],
+    )
+    .map_err(|e| format!("Failed to record watch event: {}", e))?;
+
+    Ok(WatchEvent {
+        id: conn.last_insert_rowid(),
+        anime_id,
+        episode,
+        watched_at,
+        source: source.to_string(),
+        file_path: file_path.map(str::to_string),
+        duration_seconds,
+    })
+}
+
+// Local calendar days with at least one watched episode
+pub(crate) fn watch_days(conn: &Connection) -> Result<BTreeSet<NaiveDate>, String> {
+    let mut stmt = conn
+        .prepare("SELECT watched_at FROM watch_events")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut days = BTreeSet::new();
+    for row in rows {
+        let watched_at = row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        if let Ok(time) = DateTime::parse_from_rfc3339(&watched_at) {
+            days.insert(time.with_timezone(&Local).date_naive());
+        }
+    }
+    Ok(days)
+}
+
+fn streak(start: NaiveDate, end: NaiveDate) -> Streak {
+    Streak {
+        days: (end - start).num_days() + 1,
+        start: start.format("%Y-%m-%d").to_string(),
+        end: end.format("%Y-%m-%d").to_string(),
+    }
+}
+
+// Runs of consecutive days, oldest first
+fn runs(days: &BTreeSet<NaiveDate>) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut runs: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+    for &day in days {
+        match runs.last_mut() {
+            Some((_, end)) if end.succ_opt() == Some(day) => *end = day,
+            _ => runs.push((day, day)),
+        }
+    }
+    runs
+}
+
+// Longest run of consecutive days; the earliest wins a tie
+pub(crate) fn longest_streak(days: &BTreeSet<NaiveDate>) -> Option<Streak> {
+    runs(days)
+        .into_iter()
+        .fold(None::<(NaiveDate, NaiveDate)>, |best, run| match best {
+            Some(best) if best.1 - best.0 >= run.1 - run.0 => Some(best),
+            _ => Some(run),
+        })
+        .map(|(start, end)| streak(start, end))
+}
+
+// The run ending today, or yesterday since today's episode may still be ahead
+pub(crate) fn current_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Option<Streak> {
+    let (start, end) = runs(days).pop()?;
+    (end == today || end.succ_opt() == Some(today)).then(|| streak(start, end))
+}
+
+#[tauri::command]
+pub fn get_watch_history(anime_id: Option<i64>, limit: Option<u32>) -> Result<Vec<WatchEvent>, String> {
+    let conn = DB_CONNECTION.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, anime_id, episode, watched_at, source, file_path, duration_seconds
+             FROM watch_events WHERE ?1 IS NULL OR anime_id = ?1
+             ORDER BY watched_at DESC LIMIT ?2",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![anime_id, limit.map(i64::from).unwrap_or(-1)], |row| {
+            Ok(WatchEvent {
+                id: row.get(0)?,
+                anime_id: row.get(1)?,
+                episode: row.get(2)?,
+                watched_at: row.get(3)?,
+                source: row.get(4)?,
+                file_path: row.get(5)?,
+                duration_seconds: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn days(days: &[u32]) -> BTreeSet<NaiveDate> {
+        days.iter().map(|&day| date(day)).collect()
+    }
+
+    fn span(streak: Option<Streak>) -> Option<(i64, String, String)> {
+        streak.map(|streak| (streak.days, streak.start, streak.end))
+    }
+
+    #[test]
+    fn empty_history_has_no_streaks() {
+        assert!(longest_streak(&BTreeSet::new()).is_none());
+        assert!(current_streak(&BTreeSet::new(), date(10)).is_none());
+    }
+
+    #[test]
+    fn longest_streak_spans_gaps_and_prefers_the_earliest_tie() {
+        let history = days(&[1, 2, 3, 5, 8, 9, 10]);
+        assert_eq!(
+            span(longest_streak(&history)),
+            Some((3, "2024-03-01".to_string(), "2024-03-03".to_string()))
+        );
+
+        let history = days(&[1, 3, 4, 5, 6, 9]);
+        assert_eq!(
+            span(longest_streak(&history)),
+            Some((4, "2024-03-03".to_string(), "2024-03-06".to_string()))
+        );
+    }
+
+    #[test]
+    fn current_streak_ends_today_or_yesterday() {
+        let history = days(&[4, 6, 7, 8]);
+        let expected = Some((3, "2024-03-06".to_string(), "2024-03-08".to_string()));
+        assert_eq!(span(current_streak(&history, date(8))), expected);
+        assert_eq!(span(current_streak(&history, date(9))), expected);
+        assert!(current_streak(&history, date(10)).is_none());
+    }
+
+    #[test]
+    fn current_streak_crosses_month_boundaries() {
+        let history: BTreeSet<NaiveDate> = [
+            NaiveDate::from_ymd_opt(2024, 2, 28).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+            date(1),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            span(current_streak(&history, date(1))),
+            Some((3, "2024-02-28".to_string(), "2024-03-01".to_string()))
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use crate::db::{self, DB_CONNECTION};
+use crate::filesystem;
+use crate::metadata_cache::DEFAULT_EPISODE_MINUTES;
+use crate::sandbox;
+use crate::watch_history::{self, Streak};
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+const TOP_GENRES: usize = 5;
+const TOP_RATED: usize = 5;
+
+#[derive(Debug, Serialize)]
+pub struct ReviewEntry {
+    pub anime_id: i64,
+    pub title: String,
+    pub score: i64,
+    pub end_date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GenreCount {
+    pub name: String,
+    // Shows watched or completed during the year carrying this genre
+    pub shows: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthCount {
+    // YYYY-MM
+    pub month: String,
+    pub episodes: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivitySource {
+    // Episodes recorded by the player or by progress edits
+    WatchEvents,
+    // No events in the year: each entry's progress counted in the month of its end date
+    ListProgress,
+}
+
+#[derive(Debug, Serialize)]
+pub struct YearReview {
+    pub year: i32,
+    pub generated_at: String,
+    // Where episodes, hours, months and the streak come from
+    pub activity_source: ActivitySource,
+    pub completed: Vec<ReviewEntry>,
+    pub episodes_watched: i64,
+    // Tracked playback time where the player reported it, episode lengths otherwise
+    pub hours_watched: f64,
+    pub top_genres: Vec<GenreCount>,
+    pub highest_rated: Vec<ReviewEntry>,
+    pub longest_streak: Option<Streak>,
+    pub busiest_month: Option<MonthCount>,
+    // All twelve months, including empty ones
+    pub months: Vec<MonthCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct YearReviewExport {
+    pub html_path: String,
+    pub json_path: String,
+    pub report: YearReview,
+}
+
+// Completed entries whose end date falls in the year, best scores first
+fn completed_in(conn: &Connection, year: i32) -> Result<Vec<ReviewEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT anime_id, title, score, end_date FROM user_anime
+             WHERE status = 'completed' AND substr(end_date, 1, 4) = ?1
+             ORDER BY score DESC, end_date, title",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![year.to_string()], |row| {
+            Ok(ReviewEntry {
+                anime_id: row.get(0)?,
+                title: row.get(1)?,
+                score: row.get(2)?,
+                end_date: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to retrieve row: {}", e))
+}
+
+// Watch events of the year in local time, as (anime_id, date, minutes)
+fn events_in(conn: &Connection, year: i32) -> Result<Vec<(i64, NaiveDate, f64)>, String> {
+    // watched_at is UTC, so read a day either side and filter after converting
+    let mut stmt = conn
+        .prepare(
+            "SELECT w.anime_id, w.watched_at, w.duration_seconds, m.episode_minutes
+             FROM watch_events w LEFT JOIN anime_metadata m ON m.anime_id = w.anime_id
+             WHERE w.watched_at >= ?1 AND w.watched_at < ?2",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(
+            params![format!("{}-12-31", year - 1), format!("{}-01-02", year + 1)],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut events = Vec::new();
+    for row in rows {
+        let (anime_id, watched_at, duration_seconds, episode_minutes) =
+            row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        let date = match DateTime::parse_from_rfc3339(&watched_at) {
+            Ok(time) => time.with_timezone(&Local).date_naive(),
+            Err(_) => continue,
+        };
+        if date.year() != year {
+            continue;
+        }
+        let minutes = match duration_seconds {
+            Some(seconds) if seconds > 0.0 => seconds / 60.0,
+            _ => episode_minutes.unwrap_or(DEFAULT_EPISODE_MINUTES) as f64,
+        };
+        events.push((anime_id, date, minutes));
+    }
+    Ok(events)
+}
+
+// Estimate for years watched before watch events were recorded: the progress
+// of entries finished in the year, as (anime_id, end date, minutes) per episode
+fn progress_in(conn: &Connection, year: i32) -> Result<Vec<(i64, NaiveDate, f64)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT u.anime_id, substr(u.end_date, 1, 10), u.progress, m.episode_minutes
+             FROM user_anime u LEFT JOIN anime_metadata m ON m.anime_id = u.anime_id
+             WHERE substr(u.end_date, 1, 4) = ?1 AND u.progress > 0",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params![year.to_string()], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    let mut episodes = Vec::new();
+    for row in rows {
+        let (anime_id, end_date, progress, episode_minutes) =
+            row.map_err(|e| format!("Failed to retrieve row: {}", e))?;
+        let Ok(date) = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d") else {
+            continue;
+        };
+        let minutes = episode_minutes.unwrap_or(DEFAULT_EPISODE_MINUTES) as f64;
+        episodes.extend((0..progress).map(|_| (anime_id, date, minutes)));
+    }
+    Ok(episodes)
+}
+
+fn genres_of(conn: &Connection, anime_ids: &BTreeSet<i64>) -> Result<Vec<GenreCount>, String> {
+    let mut stmt = conn
+        .prepare("SELECT data FROM anime_metadata WHERE anime_id = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for anime_id in anime_ids {
+        let data: Option<String> = stmt
+            .query_row(params![anime_id], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read cached metadata: {}", e))?;
+        let Some(data) = data.and_then(|data| serde_json::from_str::<Value>(&data).ok()) else {
+            continue;
+        };
+        for genre in data.get("genres").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(name) = genre.get("name").and_then(Value::as_str) {
+                *counts.entry(name.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut genres: Vec<GenreCount> = counts.into_iter().map(|(name, shows)| GenreCount { name, shows }).collect();
+    genres.sort_by(|a, b| b.shows.cmp(&a.shows).then_with(|| a.name.cmp(&b.name)));
+    genres.truncate(TOP_GENRES);
+    Ok(genres)
+}
+
+pub(crate) fn build_review(conn: &Connection, year: i32) -> Result<YearReview, String> {
+    let completed = completed_in(conn, year)?;
+    let mut events = events_in(conn, year)?;
+    let activity_source = if events.is_empty() {
+        events = progress_in(conn, year)?;
+        ActivitySource::ListProgress
+    } else {
+        ActivitySource::WatchEvents
+    };
+
+    let mut shows: BTreeSet<i64> = completed.iter().map(|entry| entry.anime_id).collect();
+    let mut days = BTreeSet::new();
+    let mut per_month: BTreeMap<u32, i64> = (1..=12).map(|month| (month, 0)).collect();
+    let mut minutes = 0.0;
+    for (anime_id, date, event_minutes) in &events {
+        shows.insert(*anime_id);
+        days.insert(*date);
+        *per_month.entry(date.month()).or_insert(0) += 1;
+        minutes += event_minutes;
+    }
+
+    let months: Vec<MonthCount> = per_month
+        .into_iter()
+        .map(|(month, episodes)| MonthCount {
+            month: format!("{}-{:02}", year, month),
+            episodes,
+        })
+        .collect();
+    // Earliest month wins a tie
+    let busiest_month = months
+        .iter()
+        .filter(|month| month.episodes > 0)
+        .fold(None::<&MonthCount>, |best, month| match best {
+            Some(best) if best.episodes >= month.episodes => Some(best),
+            _ => Some(month),
+        })
+        .map(|month| MonthCount {
+            month: month.month.clone(),
+            episodes: month.episodes,
+        });
+
+    let highest_rated = completed
+        .iter()
+        .filter(|entry| entry.score > 0)
+        .take(TOP_RATED)
+        .map(|entry| ReviewEntry {
+            anime_id: entry.anime_id,
+            title: entry.title.clone(),
+            score: entry.score,
+            end_date: entry.end_date.clone(),
+        })
+        .collect();
+
+    // End dates say nothing about which days were spent watching
+    let longest_streak = match activity_source {
+        ActivitySource::WatchEvents => watch_history::longest_streak(&days),
+        ActivitySource::ListProgress => None,
+    };
+
+    Ok(YearReview {
+        year,
+        generated_at: Local::now().to_rfc3339(),
+        activity_source,
+        top_genres: genres_of(conn, &shows)?,
+        episodes_watched: events.len() as i64,
+        hours_watched: (minutes / 6.0).round() / 10.0,
+        highest_rated,
+        longest_streak,
+        busiest_month,
+        months,
+        completed,
+    })
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn month_name(month: &str) -> String {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map(|date| date.format("%B").to_string())
+        .unwrap_or_else(|_| month.to_string())
+}
+
+fn entry_list(entries: &[ReviewEntry]) -> String {
+    if entries.is_empty() {
+        return "<p class=\"empty\">Nothing yet</p>".to_string();
+    }
+    let items: String = entries
+        .iter()
+        .map(|entry| {
+            let score = if entry.score > 0 {
+                format!(" <span class=\"score\">{}/10</span>", entry.score)
+            } else {
+                String::new()
+            };
+            format!(
+                "<li><a href=\"https://myanimelist.net/anime/{}\">{}</a>{}</li>",
+                entry.anime_id,
+                escape_html(&entry.title),
+                score
+            )
+        })
+        .collect();
+    format!("<ol>{}</ol>", items)
+}
+
+// Self-contained page with inline styles, so it can be shared as a single file
+pub(crate) fn render_html(report: &YearReview) -> String {
+    let max_month = report.months.iter().map(|month| month.episodes).max().unwrap_or(0).max(1);
+    let bars: String = report
+        .months
+        .iter()
+        .map(|month| {
+            format!(
+                "<div class=\"bar\"><span style=\"height:{}%\" title=\"{} episodes\"></span><label>{}</label></div>",
+                month.episodes * 100 / max_month,
+                month.episodes,
+                &month_name(&month.month)[..3]
+            )
+        })
+        .collect();
+    let genres = if report.top_genres.is_empty() {
+        "<p class=\"empty\">Nothing yet</p>".to_string()
+    } else {
+        let items: String = report
+            .top_genres
+            .iter()
+            .map(|genre| format!("<li>{} <span class=\"score\">{} shows</span></li>", escape_html(&genre.name), genre.shows))
+            .collect();
+        format!("<ol>{}</ol>", items)
+    };
+    let streak = match &report.longest_streak {
+        Some(streak) => format!(
+            "<div class=\"stat\"><b>{}</b>day streak<small>{} to {}</small></div>",
+            streak.days, streak.start, streak.end
+        ),
+        None => "<div class=\"stat\"><b>0</b>day streak</div>".to_string(),
+    };
+    let source = match report.activity_source {
+        ActivitySource::WatchEvents => "Episode counts come from your watch history.",
+        ActivitySource::ListProgress => {
+            "No watch history was recorded this year, so episode counts are estimated \
+             from the progress of shows finished in each month."
+        }
+    };
+    let busiest = match &report.busiest_month {
+        Some(month) => format!(
+            "<div class=\"stat\"><b>{}</b>busiest month<small>{} episodes</small></div>",
+            month_name(&month.month),
+            month.episodes
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{year} in anime</title>
+<style>
+body {{ font-family: system-ui, sans-serif; background: #111827; color: #f3f4f6; margin: 0; padding: 2rem; }}
+main {{ max-width: 52rem; margin: 0 auto; }}
+h1 {{ font-size: 2.5rem; margin-bottom: 0.25rem; }}
+h2 {{ margin-top: 2.5rem; border-bottom: 1px solid #374151; padding-bottom: 0.5rem; }}
+a {{ color: #93c5fd; text-decoration: none; }}
+.stats {{ display: grid; grid-template-columns: repeat(auto-fit, minmax(10rem, 1fr)); gap: 1rem; margin-top: 2rem; }}
+.stat {{ background: #1f2937; border-radius: 0.75rem; padding: 1rem; }}
+.stat b {{ display: block; font-size: 2rem; color: #a78bfa; }}
+.stat small {{ display: block; color: #9ca3af; margin-top: 0.25rem; }}
+.chart {{ display: flex; align-items: flex-end; gap: 0.5rem; height: 10rem; }}
+.bar {{ flex: 1; display: flex; flex-direction: column; justify-content: flex-end; height: 100%; text-align: center; }}
+.bar span {{ display: block; background: #8b5cf6; border-radius: 0.25rem 0.25rem 0 0; min-height: 2px; }}
+.bar label {{ font-size: 0.75rem; color: #9ca3af; margin-top: 0.25rem; }}
+.score {{ color: #9ca3af; }}
+.empty {{ color: #6b7280; }}
+footer {{ margin-top: 3rem; color: #6b7280; font-size: 0.8rem; }}
+</style>
+</head>
+<body>
+<main>
+<h1>{year} in anime</h1>
+<div class=\"stats\">
+<div class=\"stat\"><b>{completed}</b>shows completed</div>
+<div class=\"stat\"><b>{episodes}</b>episodes watched</div>
+<div class=\"stat\"><b>{hours}</b>hours watched</div>
+{streak}
+{busiest}
+</div>
+<h2>Episodes per month</h2>
+<div class=\"chart\">{bars}</div>
+<h2>Highest rated</h2>
+{highest}
+<h2>Top genres</h2>
+{genres}
+<h2>Completed this year</h2>
+{completed_list}
+<footer>{source}<br>Generated {generated}</footer>
+</main>
+</body>
+</html>
+",
+        year = report.year,
+        completed = report.completed.len(),
+        episodes = report.episodes_watched,
+        hours = report.hours_watched,
+        streak = streak,
+        busiest = busiest,
+        bars = bars,
+        highest = entry_list(&report.highest_rated),
+        genres = genres,
+        completed_list = entry_list(&report.completed),
+        source = source,
+        generated = escape_html(&report.generated_at),
+    )
+}
+
+// Writes the report as HTML and JSON. Files are named per year so
+// regenerating a year replaces the previous recap.
+#[tauri::command]
+pub fn generate_year_review(
+    app_handle: tauri::AppHandle,
+    year: Option<i32>,
+    export_dir: Option<String>,
+) -> Result<YearReviewExport, String> {
+    let year = year.unwrap_or_else(|| Local::now().year());
+    let report = {
+        let conn = DB_CONNECTION.lock().unwrap();
+        build_review(&conn, year)?
+    };
+
+    let dir = match export_dir {
+        Some(dir) => sandbox::check_path(&app_handle, Path::new(&dir))?,
+        None => db::default_export_dir(),
+    };
+    let html_path = dir.join(format!("{}year_review_{}.html", db::EXPORT_FILE_PREFIX, year));
+    let json_path = dir.join(format!("{}year_review_{}.json", db::EXPORT_FILE_PREFIX, year));
+
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Failed to serialize year review: {}", e))?;
+    filesystem::atomic_write(&json_path, json.as_bytes(), false)?;
+    filesystem::atomic_write(&html_path, render_html(&report).as_bytes(), false)?;
+
+    Ok(YearReviewExport {
+        html_path: html_path.to_string_lossy().to_string(),
+        json_path: json_path.to_string_lossy().to_string(),
+        report,
+    })
+}
@@ -1,5 +1,6 @@
-import React, { useState, useMemo } from "react";
+import React, { useState, useMemo, useEffect } from "react";
 import { Clock } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
 import { AnimeData } from "../types/anime";
 import { AppTheme } from "../themes/themeTypes";
 import AccordionView from "./AnimeScheduleViews/AccordionView";
@@ -73,23 +74,6 @@ export const formatTime = (
 	}
 };
 
-// Map API day to standard day name
-export const mapApiDayToStandard = (apiDay: string | null): DayOfWeek => {
-	if (!apiDay) return "Unknown";
-
-	const dayMap: Record<string, DayOfWeek> = {
-		mondays: "Monday",
-		tuesdays: "Tuesday",
-		wednesdays: "Wednesday",
-		thursdays: "Thursday",
-		fridays: "Friday",
-		saturdays: "Saturday",
-		sundays: "Sunday",
-	};
-
-	return dayMap[apiDay.toLowerCase()] || "Unknown";
-};
-
 // Sort function for days of the week
 export const sortDays = (a: DayOfWeek, b: DayOfWeek): number => {
 	const order: Record<DayOfWeek, number> = {
@@ -106,12 +90,63 @@ export const sortDays = (a: DayOfWeek, b: DayOfWeek): number => {
 	return order[a] - order[b];
 };
 
-// Sort function for anime by airing time
-export const sortAnimeByTime = (a: AnimeData, b: AnimeData): number => {
-	const timeA = a.broadcast.time || "99:99";
-	const timeB = b.broadcast.time || "99:99";
+// Weekly calendar from get_weekly_schedule. Broadcast times are converted to
+// local time (DST aware) in Rust, so no timezone math happens here.
+interface ScheduleEntry {
+	anime_id: number;
+	title: string;
+	image_url: string;
+	status: string;
+	episode: number;
+	airs_at: string;
+	local_time: string;
+	jst_time: string;
+}
+
+interface ScheduleDay {
+	date: string;
+	weekday: string;
+	entries: ScheduleEntry[];
+}
 
-	return timeA.localeCompare(timeB);
+interface WeeklySchedule {
+	week_start: string;
+	days: ScheduleDay[];
+	unscheduled: { anime_id: number; title: string; status: string }[];
+}
+
+// Prefer the full anime data when the page has it, the schedule entry otherwise
+const toScheduledAnime = (
+	anime: AnimeData | undefined,
+	animeId: number,
+	title: string,
+	imageUrl: string,
+	localTime: string | null
+): AnimeData => {
+	const base =
+		anime ??
+		({
+			mal_id: animeId,
+			title,
+			type: null,
+			score: null,
+			studios: [],
+			images: {
+				jpg: { image_url: imageUrl },
+				webp: { image_url: imageUrl },
+			},
+		} as unknown as AnimeData);
+
+	return {
+		...base,
+		broadcast: {
+			day: null,
+			string: null,
+			...base.broadcast,
+			time: localTime,
+			timezone: Intl.DateTimeFormat().resolvedOptions().timeZone,
+		},
+	};
 };
 
 const AnimeScheduleView: React.FC<AnimeScheduleViewProps> = ({
@@ -123,7 +158,29 @@ const AnimeScheduleView: React.FC<AnimeScheduleViewProps> = ({
 }) => {
 	const [timeFormat, setTimeFormat] = useState<TimeFormat>("24h");
 
-	// Organize anime by day
+	const [schedule, setSchedule] = useState<WeeklySchedule | null>(null);
+	const [scheduleLoading, setScheduleLoading] = useState(true);
+
+	useEffect(() => {
+		let cancelled = false;
+		setScheduleLoading(true);
+		invoke<WeeklySchedule>("get_weekly_schedule", {})
+			.then((result) => {
+				if (!cancelled) setSchedule(result);
+			})
+			.catch((error) => {
+				console.error("Failed to load weekly schedule:", error);
+				if (!cancelled) setSchedule(null);
+			})
+			.finally(() => {
+				if (!cancelled) setScheduleLoading(false);
+			});
+		return () => {
+			cancelled = true;
+		};
+	}, []);
+
+	// Organize anime by the local day they air on this week
 	const animeByDay = useMemo(() => {
 		const result: Record<DayOfWeek, AnimeData[]> = {
 			Monday: [],
@@ -135,29 +192,48 @@ const AnimeScheduleView: React.FC<AnimeScheduleViewProps> = ({
 			Sunday: [],
 			Unknown: [],
 		};
-
-		animeList.forEach((anime) => {
-			const day = mapApiDayToStandard(anime.broadcast?.day);
-			if (result[day]) {
-				result[day].push(anime);
-			} else {
-				result.Unknown.push(anime);
-			}
+		if (!schedule) return result;
+
+		const animeById = new Map(animeList.map((anime) => [anime.mal_id, anime]));
+
+		// Entries are already sorted by broadcast time
+		schedule.days.forEach((day) => {
+			const dayName = day.weekday as DayOfWeek;
+			if (!result[dayName]) return;
+			day.entries.forEach((entry) => {
+				result[dayName].push(
+					toScheduledAnime(
+						animeById.get(entry.anime_id),
+						entry.anime_id,
+						entry.title,
+						entry.image_url,
+						entry.local_time
+					)
+				);
+			});
 		});
 
-		// Sort anime in each day by airing time
-		Object.keys(result).forEach((day) => {
-			result[day as DayOfWeek].sort(sortAnimeByTime);
+		schedule.unscheduled.forEach((entry) => {
+			const anime = animeById.get(entry.anime_id);
+			result.Unknown.push(
+				toScheduledAnime(
+					anime,
+					entry.anime_id,
+					entry.title,
+					anime?.images?.jpg?.image_url ?? "",
+					null
+				)
+			);
 		});
 
 		return result;
-	}, [animeList]);
+	}, [schedule, animeList]);
 
 	const hasScheduleData = useMemo(() => {
 		return Object.values(animeByDay).some((animeList) => animeList.length > 0);
 	}, [animeByDay]);
 
-	if (isLoading) {
+	if (isLoading || scheduleLoading) {
 		return (
 			<LoadingState theme={theme}>
 				<LoadingSpinner theme={theme} />
@@ -174,8 +250,8 @@ const AnimeScheduleView: React.FC<AnimeScheduleViewProps> = ({
 				</NoAnimeIcon>
 				<NoAnimeTitle>No Schedule Information</NoAnimeTitle>
 				<NoAnimeDescription theme={theme}>
-					There is no airing schedule information available for the selected
-					anime.
+					None of the anime you are watching or planning to watch air this
+					week.
 				</NoAnimeDescription>
 			</NoAnimeMessage>
 		);
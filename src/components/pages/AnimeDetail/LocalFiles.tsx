@@ -0,0 +1,163 @@
+import { useEffect } from "react";
+import styled from "@emotion/styled";
+import { motion } from "framer-motion";
+import { useQueryClient } from "@tanstack/react-query";
+import { listen } from "@tauri-apps/api/event";
+import { AlertTriangle, CheckCircle, HardDrive } from "lucide-react";
+import { AppTheme } from "../../../themes/themeTypes";
+import { useLibraryFiles } from "../../../hooks/useAnime";
+import { LibraryFile } from "../../../types/library";
+
+interface LocalFilesProps {
+	animeId: number;
+	theme: AppTheme;
+}
+
+const FilesSection = styled(motion.div)`
+	margin-top: 24px;
+`;
+
+const SectionTitle = styled(motion.h3)`
+	margin: 0 0 12px 0;
+	font-size: 20px;
+	font-weight: 600;
+	display: flex;
+	align-items: center;
+	gap: 8px;
+
+	&::after {
+		content: "";
+		height: 2px;
+		flex: 1;
+		background: linear-gradient(
+			90deg,
+			${(props: { theme: AppTheme }) => props.theme.colors.primary}50 0%,
+			transparent 100%
+		);
+	}
+`;
+
+const FileList = styled.div`
+	display: flex;
+	flex-direction: column;
+	gap: 8px;
+`;
+
+const FileRow = styled.div<{ theme: AppTheme; mismatch: boolean }>`
+	display: flex;
+	align-items: center;
+	gap: 12px;
+	padding: 10px 16px;
+	border-radius: 8px;
+	background-color: ${(props) => props.theme.colors.surface};
+	border-left: 3px solid
+		${(props) =>
+			props.mismatch
+				? props.theme.colors.error || "#f44336"
+				: props.theme.colors.primary};
+`;
+
+const EpisodeLabel = styled.span`
+	font-weight: 600;
+	min-width: 48px;
+`;
+
+const FileName = styled.span`
+	flex: 1;
+	overflow: hidden;
+	text-overflow: ellipsis;
+	white-space: nowrap;
+	font-size: 14px;
+`;
+
+const StatusFlag = styled.span<{ color: string }>`
+	display: inline-flex;
+	align-items: center;
+	gap: 4px;
+	padding: 2px 10px;
+	border-radius: 12px;
+	font-size: 12px;
+	font-weight: 500;
+	color: ${(props) => props.color};
+	background-color: ${(props) => props.color}20;
+	white-space: nowrap;
+`;
+
+function VerifyFlag({ file, theme }: { file: LibraryFile; theme: AppTheme }) {
+	switch (file.verify_status) {
+		case "mismatch":
+			return (
+				<StatusFlag
+					color={theme.colors.error || "#f44336"}
+					title={`CRC32 ${file.crc32} does not match ${file.parsed.checksum}`}
+				>
+					<AlertTriangle size={14} />
+					Checksum mismatch
+				</StatusFlag>
+			);
+		case "error":
+			return (
+				<StatusFlag color={theme.colors.warning || "#ff9800"}>
+					<AlertTriangle size={14} />
+					Unreadable
+				</StatusFlag>
+			);
+		case "ok":
+			return (
+				<StatusFlag color={theme.colors.success || "#4caf50"}>
+					<CheckCircle size={14} />
+					Verified
+				</StatusFlag>
+			);
+		default:
+			return null;
+	}
+}
+
+export function LocalFiles({ animeId, theme }: LocalFilesProps) {
+	const queryClient = useQueryClient();
+	const { data: files } = useLibraryFiles(animeId);
+
+	// Verification runs in the background; refresh the flags when it ends
+	useEffect(() => {
+		const unlisten = listen("library://verify-finished", () => {
+			queryClient.invalidateQueries({ queryKey: ["libraryFiles"] });
+		});
+		return () => {
+			unlisten.then((stop) => stop());
+		};
+	}, [queryClient]);
+
+	if (!files || files.length === 0) {
+		return null;
+	}
+
+	return (
+		<FilesSection
+			initial={{ opacity: 0, y: 20 }}
+			animate={{ opacity: 1, y: 0 }}
+			transition={{ delay: 0.6 }}
+		>
+			<SectionTitle theme={theme}>
+				<HardDrive size={20} />
+				Local Files
+			</SectionTitle>
+			<FileList>
+				{files.map((file) => (
+					<FileRow
+						key={file.path}
+						theme={theme}
+						mismatch={file.verify_status === "mismatch"}
+						title={file.path}
+					>
+						<EpisodeLabel>
+							{file.episode !== null ? `Ep ${file.episode}` : "—"}
+						</EpisodeLabel>
+						<FileName>{file.file_name}</FileName>
+						<VerifyFlag file={file} theme={theme} />
+					</FileRow>
+				))}
+			</FileList>
+		</FilesSection>
+	);
+}
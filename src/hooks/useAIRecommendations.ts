@@ -1,7 +1,9 @@
 import { useState, useCallback } from "react";
+import { useQuery } from "@tanstack/react-query";
 import { useUserAnimeList } from "./useAnime";
 import {
 	generateAIRecommendations,
+	getAnimeAIRecommendations,
 	getCachedAIRecommendations,
 	clearCachedAIRecommendations,
 	AIRecommendationResult,
@@ -84,3 +86,14 @@ export function useAIRecommendations() {
 		hasRecommendations: recommendations !== null,
 	};
 }
+
+/**
+ * Recommendations seeded by a single anime
+ */
+export function useAnimeAIRecommendations(animeId: number, limit?: number) {
+	return useQuery({
+		queryKey: ["aiRecommendations", "anime", animeId, limit],
+		queryFn: () => getAnimeAIRecommendations(animeId, limit),
+		enabled: !!animeId,
+	});
+}
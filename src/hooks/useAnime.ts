@@ -1,7 +1,17 @@
 import { useQuery, useMutation, useQueryClient } from "@tanstack/react-query";
+import { invoke } from "@tauri-apps/api/core";
 import { jikanApi } from "../services/jikanApi";
 import { animeDatabase } from "../services/animeDatabase";
-import { UserAnimeData } from "../types/anime";
+import {
+	CompletionStats,
+	ScoreDistribution,
+	SeasonalChart,
+	StatsBreakdown,
+	StatsBreakdownKind,
+	UserAnimeData,
+	WatchTimeStats,
+} from "../types/anime";
+import { LibraryFile } from "../types/library";
 import { useError, handleAppError } from "../contexts/ErrorContext";
 
 export type AnimeSearchFilters = {
@@ -62,6 +72,16 @@ export function useSeasonalAnime(year: number, season: string, page = 1) {
 	});
 }
 
+// Full seasonal chart from the backend, paginated and cached per season
+export function useSeasonalChart(year: number, season: string) {
+	return useQuery({
+		queryKey: ["seasonalChart", year, season],
+		queryFn: () =>
+			invoke<SeasonalChart>("get_seasonal_anime", { year, season }),
+		staleTime: 60 * 60 * 1000, // 1 hour, the backend keeps its own cache
+	});
+}
+
 export function useUserAnimeList(status?: UserAnimeData["status"]) {
 	return useQuery({
 		queryKey: ["userAnimeList", status],
@@ -69,6 +89,46 @@ export function useUserAnimeList(status?: UserAnimeData["status"]) {
 	});
 }
 
+// Statistics are keyed under userAnimeList so list edits refresh them too
+export function useListStats(breakdown: StatsBreakdownKind) {
+	const watchTime = useQuery({
+		queryKey: ["userAnimeList", "stats", "watchTime"],
+		queryFn: () => invoke<WatchTimeStats>("get_watch_time_stats"),
+	});
+	const scores = useQuery({
+		queryKey: ["userAnimeList", "stats", "scores"],
+		queryFn: () => invoke<ScoreDistribution>("get_score_distribution"),
+	});
+	const breakdownQuery = useQuery({
+		queryKey: ["userAnimeList", "stats", "breakdown", breakdown],
+		queryFn: () =>
+			invoke<StatsBreakdown>("get_stats_breakdown", { kind: breakdown }),
+		// Keep the previous breakdown on screen while another kind loads
+		placeholderData: (previous) => previous,
+	});
+	const completion = useQuery({
+		queryKey: ["userAnimeList", "stats", "completion"],
+		queryFn: () => invoke<CompletionStats>("get_completion_stats"),
+	});
+
+	return {
+		watchTime: watchTime.data,
+		scores: scores.data,
+		breakdown: breakdownQuery.data,
+		completion: completion.data,
+		isLoading:
+			watchTime.isLoading ||
+			scores.isLoading ||
+			breakdownQuery.isLoading ||
+			completion.isLoading,
+		error:
+			watchTime.error ||
+			scores.error ||
+			breakdownQuery.error ||
+			completion.error,
+	};
+}
+
 export function useUserAnimeDetails(animeId: number) {
 	return useQuery({
 		queryKey: ["userAnime", animeId],
@@ -77,6 +137,15 @@ export function useUserAnimeDetails(animeId: number) {
 	});
 }
 
+// Local files linked to an anime, with their verification status
+export function useLibraryFiles(animeId: number) {
+	return useQuery({
+		queryKey: ["libraryFiles", animeId],
+		queryFn: () => invoke<LibraryFile[]>("list_library_files", { animeId }),
+		enabled: !!animeId,
+	});
+}
+
 export function useAddAnime() {
 	const queryClient = useQueryClient();
 	const { setError } = useError();
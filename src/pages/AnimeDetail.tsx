@@ -25,6 +25,7 @@ import {
 	Notes,
 	ActionButtons as ActionButtonsComponent,
 } from "../components/pages/AnimeDetail/UserProgress";
+import { LocalFiles } from "../components/pages/AnimeDetail/LocalFiles";
 
 interface AnimeDetailProps {
 	animeId: number;
@@ -625,6 +626,8 @@ export function AnimeDetail({ animeId, onBack }: AnimeDetailProps) {
 				onToggleFavorite={handleToggleFavorite}
 			/>
 
+			<LocalFiles animeId={animeId} theme={theme} />
+
 			{userAnime.favorite && (
 				<>
 					<Notes
@@ -5,7 +5,8 @@ import { themes } from "../themes/themes";
 import { motion } from "framer-motion";
 import { AnimeCard } from "../components/AnimeCard";
 import { Button } from "../components/ui/Button";
-import { useSeasonalAnime } from "../hooks/useAnime";
+import { useSeasonalChart } from "../hooks/useAnime";
+import { SeasonalClassification } from "../types/anime";
 import { Card } from "../components/ui/Card";
 import { AppTheme } from "../themes/themeTypes";
 import {
@@ -35,6 +36,14 @@ interface SeasonalAnimePageProps {
 type Season = "winter" | "spring" | "summer" | "fall";
 type ViewMode = "grid" | "list" | "schedule";
 type ScheduleLayout = "vertical" | "horizontal" | "timeline";
+type AiringFilter = "all" | SeasonalClassification;
+
+const AIRING_FILTERS: { value: AiringFilter; label: string }[] = [
+	{ value: "all", label: "All" },
+	{ value: "new", label: "New" },
+	{ value: "continuing", label: "Continuing" },
+	{ value: "leftover", label: "Leftover" },
+];
 
 // Helper function to get the current season
 const getCurrentSeason = (): Season => {
@@ -310,22 +319,34 @@ export function SeasonalAnimePage({ onAnimeSelect }: SeasonalAnimePageProps) {
 	const [scheduleLayout, setScheduleLayout] =
 		useState<ScheduleLayout>("horizontal");
 	const [searchQuery, setSearchQuery] = useState("");
+	const [airingFilter, setAiringFilter] = useState<AiringFilter>("all");
 
 	// Fetch seasonal anime data
 	const {
-		data: seasonalData,
+		data: chart,
 		isLoading,
 		error,
 		refetch,
-	} = useSeasonalAnime(year, season);
+	} = useSeasonalChart(year, season);
+	const seasonalData = chart
+		? { data: chart.entries.map((entry) => entry.anime) }
+		: undefined;
 
-	// Filter anime based on search query
+	// Filter anime based on new/continuing/leftover and the search query
 	const filteredAnime =
-		seasonalData?.data?.filter(
-			(anime) =>
-				anime.title?.toLowerCase().includes(searchQuery.toLowerCase()) ||
-				anime.title_english?.toLowerCase()?.includes(searchQuery.toLowerCase())
-		) || [];
+		chart?.entries
+			.filter(
+				(entry) =>
+					airingFilter === "all" || entry.classification === airingFilter
+			)
+			.map((entry) => entry.anime)
+			.filter(
+				(anime) =>
+					anime.title?.toLowerCase().includes(searchQuery.toLowerCase()) ||
+					anime.title_english
+						?.toLowerCase()
+						?.includes(searchQuery.toLowerCase())
+			) || [];
 
 	// Change season
 	const changeSeason = (newSeason: Season) => {
@@ -654,6 +675,18 @@ export function SeasonalAnimePage({ onAnimeSelect }: SeasonalAnimePageProps) {
 							debounceMs={300}
 						/>
 					</SearchContainer>
+					<ViewControls>
+						{AIRING_FILTERS.map((filter) => (
+							<Button
+								key={filter.value}
+								variant={airingFilter === filter.value ? "primary" : "outline"}
+								onClick={() => setAiringFilter(filter.value)}
+								size="small"
+							>
+								{filter.label}
+							</Button>
+						))}
+					</ViewControls>
 				</FilterSection>
 			)}
 
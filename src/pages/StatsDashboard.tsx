@@ -1,12 +1,18 @@
-import { useState, useEffect } from "react";
+import { useState } from "react";
 import styled from "@emotion/styled";
 import { useTheme } from "../themes/ThemeProvider";
 import { themes } from "../themes/themes";
-import { useUserAnimeList } from "../hooks/useAnime";
-import { BarChart2, PieChart as PieChartIcon, Star } from "lucide-react";
+import { useListStats } from "../hooks/useAnime";
+import {
+	BarChart2,
+	CalendarCheck,
+	Clock,
+	PieChart as PieChartIcon,
+	Star,
+} from "lucide-react";
 import { Card } from "../components/ui/Card";
 import { motion } from "framer-motion";
-import { UserAnimeData } from "../types/anime";
+import { StatsBreakdownKind, UserAnimeData } from "../types/anime";
 import { BarChart } from "../components/ui/BarChart";
 import { PieChart } from "../components/ui/PieChart";
 
@@ -14,22 +20,20 @@ interface StatsDashboardProps {
 	onBack: () => void;
 }
 
-// Statistical data model
-interface AnimeStats {
-	totalAnime: number;
-	totalEpisodes: number;
-	averageScore: number;
-	statusDistribution: {
-		watching: number;
-		completed: number;
-		plan_to_watch: number;
-		on_hold: number;
-		dropped: number;
-	};
-	scoreDistribution: {
-		[key: number]: number;
-	};
-}
+const STATUS_ORDER: UserAnimeData["status"][] = [
+	"watching",
+	"completed",
+	"plan_to_watch",
+	"on_hold",
+	"dropped",
+];
+
+const BREAKDOWN_KINDS: { value: StatsBreakdownKind; label: string }[] = [
+	{ value: "genre", label: "Genres" },
+	{ value: "theme", label: "Themes" },
+	{ value: "studio", label: "Studios" },
+	{ value: "year", label: "Years" },
+];
 
 // Styled Components
 const Container = styled.div`
@@ -166,70 +170,36 @@ const ChartColumn = styled.div`
 	flex: 1;
 `;
 
-// Calculate statistics from anime list
-const calculateStats = (animeList: UserAnimeData[]): AnimeStats => {
-	// Initialize stats
-	const stats: AnimeStats = {
-		totalAnime: animeList.length,
-		totalEpisodes: 0,
-		averageScore: 0,
-		statusDistribution: {
-			watching: 0,
-			completed: 0,
-			plan_to_watch: 0,
-			on_hold: 0,
-			dropped: 0,
-		},
-		scoreDistribution: {
-			1: 0,
-			2: 0,
-			3: 0,
-			4: 0,
-			5: 0,
-			6: 0,
-			7: 0,
-			8: 0,
-			9: 0,
-			10: 0,
-		},
-	};
-
-	// Skip empty list
-	if (animeList.length === 0) return stats;
-
-	// Calculate episode count and score sum
-	let scoreSum = 0;
-	let scoredAnimeCount = 0;
-
-	animeList.forEach((anime) => {
-		// Count total episodes watched
-		stats.totalEpisodes += anime.progress;
-
-		// Count status
-		stats.statusDistribution[anime.status]++;
-
-		// Score distribution
-		if (anime.score > 0) {
-			stats.scoreDistribution[anime.score]++;
-			scoreSum += anime.score;
-			scoredAnimeCount++;
-		}
-	});
-
-	// Calculate average score (only for anime that have scores)
-	stats.averageScore =
-		scoredAnimeCount > 0
-			? parseFloat((scoreSum / scoredAnimeCount).toFixed(1))
-			: 0;
+const KindButtons = styled.div`
+	display: flex;
+	gap: 8px;
+`;
 
-	return stats;
-};
+const KindButton = styled.button<{ isActive: boolean; color: string }>`
+	padding: 4px 12px;
+	border-radius: 16px;
+	border: 1px solid ${(props) => props.color};
+	background: ${(props) => (props.isActive ? props.color : "transparent")};
+	color: ${(props) => (props.isActive ? "white" : props.color)};
+	font-size: 13px;
+	cursor: pointer;
+`;
 
 // Format number with commas
 const formatNumber = (num: number): string => {
 	return num.toString().replace(/\B(?=(\d{3})+(?!\d))/g, ",");
 };
 
+// Watch time as days and hours, e.g. "3d 4h"
+const formatWatchTime = (minutes: number): string => {
+	const hours = Math.round(minutes / 60);
+	const days = Math.floor(hours / 24);
+	return days > 0 ? `${days}d ${hours % 24}h` : `${hours}h`;
+};
+
+const formatOptional = (value: number | null, digits = 1): string =>
+	value === null ? "-" : value.toFixed(digits);
+
 // StatCard component for reusability
 const Stat = ({
 	title,
@@ -274,20 +244,16 @@ const Stat = ({
 export function StatsDashboard({}: StatsDashboardProps) {
 	const { currentTheme } = useTheme();
 	const theme = themes[currentTheme];
-	const { data: animeList, isLoading } = useUserAnimeList();
-	const [stats, setStats] = useState<AnimeStats | null>(null);
-
-	useEffect(() => {
-		if (animeList) {
-			setStats(calculateStats(animeList));
-		}
-	}, [animeList]);
+	const [breakdownKind, setBreakdownKind] =
+		useState<StatsBreakdownKind>("genre");
+	const { watchTime, scores, breakdown, completion, isLoading, error } =
+		useListStats(breakdownKind);
 
 	if (isLoading) {
 		return <div>Loading statistics...</div>;
 	}
 
-	if (!stats) {
+	if (error || !watchTime || !scores || !breakdown || !completion) {
 		return <div>No stats available</div>;
 	}
 
@@ -317,22 +283,39 @@ export function StatsDashboard({}: StatsDashboardProps) {
 		}
 	};
 
-	// Prepare data for bar charts
-	const statusChartData = Object.entries(stats.statusDistribution).map(
-		([status, count]) => ({
-			label: formatStatus(status),
-			value: count,
-			color: getStatusColor(status),
-		})
-	);
+	// Every status is shown, including ones without entries
+	const statusCounts = STATUS_ORDER.map((status) => ({
+		status,
+		count:
+			watchTime.by_status.find((row) => row.status === status)?.entries ?? 0,
+	}));
+	const totalAnime = watchTime.total_entries;
 
-	const scoreChartData = Object.entries(stats.scoreDistribution)
-		.filter(([_, count]) => count > 0)
-		.map(([score, count]) => ({
-			label: score,
-			value: count,
-			color: theme.colors.primary,
-		}));
+	// Prepare data for bar charts
+	const statusChartData = statusCounts.map(({ status, count }) => ({
+		label: formatStatus(status),
+		value: count,
+		color: getStatusColor(status),
+	}));
+
+	const scoreBuckets = scores.buckets.filter((bucket) => bucket.count > 0);
+	const scoreChartData = scoreBuckets.map((bucket) => ({
+		label: bucket.score.toString(),
+		value: bucket.count,
+		color: theme.colors.primary,
+	}));
+
+	const breakdownChartData = breakdown.rows.slice(0, 10).map((row) => ({
+		label: row.name,
+		value: row.count,
+		color: theme.colors.primary,
+	}));
+
+	const completionChartData = completion.buckets.map((bucket) => ({
+		label: bucket.label,
+		value: bucket.count,
+		color: theme.colors.success || theme.colors.primary,
+	}));
 
 	return (
 		<Container>
@@ -346,23 +329,37 @@ export function StatsDashboard({}: StatsDashboardProps) {
 			<StatsGrid>
 				<Stat
 					title="Total Anime"
-					value={formatNumber(stats.totalAnime)}
+					value={formatNumber(totalAnime)}
 					icon={<BarChart2 size={24} />}
 					theme={theme}
 				/>
 
 				<Stat
 					title="Episodes Watched"
-					value={formatNumber(stats.totalEpisodes)}
+					value={formatNumber(watchTime.total_episodes)}
 					icon={<PieChartIcon size={24} />}
 					theme={theme}
 				/>
 
+				<Stat
+					title="Watch Time"
+					value={formatWatchTime(watchTime.total_minutes)}
+					icon={<Clock size={24} />}
+					footer={
+						watchTime.episodes_with_known_length < watchTime.total_episodes
+							? "Some episode lengths are estimated"
+							: undefined
+					}
+					theme={theme}
+				/>
+
 				<Stat
 					title="Average Score"
-					value={stats.averageScore.toString()}
+					value={formatOptional(scores.mean)}
 					icon={<Star size={24} />}
-					footer="Out of 10"
+					footer={`Out of 10 · median ${formatOptional(
+						scores.median
+					)} · std dev ${formatOptional(scores.std_dev, 2)}`}
 					theme={theme}
 				/>
 			</StatsGrid>
@@ -381,7 +378,7 @@ export function StatsDashboard({}: StatsDashboardProps) {
 				</StatHeader>
 
 				<StatusGrid>
-					{Object.entries(stats.statusDistribution).map(([status, count]) => (
+					{statusCounts.map(({ status, count }) => (
 						<StatusItem key={status}>
 							<StatusLabel>
 								<StatusDot color={getStatusColor(status)} />
@@ -389,7 +386,7 @@ export function StatsDashboard({}: StatsDashboardProps) {
 							</StatusLabel>
 							<StatusValue>{count}</StatusValue>
 							<ProgressBar
-								width={`${(count / stats.totalAnime) * 100}%`}
+								width={`${totalAnime > 0 ? (count / totalAnime) * 100 : 0}%`}
 								color={getStatusColor(status)}
 							/>
 						</StatusItem>
@@ -431,22 +428,94 @@ export function StatsDashboard({}: StatsDashboardProps) {
 						gridTemplateColumns: "repeat(auto-fill, minmax(60px, 1fr))",
 					}}
 				>
-					{Object.entries(stats.scoreDistribution)
-						.filter(([_, count]) => count > 0)
-						.map(([score, count]) => (
-							<StatusItem key={score}>
-								<StatusValue>{score}</StatusValue>
-								<StatusLabel>{count} anime</StatusLabel>
-								<ProgressBar
-									width={`${(count / stats.totalAnime) * 100}%`}
-									color={theme.colors.primary}
-								/>
-							</StatusItem>
-						))}
+					{scoreBuckets.map(({ score, count }) => (
+						<StatusItem key={score}>
+							<StatusValue>{score}</StatusValue>
+							<StatusLabel>{count} anime</StatusLabel>
+							<ProgressBar
+								width={`${(count / scores.scored) * 100}%`}
+								color={theme.colors.primary}
+							/>
+						</StatusItem>
+					))}
 				</StatusGrid>
 
 				<BarChart data={scoreChartData} height={200} animate={true} />
 			</StatCard>
+
+			<StatCard
+				initial={{ opacity: 0, y: 20 }}
+				animate={{ opacity: 1, y: 0 }}
+				transition={{ duration: 0.5, delay: 0.4 }}
+				style={{
+					marginTop: "24px",
+					background: theme.colors.surface,
+					border: `1px solid ${theme.colors.primary}20`,
+				}}
+			>
+				<StatHeader>
+					<StatTitle color={theme.colors.text}>
+						Top{" "}
+						{BREAKDOWN_KINDS.find((kind) => kind.value === breakdownKind)?.label}
+					</StatTitle>
+					<KindButtons>
+						{BREAKDOWN_KINDS.map((kind) => (
+							<KindButton
+								key={kind.value}
+								isActive={kind.value === breakdownKind}
+								color={theme.colors.primary}
+								onClick={() => setBreakdownKind(kind.value)}
+							>
+								{kind.label}
+							</KindButton>
+						))}
+					</KindButtons>
+				</StatHeader>
+
+				<BarChart data={breakdownChartData} height={240} animate={true} />
+
+				{breakdown.entries_without_metadata > 0 && (
+					<StatFooter color={theme.colors.textSecondary}>
+						{breakdown.entries_without_metadata} entries have no cached
+						details yet and are not counted
+					</StatFooter>
+				)}
+			</StatCard>
+
+			<StatCard
+				initial={{ opacity: 0, y: 20 }}
+				animate={{ opacity: 1, y: 0 }}
+				transition={{ duration: 0.5, delay: 0.5 }}
+				style={{
+					marginTop: "24px",
+					background: theme.colors.surface,
+					border: `1px solid ${theme.colors.primary}20`,
+				}}
+			>
+				<StatHeader>
+					<StatTitle color={theme.colors.text}>Time to Complete</StatTitle>
+					<StatIcon
+						style={{
+							backgroundColor: `${theme.colors.primary}20`,
+							color: theme.colors.primary,
+						}}
+					>
+						<CalendarCheck size={24} />
+					</StatIcon>
+				</StatHeader>
+
+				<BarChart data={completionChartData} height={200} animate={true} />
+
+				<StatFooter color={theme.colors.textSecondary}>
+					{completion.measured > 0
+						? `${completion.measured} completed anime · mean ${formatOptional(
+								completion.mean_days
+						  )} days · median ${formatOptional(
+								completion.median_days
+						  )} days`
+						: "Add start and end dates to completed anime to see this"}
+				</StatFooter>
+			</StatCard>
 		</Container>
 	);
 }
@@ -1,9 +1,4 @@
-// Remove unused imports
-// Define types for our cache statistics
-interface CacheStats {
-	sizeInBytes: number;
-	imageCount: number;
-}
+import { CacheStats } from "../types/cache";
 
 class ImageCacheService {
 	private cacheDir: string | null = null;
@@ -37,32 +32,20 @@ class ImageCacheService {
 	}
 
 	/**
-	 * Get a cached image URL if available, otherwise cache the image and return its URL
+	 * Get a cached image URL if available, otherwise cache the image and return its URL.
+	 * Rust downloads and stores the image and serves it over the animecache:// protocol.
 	 */
 	async getCachedImageUrl(imageUrl: string): Promise<string> {
 		await this.ensureInitialized();
 
-		if (!imageUrl || !this.cacheDir) return imageUrl;
-
-		const imageId = this.getImageId(imageUrl);
-		const imagePath = await window.__TAURI__.path.join(this.cacheDir, imageId);
+		if (!imageUrl) return imageUrl;
 
 		try {
-			// Check if the image exists in cache using Rust
-			const exists = await window.__TAURI__.invoke("file_exists", {
-				file_path: imagePath,
-				location: "Custom",
-				custom_path: "",
-			});
-
-			if (exists) {
-				// Return the cached image URL
-				return window.__TAURI__.tauri.convertFileSrc(imagePath);
-			} else {
-				// Download and cache the image
-				await this.downloadAndCacheImage(imageUrl, imagePath);
-				return window.__TAURI__.tauri.convertFileSrc(imagePath);
-			}
+			const cached = await window.__TAURI__.invoke<{ protocol_url: string }>(
+				"fetch_cached_image",
+				{ url: imageUrl }
+			);
+			return cached.protocol_url;
 		} catch (error) {
 			console.error("Error accessing cached image:", error);
 			// Fall back to original URL on error
@@ -70,61 +53,6 @@ class ImageCacheService {
 		}
 	}
 
-	/**
-	 * Download an image and save it to the cache
-	 */
-	private async downloadAndCacheImage(
-		imageUrl: string,
-		targetPath: string
-	): Promise<void> {
-		try {
-			// Download the image using fetch
-			const response = await fetch(imageUrl);
-			if (!response.ok) {
-				throw new Error(
-					`Failed to download image: ${response.status} ${response.statusText}`
-				);
-			}
-
-			// Convert to blob
-			const imageBlob = await response.blob();
-
-			// Convert blob to ArrayBuffer
-			const arrayBuffer = await imageBlob.arrayBuffer();
-			const uint8Array = new Uint8Array(arrayBuffer);
-
-			// Write to file using Rust
-			await window.__TAURI__.invoke("write_binary_file", {
-				path: targetPath,
-				contents: Array.from(uint8Array),
-			});
-
-			console.log("Image cached successfully:", imageUrl);
-		} catch (error) {
-			console.error("Failed to cache image:", error);
-			throw error;
-		}
-	}
-
-	/**
-	 * Generate a unique ID for an image URL
-	 */
-	private getImageId(url: string): string {
-		// Create a hash of the URL to use as the filename
-		let hash = 0;
-		for (let i = 0; i < url.length; i++) {
-			const char = url.charCodeAt(i);
-			hash = (hash << 5) - hash + char;
-			hash = hash & hash; // Convert to 32bit integer
-		}
-
-		// Extract file extension from URL if possible
-		const extension = url.split(".").pop()?.split("?")[0] || "jpg";
-
-		// Return the hashed filename with extension
-		return `${Math.abs(hash).toString(16)}.${extension}`;
-	}
-
 	/**
 	 * Clear the image cache
 	 */
@@ -156,17 +84,40 @@ class ImageCacheService {
 
 		try {
 			// Get cache stats using Rust
-			const stats = await window.__TAURI__.invoke<{
-				size_in_bytes: number;
-				image_count: number;
-			}>("get_cache_stats", {
+			const stats = await window.__TAURI__.invoke<any>("get_cache_stats", {
 				cache_path: this.cacheDir,
 			});
 
+			const mapFile = (file: any) =>
+				file && {
+					path: file.path,
+					category: file.category,
+					sizeInBytes: file.size_in_bytes,
+					modified: file.modified,
+				};
+
 			// Map from snake_case (Rust) to camelCase (TypeScript)
 			return {
 				sizeInBytes: stats.size_in_bytes,
 				imageCount: stats.image_count,
+				fileCount: stats.file_count,
+				sizeClasses: stats.size_classes.map((sizeClass: any) => ({
+					sizeClass: sizeClass.size_class,
+					fileCount: sizeClass.file_count,
+					sizeInBytes: sizeClass.size_in_bytes,
+				})),
+				categories: stats.categories.map((category: any) => ({
+					category: category.category,
+					paths: category.paths,
+					fileCount: category.file_count,
+					sizeInBytes: category.size_in_bytes,
+					error: category.error,
+				})),
+				oldestFile: mapFile(stats.oldest_file),
+				newestFile: mapFile(stats.newest_file),
+				largestFiles: stats.largest_files.map(mapFile),
+				freeSpaceBytes: stats.free_space_bytes,
+				totalSpaceBytes: stats.total_space_bytes,
 			};
 		} catch (error) {
 			console.error("Failed to get cache stats:", error);
@@ -211,8 +162,10 @@ class ImageCacheService {
 		if (!this.cacheDir) throw new Error("Cache directory not initialized");
 
 		try {
-			// Create zip in the documents directory using Rust
-			const documentsDir = await this.getDocumentsDir();
+			// Create zip in the configured backup folder, which the Rust sandbox allows
+			const backupDir = await window.__TAURI__.invoke<string>(
+				"get_backup_directory"
+			);
 			const timestamp = new Date().toISOString().replace(/[:.]/g, "-");
 			const zipName = `anitrack_image_cache_backup_${timestamp}.zip`;
 
@@ -221,7 +174,7 @@ class ImageCacheService {
 				"create_backup_zip",
 				{
 					source_dir: this.cacheDir,
-					destination_dir: documentsDir,
+					destination_dir: backupDir,
 					zip_name: zipName,
 				}
 			);
@@ -14,6 +14,7 @@ interface TauriUserAnime {
 	end_date?: string | null;
 	image_url: string;
 	title: string;
+	tags?: string[];
 }
 
 // Convert our React type to Tauri type
@@ -45,6 +46,7 @@ const convertFromTauriAnime = (anime: TauriUserAnime): UserAnimeData => {
 		end_date: anime.end_date || null,
 		image_url: anime.image_url,
 		title: anime.title,
+		tags: anime.tags ?? [],
 	};
 };
 
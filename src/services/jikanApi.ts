@@ -1,4 +1,5 @@
 import axios from "axios";
+import { invoke } from "@tauri-apps/api/core";
 import { AnimeData, JikanResponse } from "../types/anime";
 
 const BASE_URL = "https://api.jikan.moe/v4";
@@ -86,10 +87,16 @@ export const jikanApi = {
 		);
 	},
 
-	// Get anime by ID
+	// Get anime by ID; the full variant also lists related anime
 	getAnimeById: (id: number) => {
 		return executeWithRateLimit<JikanResponse<AnimeData>>(() =>
-			axios.get(`${BASE_URL}/anime/${id}`).then((response) => response.data)
+			axios.get(`${BASE_URL}/anime/${id}/full`).then((response) => {
+				// Keep a copy for offline features in the Rust backend
+				invoke("cache_anime_metadata", { entries: [response.data.data] }).catch(
+					(error) => console.error("Failed to cache anime metadata:", error)
+				);
+				return response.data;
+			})
 		);
 	},
 
@@ -118,9 +125,16 @@ export const jikanApi = {
 	// Get anime recommendations
 	getAnimeRecommendations: (id: number) => {
 		return executeWithRateLimit<JikanResponse<any>>(() =>
-			axios
-				.get(`${BASE_URL}/anime/${id}/recommendations`)
-				.then((response) => response.data)
+			axios.get(`${BASE_URL}/anime/${id}/recommendations`).then((response) => {
+				// Recommendation edges feed the offline recommender in the Rust backend
+				invoke("cache_anime_recommendations", {
+					animeId: id,
+					entries: response.data.data,
+				}).catch((error) =>
+					console.error("Failed to cache anime recommendations:", error)
+				);
+				return response.data;
+			})
 		);
 	},
 };
@@ -121,6 +121,23 @@ export interface JikanResponse<T> {
 	};
 }
 
+export type SeasonalClassification = "new" | "continuing" | "leftover";
+
+export interface SeasonalEntry {
+	classification: SeasonalClassification;
+	anime: AnimeData;
+}
+
+export interface SeasonalChart {
+	year: number;
+	season: "winter" | "spring" | "summer" | "fall";
+	start_date: string;
+	end_date: string;
+	fetched_at: number;
+	stale: boolean;
+	entries: SeasonalEntry[];
+}
+
 export interface UserAnimeData {
 	anime_id: number;
 	status: "watching" | "completed" | "on_hold" | "dropped" | "plan_to_watch";
@@ -132,6 +149,70 @@ export interface UserAnimeData {
 	end_date: string | null;
 	image_url: string;
 	title: string;
+	// User-defined tags, managed with set_anime_tags
+	tags?: string[];
+}
+
+// List statistics computed in SQL by the stats commands
+export interface StatusWatchTime {
+	status: UserAnimeData["status"];
+	entries: number;
+	minutes: number;
+	episodes: number;
+}
+
+export interface WatchTimeStats {
+	total_entries: number;
+	total_minutes: number;
+	total_episodes: number;
+	episodes_with_known_length: number;
+	by_status: StatusWatchTime[];
+}
+
+export interface ScoreDistribution {
+	// Always 10 buckets, scores 1 to 10
+	buckets: { score: number; count: number }[];
+	scored: number;
+	mean: number | null;
+	median: number | null;
+	std_dev: number | null;
+}
+
+export type StatsBreakdownKind = "genre" | "theme" | "studio" | "year";
+
+export interface StatsBreakdown {
+	kind: StatsBreakdownKind;
+	rows: {
+		name: string;
+		count: number;
+		completed: number;
+		mean_score: number | null;
+	}[];
+	entries_without_metadata: number;
+}
+
+export interface CompletionStats {
+	buckets: { label: string; count: number }[];
+	measured: number;
+	mean_days: number | null;
+	median_days: number | null;
+}
+
+// Results of the get_recommendations and get_anime_recommendations commands
+export interface RecommendationSignal {
+	kind: "related" | "genre" | "theme" | "demographic" | "studio" | "popularity";
+	label: string;
+	// Share of the final score, 0-100
+	contribution: number;
+}
+
+export interface Recommendation {
+	anime_id: number;
+	title: string;
+	image_url: string;
+	// 0-100
+	score: number;
+	signals: RecommendationSignal[];
 }
 
 // Common anime genres to use for filter options
@@ -4,6 +4,45 @@
 export interface CacheStats {
 	sizeInBytes: number;
 	imageCount: number;
+	fileCount?: number;
+	sizeClasses?: CacheSizeClassStats[];
+	categories?: CacheCategoryStats[];
+	oldestFile?: CacheFileSummary | null;
+	newestFile?: CacheFileSummary | null;
+	largestFiles?: CacheFileSummary[];
+	freeSpaceBytes?: number | null;
+	totalSpaceBytes?: number | null;
+}
+
+/**
+ * Disk usage of one storage category
+ */
+export interface CacheCategoryStats {
+	category: "covers" | "thumbnails" | "api_cache" | "exports" | "backups";
+	paths: string[];
+	fileCount: number;
+	sizeInBytes: number;
+	// Why the category could not be scanned, e.g. an unreachable backup folder
+	error: string | null;
+}
+
+/**
+ * Disk usage of one thumbnail size class in the image cache
+ */
+export interface CacheSizeClassStats {
+	sizeClass: string;
+	fileCount: number;
+	sizeInBytes: number;
+}
+
+/**
+ * A single file reported by the cache statistics
+ */
+export interface CacheFileSummary {
+	path: string;
+	category: string;
+	sizeInBytes: number;
+	modified: string | null;
 }
 
 /**
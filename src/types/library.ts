@@ -0,0 +1,41 @@
+/**
+ * Release details parsed from a library file name
+ */
+export interface ParsedRelease {
+	group: string | null;
+	title: string;
+	season: number | null;
+	episode: number | null;
+	resolution: string | null;
+	checksum: string | null;
+}
+
+/**
+ * A video file found in one of the library folders
+ */
+export interface LibraryFile {
+	path: string;
+	file_name: string;
+	size_in_bytes: number;
+	parsed: ParsedRelease;
+	anime_id: number | null;
+	episode: number | null;
+	match_score: number | null;
+	manual: boolean;
+	crc32: string | null;
+	ed2k: string | null;
+	// null until the file has been verified
+	verify_status: "ok" | "mismatch" | "computed" | "error" | null;
+}
+
+/**
+ * Payload of the library://verify-finished event
+ */
+export interface VerifySummary {
+	verified: number;
+	mismatched: number;
+	computed: number;
+	failed: number;
+	cancelled: boolean;
+	error: string | null;
+}